@@ -1,5 +1,182 @@
 
+use std::fmt;
+use std::ptr;
 use std::str;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+#[derive(Debug, PartialEq)]
+enum BufferError {
+    // The requested range ends past the end of the buffer.
+    OutOfRange { end: usize, len: usize },
+    // A record is longer than its one-byte length prefix can describe.
+    RecordTooLong { len: usize },
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BufferError::OutOfRange { end, len } => {
+                write!(f, "range ends at {} but the buffer is only {} bytes", end, len)
+            }
+            BufferError::RecordTooLong { len } => {
+                write!(f, "record of {} bytes is longer than the 255 byte limit", len)
+            }
+        }
+    }
+}
+
+// Writes the counter values 0..count into buffer[start..start + count].
+// Returns an error instead of panicking when the range doesn't fit.
+fn fill_counter(buffer: &mut [u8], start: usize, count: usize) -> Result<(), BufferError> {
+    let end = start.saturating_add(count);
+    let len = buffer.len();
+    let section = buffer
+        .get_mut(start..end)
+        .ok_or(BufferError::OutOfRange { end, len })?;
+    for (i, byte) in section.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    Ok(())
+}
+
+// Formats a buffer the way `hexdump -C` does: an offset column, 16 bytes per
+// line in hex, and an ASCII gutter where non-printable bytes show up as `.`.
+fn hexdump(buffer: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in buffer.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x}  ", line * 16));
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(byte) => out.push_str(&format!("{:02x} ", byte)),
+                None => out.push_str("   "),
+            }
+            if i == 7 {
+                out.push(' ');
+            }
+        }
+        out.push_str(" |");
+        for &byte in chunk {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                out.push(byte as char);
+            } else {
+                out.push('.');
+            }
+        }
+        out.push_str("|\n");
+    }
+    out
+}
+
+// Caesar-shifts the ASCII letters of `buffer` in place, keeping the case and
+// leaving every other byte untouched. Nothing is allocated, so this works on
+// fixed [0u8; N] buffers. Shifting by 26 - shift decrypts.
+fn caesar_encrypt_in_place(buffer: &mut [u8], shift: u8) {
+    let shift = shift % 26;
+    for byte in buffer.iter_mut() {
+        let base = match *byte {
+            b'A'..=b'Z' => b'A',
+            b'a'..=b'z' => b'a',
+            _ => continue,
+        };
+        *byte = (*byte - base + shift) % 26 + base;
+    }
+}
+
+// Rotates the slice left by n positions using three in-place reversals, so no
+// second buffer is needed. n wraps around the length, so over-rotating is fine.
+fn rotate_left<T>(slice: &mut [T], n: usize) {
+    if slice.is_empty() {
+        return;
+    }
+    let n = n % slice.len();
+    slice[..n].reverse();
+    slice[n..].reverse();
+    slice.reverse();
+}
+
+// Pushes `pushes` elements into a fresh Vec and records (len, capacity) after
+// each push, so the growth pattern can be inspected instead of eyeballed.
+fn record_capacity_growth(pushes: usize) -> Vec<(usize, usize)> {
+    let mut v: Vec<usize> = Vec::new();
+    let mut growth = Vec::with_capacity(pushes);
+    for i in 0..pushes {
+        v.push(i);
+        growth.push((v.len(), v.capacity()));
+    }
+    growth
+}
+
+// Writes each record as a one-byte length followed by its bytes and returns
+// how many bytes of the buffer were used.
+fn pack_records(buffer: &mut [u8], records: &[&[u8]]) -> Result<usize, BufferError> {
+    let mut pos = 0;
+    for record in records {
+        let len = u8::try_from(record.len())
+            .map_err(|_| BufferError::RecordTooLong { len: record.len() })?;
+        let end = pos + 1 + record.len();
+        if end > buffer.len() {
+            return Err(BufferError::OutOfRange { end, len: buffer.len() });
+        }
+        buffer[pos] = len;
+        buffer[pos + 1..end].copy_from_slice(record);
+        pos = end;
+    }
+    Ok(pos)
+}
+
+// Reads back what pack_records wrote. Pass only the bytes that were written:
+// every byte is taken as a length prefix, so trailing zeros read as empty
+// records. The returned records borrow from the buffer, nothing is copied.
+fn unpack_records(buffer: &[u8]) -> Result<Vec<&[u8]>, BufferError> {
+    let mut records = Vec::new();
+    let mut pos = 0;
+    while pos < buffer.len() {
+        let start = pos + 1;
+        let end = start + buffer[pos] as usize;
+        let record = buffer
+            .get(start..end)
+            .ok_or(BufferError::OutOfRange { end, len: buffer.len() })?;
+        records.push(record);
+        pos = end;
+    }
+    Ok(records)
+}
+
+// Reads a big-endian u32 from the first four bytes, or None if there aren't four.
+fn read_u32_be(slice: &[u8]) -> Option<u32> {
+    let bytes: [u8; 4] = slice.get(..4)?.try_into().ok()?;
+    Some(u32::from_be_bytes(bytes))
+}
+
+// Writes `value` big-endian into the first four bytes of the buffer.
+fn write_u32_be(buffer: &mut [u8], value: u32) -> Result<(), BufferError> {
+    let len = buffer.len();
+    let section = buffer
+        .get_mut(..4)
+        .ok_or(BufferError::OutOfRange { end: 4, len })?;
+    section.copy_from_slice(&value.to_be_bytes());
+    Ok(())
+}
+
+// Overwrites the buffer with zeros using volatile writes, which the compiler
+// isn't allowed to drop even though the buffer is never read again. This is
+// best effort, not a cryptographic guarantee: copies the program made earlier
+// (moves, registers, swapped-out pages) are not touched.
+fn zeroize(buffer: &mut [u8]) {
+    for byte in buffer.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned &mut u8 for the whole write.
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+// Splits at the first `delim`, returning the bytes before and after it with
+// the delimiter itself left out, or None if the delimiter never appears.
+fn split_at_delimiter(buffer: &[u8], delim: u8) -> Option<(&[u8], &[u8])> {
+    let pos = buffer.iter().position(|&byte| byte == delim)?;
+    Some((&buffer[..pos], &buffer[pos + 1..]))
+}
+
 fn main() {
     
 // Rust has three types for representing a sequence of values in memory:
@@ -53,13 +230,25 @@ buffer[..message.len()].copy_from_slice(message);
 
 println!("{}", str::from_utf8(&buffer[..buffer.len()]).unwrap());
 
-for i in 0..10 {
-    buffer[message.len() + i] = i as u8;
-}
+fill_counter(&mut buffer, message.len(), 10).unwrap();
 
 println!("Counter values {:?}", &buffer[message.len()..message.len() + 10]);
 
-println!("{:?}", &buffer);
+// Filling past the end of the buffer is reported as an error, not a panic.
+assert_eq!(
+    fill_counter(&mut buffer, 1020, 10),
+    Err(BufferError::OutOfRange { end: 1030, len: 1024 })
+);
+if let Err(e) = fill_counter(&mut buffer, 1020, 10) {
+    println!("{}", e);
+}
+
+print!("{}", hexdump(&buffer[..message.len() + 10]));
+
+assert_eq!(
+    hexdump(b"Hi!\n"),
+    "00000000  48 69 21 0a                                       |Hi!.|\n"
+);
 
 println!("{}", str::from_utf8(&buffer).unwrap());
 
@@ -95,6 +284,62 @@ let shift = 3;
 //let encrypted = str::from_utf8(&buffer[message.len() + 10..message.len() + 10 + secret_message.len()]).unwrap();
 //println!("{}", encrypted);
 
+// The same cipher can run directly on the buffer without allocating a String.
+let start = message.len() + 10;
+let end = start + secret_message.len();
+buffer[start..end].copy_from_slice(secret_message);
+
+caesar_encrypt_in_place(&mut buffer[start..end], shift);
+println!("{}", str::from_utf8(&buffer[start..end]).unwrap());
+
+caesar_encrypt_in_place(&mut buffer[start..end], 26 - shift);
+assert_eq!(&buffer[start..end], secret_message);
+
+// Wipe the secret once it's no longer needed.
+zeroize(&mut buffer[start..end]);
+assert!(buffer[start..end].iter().all(|&byte| byte == 0));
+
+// Several records can share one fixed buffer if each one is prefixed by its length.
+let mut records_buffer = [0u8; 16];
+let records: [&[u8]; 3] = [b"abc", b"", b"rust"];
+let written = pack_records(&mut records_buffer, &records).unwrap();
+assert_eq!(written, 10);
+assert_eq!(&records_buffer[..written], b"\x03abc\x00\x04rust");
+assert_eq!(unpack_records(&records_buffer[..written]).unwrap(), records);
+// The last record claims 4 bytes but only 2 are left.
+assert_eq!(
+    unpack_records(&records_buffer[..8]),
+    Err(BufferError::OutOfRange { end: 10, len: 8 })
+);
+assert_eq!(
+    pack_records(&mut records_buffer[..5], &records),
+    Err(BufferError::OutOfRange { end: 10, len: 5 })
+);
+assert_eq!(
+    pack_records(&mut buffer, &[&[0u8; 256]]),
+    Err(BufferError::RecordTooLong { len: 256 })
+);
+
+// Fixed-width integers are written byte by byte, most significant byte first.
+let mut word = [0u8; 4];
+write_u32_be(&mut word, 0xDEADBEEF).unwrap();
+assert_eq!(word, [0xDE, 0xAD, 0xBE, 0xEF]);
+assert_eq!(read_u32_be(&word), Some(0xDEADBEEF));
+assert_eq!(read_u32_be(&word[..3]), None);
+assert_eq!(
+    write_u32_be(&mut word[..3], 1),
+    Err(BufferError::OutOfRange { end: 4, len: 3 })
+);
+
+// A header and payload can be told apart by a delimiter byte.
+assert_eq!(
+    split_at_delimiter(b"Hello:rust buffer", b':'),
+    Some((&b"Hello"[..], &b"rust buffer"[..]))
+);
+assert_eq!(split_at_delimiter(b":payload", b':'), Some((&b""[..], &b"payload"[..])));
+assert_eq!(split_at_delimiter(b"header:", b':'), Some((&b"header"[..], &b""[..])));
+assert_eq!(split_at_delimiter(message, b':'), None);
+
 //All methods are on slices
 // The useful methods you’d like to see on arrays—iterating over elements, searching, sorting, filling, filtering, and so on—are all provided as methods on slices, not arrays. But Rust implicitly converts a reference to an array to a slice when searching for methods, so you can call any slice method on an array directly:
 
@@ -102,6 +347,17 @@ let mut chaos = [3, 5, 4, 1, 2];
 chaos.sort();
 assert_eq!(chaos, [1,2,3,4,5]);
 
+// rotate_left is built only from the slice method reverse.
+rotate_left(&mut chaos, 2);
+assert_eq!(chaos, [3,4,5,1,2]);
+rotate_left(&mut chaos, 0);
+assert_eq!(chaos, [3,4,5,1,2]);
+let len = chaos.len();
+rotate_left(&mut chaos, len);
+assert_eq!(chaos, [3,4,5,1,2]);
+rotate_left(&mut chaos, 8);
+assert_eq!(chaos, [1,2,3,4,5]);
+
 // The useful methods you’d like to see on arrays—iterating over elements, searching, sorting, filling, filtering, and so on—are all provided as methods on slices, not arrays. But Rust implicitly converts a reference to an array to a slice when searching for methods, so you can call any slice method on an array directly
 
 // A vector Vec<T> is a resizable array of elements of type T, allocated on the heap.
@@ -154,6 +410,13 @@ println!("{}", v.capacity()); // capacity is 4, len is 3
 let vr = &v;
 println!("{:p}", vr);
 
+// record_capacity_growth shows the same thing for any number of pushes.
+// The capacity never shrinks and is always at least the length.
+let growth = record_capacity_growth(20);
+println!("(len, capacity) {:?}", growth);
+assert!(growth.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+assert!(growth.iter().all(|&(len, capacity)| len <= capacity));
+
 
 let mut v = vec![10, 20, 30, 40, 50];
 v.insert(3, 35);
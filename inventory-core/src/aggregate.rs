@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use crate::{normalize, Collection, Item};
+
+// Totals over groups of items: how many items fall in each category, say,
+// how much of them there is and what it is worth. Quantities are added up
+// whatever their unit, so they mean most when a group shares one.
+
+// What items are grouped by.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GroupBy {
+    Category,
+    Unit,
+    // By normalized name. Items without the attribute make a group of their
+    // own.
+    Attribute(String),
+}
+
+impl GroupBy {
+    // category and unit group by those; any other name by that attribute.
+    pub fn named(name: &str) -> GroupBy {
+        match normalize(name).as_str() {
+            "category" => GroupBy::Category,
+            "unit" => GroupBy::Unit,
+            name => GroupBy::Attribute(name.to_string()),
+        }
+    }
+
+    // None for an item without the attribute grouped by.
+    fn key(&self, item: &Item) -> Option<String> {
+        match self {
+            GroupBy::Category => Some(item.category.to_string()),
+            GroupBy::Unit => Some(item.unit.to_string()),
+            GroupBy::Attribute(name) => item.attribute(name).map(str::to_string),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Group {
+    // As the first item in the group by name spells it; None for the items
+    // without the attribute grouped by, and for the grand totals.
+    pub key: Option<String>,
+    pub items: usize,
+    pub quantity: u64,
+    // In cents.
+    pub value: u64,
+}
+
+impl Group {
+    fn add(&mut self, item: &Item) {
+        self.items += 1;
+        self.quantity += u64::from(item.quantity);
+        self.value += item.value();
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    // By key, ignoring case, with the items without one last.
+    pub groups: Vec<Group>,
+    pub total: Group,
+}
+
+impl Collection {
+    // Groups the items by `by`. Keys that differ only in case, like Produce
+    // and produce, are one group.
+    pub fn summarize(&self, by: &GroupBy) -> Summary {
+        let mut items: Vec<&Item> = self.iter().collect();
+        items.sort_by_key(|item| (&item.name, &item.sku));
+
+        let mut groups: BTreeMap<(bool, String), Group> = BTreeMap::new();
+        let mut total = Group::default();
+        for item in items {
+            let key = by.key(item);
+            let sort_key = (key.is_none(), key.as_deref().map(normalize).unwrap_or_default());
+            groups.entry(sort_key).or_insert_with(|| Group { key, ..Group::default() }).add(item);
+            total.add(item);
+        }
+        Summary { groups: groups.into_values().collect(), total }
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use crate::{Collection, Item};
+
+// Stock events, told to whoever subscribed to the collection as they happen:
+// an item was created, fell to its low stock threshold, or ran out. They
+// come from record_audit, so every change to an item is covered, wherever it
+// was made from.
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "event", rename_all = "snake_case"))]
+pub enum StockEvent {
+    Created { sku: String, name: String, quantity: u32 },
+    // Only when the quantity goes from above the threshold to at or below
+    // it, and not to 0, which is OutOfStock.
+    LowStock { sku: String, name: String, quantity: u32, threshold: u32 },
+    OutOfStock { sku: String, name: String },
+}
+
+// Called with each event, on the thread that made the change, so it should
+// hand anything slow to another.
+pub type Listener = Arc<dyn Fn(&StockEvent) + Send + Sync>;
+
+impl Collection {
+    pub fn subscribe(&mut self, listener: impl Fn(&StockEvent) + Send + Sync + 'static) {
+        self.listeners.push(Arc::new(listener));
+    }
+
+    // Items without a reorder level of their own are low on stock at this
+    // many or fewer. Without one, only they are.
+    pub fn set_low_stock_threshold(&mut self, threshold: u32) {
+        self.low_stock_threshold = Some(threshold);
+    }
+
+    // The quantity at or below which `item` is low on stock: its reorder
+    // level, or else the collection's threshold.
+    pub fn low_stock_threshold_of(&self, item: &Item) -> Option<u32> {
+        item.reorder_level.or(self.low_stock_threshold)
+    }
+
+    // Tells the listeners what the change to the item `sku` from `before` to
+    // `after`, its quantities or None where it didn't exist, amounts to.
+    // Inside a batch the event waits for the batch to go through.
+    pub(crate) fn announce(
+        &mut self,
+        sku: &str,
+        name: &str,
+        before: Option<u32>,
+        after: Option<u32>,
+    ) {
+        if self.listeners.is_empty() {
+            return;
+        }
+        let threshold = self._items.get(sku).and_then(|item| self.low_stock_threshold_of(item));
+        let (sku, name) = (sku.to_string(), name.to_string());
+        let event = match (before, after) {
+            (None, Some(quantity)) => StockEvent::Created { sku, name, quantity },
+            (Some(before), Some(0)) if before > 0 => StockEvent::OutOfStock { sku, name },
+            (Some(before), Some(quantity)) => match threshold {
+                Some(threshold) if quantity > 0 && quantity <= threshold && before > threshold => {
+                    StockEvent::LowStock { sku, name, quantity, threshold }
+                }
+                _ => return,
+            },
+            _ => return,
+        };
+        match &mut self.held_events {
+            Some(held) => held.push(event),
+            None => self.tell(&event),
+        }
+    }
+
+    // Holds back events until release_events, for a batch that may yet be
+    // rolled back.
+    pub(crate) fn hold_events(&mut self) {
+        self.held_events = Some(Vec::new());
+    }
+
+    // Sends the events held back, or drops them when the batch they came
+    // from failed.
+    pub(crate) fn release_events(&mut self, send: bool) {
+        let held = self.held_events.take().unwrap_or_default();
+        if send {
+            held.iter().for_each(|event| self.tell(event));
+        }
+    }
+
+    fn tell(&self, event: &StockEvent) {
+        tracing::debug!(?event, "stock event");
+        for listener in &self.listeners {
+            listener(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::unit::Unit;
+    use crate::{LocationId, Operation};
+
+    // A collection holding 10 apples, and the events it sends.
+    fn listened() -> (Collection, Arc<Mutex<Vec<StockEvent>>>) {
+        let mut collection = Collection::new();
+        let apple = String::from("apple");
+        collection.add_item(apple, 10, Unit::default(), String::from("fruit"), 0).unwrap();
+        collection.set_low_stock_threshold(5);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sent = Arc::clone(&events);
+        collection.subscribe(move |event| sent.lock().unwrap().push(event.clone()));
+        (collection, events)
+    }
+
+    #[test]
+    fn quantities_falling_past_the_threshold_and_to_nothing_are_events() {
+        let (mut collection, events) = listened();
+        collection.update_item(String::from("apple"), 6, None).unwrap();
+        collection.update_item(String::from("apple"), 4, None).unwrap();
+        collection.update_item(String::from("apple"), 3, None).unwrap();
+        collection.update_item(String::from("apple"), 0, None).unwrap();
+        let (sku, name) = (String::from("SKU-000001"), String::from("apple"));
+        let low = StockEvent::LowStock {
+            sku: sku.clone(),
+            name: name.clone(),
+            quantity: 4,
+            threshold: 5,
+        };
+        let out = StockEvent::OutOfStock { sku, name };
+        assert_eq!(*events.lock().unwrap(), [low, out]);
+    }
+
+    #[test]
+    fn a_failed_batch_sends_nothing() {
+        let (mut collection, events) = listened();
+        let ops = [
+            Operation::Adjust { name: String::from("apple"), delta: -10 },
+            Operation::Remove { name: String::from("pear") },
+        ];
+        assert!(collection.apply_all(&ops).is_err());
+        assert!(events.lock().unwrap().is_empty());
+
+        collection.apply_all(&ops[..1]).unwrap();
+        assert!(matches!(events.lock().unwrap()[..], [StockEvent::OutOfStock { .. }]));
+    }
+
+    #[test]
+    fn a_kit_short_of_a_component_sends_nothing() {
+        let (mut collection, events) = listened();
+        let pear = String::from("pear");
+        collection.add_item(pear, 1, Unit::default(), String::from("fruit"), 0).unwrap();
+        let basket = String::from("basket");
+        collection.add_item(basket, 0, Unit::default(), String::from("gifts"), 0).unwrap();
+        let components = [(String::from("apple"), 10), (String::from("pear"), 2)];
+        collection.set_components("basket", &components).unwrap();
+        events.lock().unwrap().clear();
+
+        // The apples run out before the pears are found short.
+        assert!(collection.assemble("basket", &LocationId::main(), 1).is_err());
+        assert_eq!(collection.lookup("apple").unwrap().quantity, 10);
+        assert!(events.lock().unwrap().is_empty());
+    }
+}
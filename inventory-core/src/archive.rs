@@ -0,0 +1,75 @@
+use crate::audit::Snapshot;
+use crate::{normalize, Collection, InventoryError, Item};
+
+// Items taken out of use without losing them. An archived item keeps its
+// SKU, stock and history but is left out of everything else: lists,
+// reports and lookups by name or SKU. Restoring brings it back as it was;
+// purging removes it for good.
+
+impl Collection {
+    // Moves the item into the archive. Its reservations have to be released
+    // or committed first.
+    #[tracing::instrument(skip_all, fields(key = key))]
+    pub fn archive(&mut self, key: &str) -> Result<(), InventoryError> {
+        let sku = self.resolve(key)?;
+        if self._items[&sku].reserved > 0 {
+            return Err(InventoryError::ArchiveReserved { item: key.to_string() });
+        }
+        let item = self.take(&sku).expect("resolve only returns stored SKUs");
+        self.archived.insert(sku, item);
+        Ok(())
+    }
+
+    // Brings an archived item back, given its SKU or name. It fails if an
+    // item with the same SKU or name was added in the meantime, e.g. by an
+    // import.
+    #[tracing::instrument(skip_all, fields(key = key))]
+    pub fn restore(&mut self, key: &str) -> Result<(), InventoryError> {
+        let sku = self.resolve_archived(key)?;
+        if self._items.contains_key(&sku) {
+            return Err(InventoryError::SkuInUse { sku });
+        }
+        self.check_name(&self.archived[&sku].name, None)?;
+        let item = self.archived.remove(&sku).expect("resolve_archived only returns stored SKUs");
+        self.insert(item);
+        Ok(())
+    }
+
+    // Removes an archived item for good. The ledger takes its stock out, so
+    // it still reconciles.
+    #[tracing::instrument(skip_all, fields(key = key))]
+    pub fn purge(&mut self, key: &str) -> Result<Item, InventoryError> {
+        let sku = self.resolve_archived(key)?;
+        let item = self.archived.remove(&sku).expect("resolve_archived only returns stored SKUs");
+        self.record_change(&sku, &item.name, Some(Snapshot::of(&item)));
+        Ok(item)
+    }
+
+    // By name.
+    pub fn archived(&self) -> Vec<&Item> {
+        let mut items: Vec<&Item> = self.archived.values().collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        items
+    }
+
+    // The SKU of the archived item with SKU or name `key`. Names match
+    // ignoring case, as there is no index of them to go by.
+    fn resolve_archived(&self, key: &str) -> Result<String, InventoryError> {
+        if self.archived.contains_key(key) {
+            return Ok(key.to_string());
+        }
+        let wanted = normalize(key);
+        let mut skus: Vec<String> = self
+            .archived
+            .values()
+            .filter(|item| normalize(&item.name) == wanted)
+            .map(|item| item.sku.to_string())
+            .collect();
+        skus.sort();
+        match skus.len() {
+            0 => Err(InventoryError::NotArchived { item: key.to_string() }),
+            1 => Ok(skus.remove(0)),
+            _ => Err(InventoryError::AmbiguousName { name: key.to_string(), skus }),
+        }
+    }
+}
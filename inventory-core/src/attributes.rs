@@ -0,0 +1,69 @@
+use std::collections::BTreeSet;
+
+use crate::{normalize, Collection, InventoryError, Item};
+
+// Details of an item beyond the ones every item has, like a color, a size or
+// the vendor's own code. Any name can be used. Names are kept in their
+// normalized form, so Color and color are one attribute; values are kept as
+// given but compared ignoring case.
+
+impl Item {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(&normalize(name)).map(String::as_str)
+    }
+
+    // By name, for showing.
+    pub fn attributes(&self) -> Vec<(&str, &str)> {
+        let mut attributes: Vec<(&str, &str)> = self
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        attributes.sort();
+        attributes
+    }
+
+    // Whether the item has attribute `name`, and with `value` when one is
+    // given.
+    pub fn has_attribute(&self, name: &str, value: Option<&str>) -> bool {
+        match (self.attribute(name), value) {
+            (Some(held), Some(value)) => normalize(held) == normalize(value),
+            (held, None) => held.is_some(),
+            (None, Some(_)) => false,
+        }
+    }
+}
+
+impl Collection {
+    // Sets attribute `name` of the item with SKU or name `key`, replacing any
+    // value it had.
+    #[tracing::instrument(skip_all, fields(key = key, name = name, value = value))]
+    pub fn set_attribute(
+        &mut self,
+        key: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<(), InventoryError> {
+        let (name, value) = (normalize(name), value.trim());
+        if name.is_empty() || value.is_empty() {
+            return Err(InventoryError::EmptyAttribute);
+        }
+        self.item_mut(key)?.attributes.insert(name, value.to_string());
+        Ok(())
+    }
+
+    // Removes attribute `name` from the item and returns the value it had.
+    #[tracing::instrument(skip_all, fields(key = key, name = name))]
+    pub fn unset_attribute(&mut self, key: &str, name: &str) -> Result<String, InventoryError> {
+        self.item_mut(key)?.attributes.remove(&normalize(name)).ok_or_else(|| {
+            InventoryError::AttributeNotSet { item: key.to_string(), name: name.to_string() }
+        })
+    }
+
+    // Every attribute name some item has, in order.
+    pub fn attribute_names(&self) -> Vec<&str> {
+        let names: BTreeSet<&str> =
+            self.iter().flat_map(|item| item.attributes.keys().map(String::as_str)).collect();
+        names.into_iter().collect()
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::path::Path;
+
+use chrono::{DateTime, Local, Utc};
+
+use crate::ledger::Reason;
+use crate::{format_price, Collection, Item};
+
+// Every change is appended here and never rewritten.
+pub const AUDIT_LOG: &str = "audit.log";
+
+// The parts of an item a change is recorded for.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    quantity: u32,
+    unit_price: u64,
+}
+
+impl Snapshot {
+    pub fn of(item: &Item) -> Snapshot {
+        Snapshot { quantity: item.quantity, unit_price: item.unit_price }
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.quantity, format_price(self.unit_price))
+    }
+}
+
+// One change to one item. No `before` means the item was added, no `after`
+// that it was deleted.
+#[derive(Clone)]
+pub struct AuditEntry {
+    at: DateTime<Utc>,
+    sku: String,
+    item: String,
+    before: Option<Snapshot>,
+    after: Option<Snapshot>,
+}
+
+impl AuditEntry {
+    fn action(&self) -> &'static str {
+        match (self.before, self.after) {
+            (None, _) => "added",
+            (_, None) => "deleted",
+            _ => "updated",
+        }
+    }
+
+    // A line of the log: time, before, after, SKU and name, separated by
+    // tabs. The name goes last so a tab inside it can't shift the other fields.
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.at.to_rfc3339(),
+            snapshot_field(self.before),
+            snapshot_field(self.after),
+            self.sku,
+            self.item
+        )
+    }
+
+    fn from_line(line: &str) -> Option<AuditEntry> {
+        let mut fields = line.splitn(5, '\t');
+        let at = DateTime::parse_from_rfc3339(fields.next()?).ok()?.with_timezone(&Utc);
+        let before = parse_snapshot(fields.next()?)?;
+        let after = parse_snapshot(fields.next()?)?;
+        let sku = fields.next()?.to_string();
+        let item = fields.next()?.to_string();
+        Some(AuditEntry { at, sku, item, before, after })
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let at = self.at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+        write!(f, "{} {} {} ({})", at, self.action(), self.item, self.sku)?;
+        match (self.before, self.after) {
+            (Some(before), Some(after)) => write!(f, ": {} -> {}", before, after),
+            (None, Some(after)) => write!(f, ": {}", after),
+            (Some(before), None) => write!(f, ": was {}", before),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+fn snapshot_field(snapshot: Option<Snapshot>) -> String {
+    match snapshot {
+        Some(snapshot) => format!("{}:{}", snapshot.quantity, snapshot.unit_price),
+        None => String::from("-"),
+    }
+}
+
+// The outer Option is whether the field parsed at all.
+fn parse_snapshot(field: &str) -> Option<Option<Snapshot>> {
+    if field == "-" {
+        return Some(None);
+    }
+    let (quantity, unit_price) = field.split_once(':')?;
+    Some(Some(Snapshot { quantity: quantity.parse().ok()?, unit_price: unit_price.parse().ok()? }))
+}
+
+impl Collection {
+    // Notes the change to the item `sku` since `before`, if there was one.
+    // Every method that changes an item calls this afterwards. A change of
+    // quantity goes in the ledger as an adjustment.
+    pub fn record_change(&mut self, sku: &str, name: &str, before: Option<Snapshot>) {
+        self.record_change_as(sku, name, before, Reason::Adjustment, None);
+    }
+
+    // Like record_change, for changes of quantity with a reason of their own
+    // and what they were part of.
+    pub(crate) fn record_change_as(
+        &mut self,
+        sku: &str,
+        name: &str,
+        before: Option<Snapshot>,
+        reason: Reason,
+        reference: Option<&str>,
+    ) {
+        let after = self._items.get(sku).map(Snapshot::of);
+        let quantity = |snapshot: Option<Snapshot>| snapshot.map_or(0, |s| i64::from(s.quantity));
+        let delta = quantity(after) - quantity(before);
+        if delta != 0 {
+            self.record_movement(sku, delta, reason, reference);
+        }
+        self.record_audit(sku, name, before);
+    }
+
+    // Notes the change in the audit log only, for changes the ledger takes
+    // in some other way.
+    pub(crate) fn record_audit(&mut self, sku: &str, name: &str, before: Option<Snapshot>) {
+        let after = self._items.get(sku).map(Snapshot::of);
+        if before != after {
+            tracing::info!(
+                sku,
+                item = name,
+                old_quantity = before.map(|s| s.quantity),
+                new_quantity = after.map(|s| s.quantity),
+                "item changed",
+            );
+            self.announce(sku, name, before.map(|s| s.quantity), after.map(|s| s.quantity));
+            self.audit.push(AuditEntry {
+                at: Utc::now(),
+                sku: sku.to_string(),
+                item: name.to_string(),
+                before,
+                after,
+            });
+        }
+    }
+
+    // Appends the changes recorded since the last call to the audit log.
+    pub fn write_audit(&mut self, path: &Path) -> io::Result<()> {
+        let lines = self.take_audit_lines();
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(lines.as_bytes())
+    }
+
+    // The changes recorded since the last call, as lines for the audit log,
+    // for callers that write the log themselves.
+    pub fn take_audit_lines(&mut self) -> String {
+        let mut lines = String::new();
+        for entry in mem::take(&mut self.audit) {
+            lines.push_str(&entry.to_line());
+            lines.push('\n');
+        }
+        lines
+    }
+}
+
+// Every logged change to the item with SKU or name `key`, oldest first. A
+// missing log has no history.
+pub fn item_history(path: &Path, key: &str) -> io::Result<Vec<AuditEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Some(entry) = AuditEntry::from_line(&line?) {
+            if entry.sku == key || entry.item == key {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
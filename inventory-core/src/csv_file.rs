@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+use chrono::DateTime;
+
+use crate::unit::Unit;
+use crate::{format_price, normalize, parse_price, Collection, InventoryError, Item};
+
+// Columns added later go on the end, so older files are a prefix of this.
+// After them comes a column for each attribute in use, named after it.
+const HEADER: [&str; 6] = ["name", "quantity", "category", "unit_price", "sku", "unit"];
+
+// How CSV files are read and written. Only the field separator can be
+// changed; quoting always uses `"`.
+pub struct CsvOptions {
+    delimiter: u8,
+}
+
+impl CsvOptions {
+    // Spreadsheets in some locales use `;`, and `\t` gives TSV. The delimiter
+    // has to be a single ASCII character that doesn't clash with quoting.
+    pub fn new(delimiter: char) -> Result<Self, InventoryError> {
+        match delimiter {
+            '"' | '\n' | '\r' => Err(InventoryError::InvalidDelimiter { delimiter }),
+            _ if !delimiter.is_ascii() => Err(InventoryError::InvalidDelimiter { delimiter }),
+            _ => Ok(CsvOptions { delimiter: delimiter as u8 }),
+        }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: b',' }
+    }
+}
+
+impl Collection {
+    // Writes a header row and one row per item, sorted by name.
+    pub fn export_csv(&self, path: &Path, options: &CsvOptions) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .from_path(path)?;
+
+        let mut items: Vec<_> = self.iter().collect();
+        items.sort_by_key(|&item| (&item.name, &item.sku));
+        let attributes = self.attribute_names();
+
+        writer.write_record(HEADER.iter().chain(&attributes))?;
+        for item in items {
+            let fixed = [
+                item.name.to_string(),
+                item.quantity.to_string(),
+                item.category.to_string(),
+                format_price(item.unit_price),
+                item.sku.to_string(),
+                item.unit.to_string(),
+            ];
+            let values = attributes.iter().map(|name| item.attribute(name).unwrap_or_default());
+            writer.write_record(fixed.iter().map(String::as_str).chain(values))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Adds every valid row of the file. A missing file or a wrong header is
+    // an error; rows that can't be used are skipped and described in the
+    // returned list, with the line number they were on. Files written before
+    // prices were added have no unit_price column and import at 0.00, and
+    // ones without a unit column count in pieces. A row with a SKU replaces
+    // the item with that SKU; one without gets a new SKU. Columns past unit
+    // are attributes, and an empty cell leaves that attribute unset.
+    #[tracing::instrument(skip_all, fields(path = %path.display()))]
+    pub fn import_csv(
+        &mut self,
+        path: &Path,
+        options: &CsvOptions,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(options.delimiter)
+            .flexible(true)
+            .from_path(path)?;
+
+        let header = reader.headers()?;
+        let columns: Vec<String> =
+            header.iter().map(|column| column.trim().to_lowercase()).collect();
+        let known = columns.len().min(HEADER.len());
+        if known < 3 || columns[..known] != HEADER[..known] {
+            return Err(format!(
+                "expected the header {} but found {}",
+                HEADER.join(","),
+                header.iter().collect::<Vec<_>>().join(",")
+            )
+            .into());
+        }
+
+        let mut problems: Vec<String> = Vec::new();
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    problems.push(e.to_string());
+                    continue;
+                }
+            };
+            let line = record.position().map_or(0, |position| position.line());
+
+            if record.len() != columns.len() {
+                problems.push(format!(
+                    "line {}: expected {} fields but found {}",
+                    line,
+                    columns.len(),
+                    record.len()
+                ));
+                continue;
+            }
+            let name = record[0].trim();
+            if name.is_empty() {
+                problems.push(format!("line {}: the name is empty", line));
+                continue;
+            }
+            let quantity: u32 = match record[1].trim().parse() {
+                Ok(quantity) => quantity,
+                Err(_) => {
+                    problems.push(format!(
+                        "line {}: quantity {:?} is not a number from 0 to {}",
+                        line,
+                        &record[1],
+                        u32::MAX
+                    ));
+                    continue;
+                }
+            };
+            let category = match record[2].trim() {
+                "" => "uncategorized",
+                category => category,
+            };
+            let unit_price = match record.get(3) {
+                None => 0,
+                Some(price) => match parse_price(price) {
+                    Some(price) => price,
+                    None => {
+                        problems.push(format!("line {}: price {:?} is not an amount", line, price));
+                        continue;
+                    }
+                },
+            };
+
+            let attributes: HashMap<String, String> = columns
+                .iter()
+                .zip(record.iter())
+                .skip(HEADER.len())
+                .filter(|(_, value)| !value.trim().is_empty())
+                .map(|(name, value)| (normalize(name), value.trim().to_string()))
+                .collect();
+
+            let unit = match record.get(5).map(str::trim) {
+                None | Some("") => Unit::Pieces,
+                Some(unit) => match unit.parse() {
+                    Ok(unit) => unit,
+                    Err(e) => {
+                        problems.push(format!("line {}: {}", line, e));
+                        continue;
+                    }
+                },
+            };
+
+            let added = match record.get(4).map(str::trim) {
+                Some(sku) if !sku.is_empty() => self.put_item(Item {
+                    sku: sku.to_string(),
+                    name: name.to_string(),
+                    quantity,
+                    unit,
+                    category: category.to_string(),
+                    reserved: 0,
+                    // put_item places the stock.
+                    locations: HashMap::new(),
+                    unit_price,
+                    // put_item sets both.
+                    created_at: DateTime::UNIX_EPOCH,
+                    updated_at: DateTime::UNIX_EPOCH,
+                    expires_on: None,
+                    reorder_level: None,
+                    serials: None,
+                    lots: None,
+                    components: Vec::new(),
+                    attributes,
+                }),
+                _ => self
+                    .add_item(name.to_string(), quantity, unit, category.to_string(), unit_price)
+                    .and_then(|sku| {
+                        self.item_mut(&sku)?.attributes = attributes;
+                        Ok(())
+                    }),
+            };
+            if let Err(e) = added {
+                problems.push(format!("line {}: {}", line, e));
+            }
+        }
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::SortOrder;
+
+    // A file of its own for each test, removed before it starts.
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("inventory-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn semicolons_round_trip_names_holding_them_and_empty_cells() {
+        let mut collection = Collection::new();
+        for (name, quantity, price) in [("salt; fine", 3, 120), ("pepper, black", 0, 0)] {
+            let (name, category) = (name.to_string(), String::from("spices"));
+            collection.add_item(name, quantity, Unit::Grams, category, price).unwrap();
+        }
+        collection.set_attribute("salt; fine", "origin", "sea").unwrap();
+
+        let path = temp_file("semicolons.csv");
+        let options = CsvOptions::new(';').unwrap();
+        collection.export_csv(&path, &options).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("\"salt; fine\";3;spices;1.20;SKU-000001;g;sea\n"), "{}", text);
+        assert!(text.contains("pepper, black;0;spices;0.00;SKU-000002;g;\n"), "{}", text);
+
+        let mut loaded = Collection::new();
+        assert!(loaded.import_csv(&path, &options).unwrap().is_empty());
+        let rows = |collection: &Collection| {
+            let items = collection.sorted(SortOrder::Name).into_iter();
+            let row = |item: &Item| {
+                let fields = (&item.sku, &item.name, item.quantity, item.unit, item.unit_price);
+                format!("{:?} {} {:?}", fields, item.category, item.attributes())
+            };
+            items.map(row).collect::<Vec<_>>()
+        };
+        assert_eq!(rows(&loaded), rows(&collection));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn empty_cells_take_defaults_or_are_reported() {
+        let path = temp_file("empty.csv");
+        fs::write(&path, "name;quantity;category\nrice;3;\n;2;grains\nflour;;grains\n").unwrap();
+        let mut collection = Collection::new();
+        let problems = collection.import_csv(&path, &CsvOptions::new(';').unwrap()).unwrap();
+        assert_eq!(
+            problems,
+            [
+                "line 3: the name is empty",
+                "line 4: quantity \"\" is not a number from 0 to 4294967295",
+            ]
+        );
+        assert_eq!(collection.lookup("rice").unwrap().category, "uncategorized");
+        fs::remove_file(path).unwrap();
+
+        for delimiter in ['"', '\n', 'é'] {
+            assert!(CsvOptions::new(delimiter).is_err(), "{:?}", delimiter);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use crate::audit::Snapshot;
+use crate::fuzzy::edit_distance;
+use crate::{normalize, Collection, InventoryError, Item};
+
+// Items that are probably the same thing entered twice, and folding one into
+// another. Unlike merge, which brings in another collection, this works
+// within one and keeps a single SKU for what were two.
+
+// Names this long or longer count as alike one edit apart, like Apple and
+// Apples; shorter ones only when they are the same.
+const MIN_FUZZY_NAME: usize = 5;
+
+// Whether two items look like the same thing: their names are the same
+// ignoring case and surrounding spaces or one edit apart, or their SKUs are
+// the same ignoring case and punctuation.
+fn alike(a: &Item, b: &Item) -> bool {
+    let (a_name, b_name) = (normalize(&a.name), normalize(&b.name));
+    let long = a_name.chars().count().min(b_name.chars().count()) >= MIN_FUZZY_NAME;
+    a_name == b_name || (long && edit_distance(&a_name, &b_name) <= 1) || sku_key(a) == sku_key(b)
+}
+
+fn sku_key(item: &Item) -> String {
+    item.sku.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+impl Collection {
+    // The groups of items that look like the same thing, directly or
+    // through another item of the group. Each group has the item added first
+    // first, as the one to keep, and the groups are in the order of those.
+    pub fn likely_duplicates(&self) -> Vec<Vec<&Item>> {
+        let mut items: Vec<&Item> = self.iter().collect();
+        items.sort_by_key(|item| (item.created_at, &item.sku));
+
+        // The group each item is in, by index into items.
+        let mut group: Vec<usize> = (0..items.len()).collect();
+        for i in 0..items.len() {
+            for j in i + 1..items.len() {
+                if group[i] != group[j] && alike(items[i], items[j]) {
+                    let (from, to) = (group[j], group[i]);
+                    for other in group.iter_mut().filter(|other| **other == from) {
+                        *other = to;
+                    }
+                }
+            }
+        }
+
+        let mut groups: BTreeMap<usize, Vec<&Item>> = BTreeMap::new();
+        for (i, item) in items.into_iter().enumerate() {
+            groups.entry(group[i]).or_default().push(item);
+        }
+        let mut groups: Vec<Vec<&Item>> =
+            groups.into_values().filter(|found| found.len() > 1).collect();
+        groups.sort_by(|a, b| (a[0].created_at, &a[0].sku).cmp(&(b[0].created_at, &b[0].sku)));
+        groups
+    }
+
+    // Folds the item `other` into `keep`, both given by SKU or name: the
+    // stock, reservations, serials and lots of `other` move over, and its
+    // movements, sales, orders and kit lines are relabelled with the SKU of
+    // `keep`, so the ledger reads as if there had only ever been the one.
+    // Where both have a setting, like a price, that of `keep` stays.
+    #[tracing::instrument(skip_all, fields(keep = keep, other = other))]
+    pub fn merge_items(&mut self, keep: &str, other: &str) -> Result<(), InventoryError> {
+        let keep_sku = self.resolve(keep)?;
+        let other_sku = self.resolve(other)?;
+        if keep_sku == other_sku {
+            return Ok(());
+        }
+        let (kept, merged) = (&self._items[&keep_sku], &self._items[&other_sku]);
+        if kept.is_serialized() != merged.is_serialized()
+            || kept.is_lot_tracked() != merged.is_lot_tracked()
+        {
+            return Err(InventoryError::TrackingConflict { item: other.to_string() });
+        }
+        if kept.unit != merged.unit {
+            return Err(InventoryError::IncompatibleUnits { from: merged.unit, to: kept.unit });
+        }
+        let quantity = kept
+            .quantity
+            .checked_add(merged.quantity)
+            .ok_or_else(|| InventoryError::QuantityOverflow { item: keep.to_string() })?;
+        self.check_max(quantity)?;
+
+        let merged = self.take(&other_sku).expect("resolve only returns stored SKUs");
+        let kept = self._items.get_mut(&keep_sku).expect("resolve only returns stored SKUs");
+        let before = Snapshot::of(kept);
+        kept.quantity = quantity;
+        kept.reserved += merged.reserved;
+        for (location, held) in &merged.locations {
+            *kept.locations.entry(location.clone()).or_insert(0) += held;
+        }
+        if let (Some(serials), Some(more)) = (kept.serials.as_mut(), merged.serials.as_ref()) {
+            serials.extend(more.iter().cloned());
+        }
+        if let (Some(lots), Some(more)) = (kept.lots.as_mut(), merged.lots.as_ref()) {
+            lots.extend(more.iter().cloned());
+        }
+        kept.settle_lots();
+        for (name, value) in &merged.attributes {
+            kept.attributes.entry(name.to_string()).or_insert_with(|| value.to_string());
+        }
+        if kept.components.is_empty() {
+            kept.components = merged.components.clone();
+        }
+        kept.created_at = kept.created_at.min(merged.created_at);
+        kept.expires_on = kept.expires_on.or(merged.expires_on);
+        kept.reorder_level = kept.reorder_level.or(merged.reorder_level);
+        kept.touch();
+        let name = kept.name.to_string();
+
+        for (sku, _) in self.reservations.values_mut() {
+            if *sku == other_sku {
+                *sku = keep_sku.to_string();
+            }
+        }
+        for movement in self.movements.iter_mut().filter(|movement| movement.sku == other_sku) {
+            movement.sku = keep_sku.to_string();
+        }
+        let sale_lines = self.sales.values_mut().flat_map(|sale| sale.lines.iter_mut());
+        for line in sale_lines.filter(|line| line.sku == other_sku) {
+            line.sku = keep_sku.to_string();
+        }
+        let order_lines = self.orders.values_mut().flat_map(|order| order.lines.iter_mut());
+        for line in order_lines.filter(|line| line.sku == other_sku) {
+            line.sku = keep_sku.to_string();
+        }
+        let components = self._items.values_mut().flat_map(|item| item.components.iter_mut());
+        for component in components.filter(|component| component.sku == other_sku) {
+            component.sku = keep_sku.to_string();
+        }
+        // An item can't be a component of itself.
+        if let Some(kept) = self._items.get_mut(&keep_sku) {
+            kept.components.retain(|component| component.sku != keep_sku);
+        }
+
+        // The movements moved over with the stock, so only the audit log
+        // needs to hear of it.
+        self.record_audit(&keep_sku, &name, Some(before));
+        self.record_audit(&other_sku, &merged.name, Some(Snapshot::of(&merged)));
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::Movement;
+use crate::purchasing::{PurchaseOrder, Supplier};
+use crate::sales::Sale;
+use crate::{Collection, Item, LocationId, ReservationId};
+
+// The collection kept as a log of what happened to it rather than as its
+// latest state. Each save appends the events that turn the state on disk
+// into the collection being saved, one JSON object per line, and loading
+// replays them on top of the last snapshot. An interrupted save can only
+// lose its own last lines.
+//
+// Events are numbered, and a snapshot notes the number of the last one it
+// has taken in. Loading skips those, so a log left behind by a compaction
+// that was cut off isn't applied twice.
+
+// One change, as it is written to the log.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    // Logs from before events were numbered have 0 for each.
+    #[serde(default)]
+    seq: u64,
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: Event,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event")]
+enum Event {
+    ItemAdded {
+        item: Item,
+    },
+    // Stock moved in or out; nothing else about the item changed.
+    QuantityChanged {
+        sku: String,
+        quantity: u32,
+        locations: HashMap<LocationId, u32>,
+        updated_at: DateTime<Utc>,
+    },
+    // Anything else about an item, like its name or price.
+    ItemChanged {
+        item: Item,
+    },
+    ItemRemoved {
+        sku: String,
+    },
+    // The item as it was when it was archived.
+    ItemArchived {
+        item: Item,
+    },
+    ItemRestored {
+        sku: String,
+    },
+    ArchivedItemPurged {
+        sku: String,
+    },
+    // The parts of the collection that aren't items.
+    CollectionChanged {
+        settings: Settings,
+    },
+    // A change of quantity was added to the ledger.
+    Moved {
+        movement: Movement,
+    },
+    // The ledger was replaced rather than added to, as when the whole
+    // collection is.
+    LedgerReplaced {
+        movements: Vec<Movement>,
+    },
+}
+
+// Maps keyed by an ID are kept as lists in ID order: a record is flattened,
+// and the keys of maps inside one only read back as strings.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Settings {
+    max_per_item: Option<u32>,
+    reservations: Vec<(ReservationId, (String, u32))>,
+    next_reservation: u64,
+    next_sku: u64,
+    locations: BTreeSet<LocationId>,
+    suppliers: Vec<Supplier>,
+    next_supplier: u64,
+    orders: Vec<PurchaseOrder>,
+    next_order: u64,
+    sales: Vec<Sale>,
+    next_sale: u64,
+}
+
+impl Settings {
+    fn of(collection: &Collection) -> Settings {
+        let mut reservations: Vec<(ReservationId, (String, u32))> =
+            collection.reservations.iter().map(|(id, held)| (*id, held.clone())).collect();
+        reservations.sort_by_key(|(id, _)| id.0);
+        Settings {
+            max_per_item: collection.max_per_item,
+            reservations,
+            next_reservation: collection.next_reservation,
+            next_sku: collection.next_sku,
+            locations: collection.locations.clone(),
+            suppliers: collection.suppliers.values().cloned().collect(),
+            next_supplier: collection.next_supplier,
+            orders: collection.orders.values().cloned().collect(),
+            next_order: collection.next_order,
+            sales: collection.sales.values().cloned().collect(),
+            next_sale: collection.next_sale,
+        }
+    }
+
+    fn apply(self, collection: &mut Collection) {
+        collection.max_per_item = self.max_per_item;
+        collection.reservations = self.reservations.into_iter().collect();
+        collection.next_reservation = self.next_reservation;
+        collection.next_sku = self.next_sku;
+        collection.locations = self.locations;
+        collection.suppliers =
+            self.suppliers.into_iter().map(|supplier| (supplier.id, supplier)).collect();
+        collection.next_supplier = self.next_supplier;
+        collection.orders = self.orders.into_iter().map(|order| (order.id, order)).collect();
+        collection.next_order = self.next_order;
+        collection.sales = self.sales.into_iter().map(|sale| (sale.id, sale)).collect();
+        collection.next_sale = self.next_sale;
+    }
+}
+
+// The collection as a snapshot holds it, with the number of the last event
+// it covers.
+#[derive(Serialize, Deserialize)]
+struct Snapshot<C> {
+    covers: u64,
+    collection: C,
+}
+
+// The snapshot sits next to the log, as `<log>.snapshot`.
+fn snapshot_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".snapshot");
+    PathBuf::from(name)
+}
+
+// Rebuilds the collection from the snapshot and the log after it. Neither
+// file existing is an empty collection.
+pub fn load(path: &Path) -> io::Result<Collection> {
+    Ok(replay(path)?.0)
+}
+
+// Also returns how many events the log holds, how long the part of it
+// holding them is, and the number of the last event taken in.
+fn replay(path: &Path) -> io::Result<(Collection, usize, u64, u64)> {
+    let (mut collection, covers) = match fs::read_to_string(snapshot_path(path)) {
+        Ok(text) => match serde_json::from_str::<Snapshot<Collection>>(&text) {
+            Ok(snapshot) => (snapshot.collection, Some(snapshot.covers)),
+            // A snapshot from before events were numbered is the collection
+            // alone, and covers none of the log.
+            Err(e) => (serde_json::from_str(&text).map_err(|_| e)?, None),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (Collection::new(), None),
+        Err(e) => return Err(e),
+    };
+    let log = match fs::read_to_string(path) {
+        Ok(log) => log,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    // Anything after the last newline is from a save that was cut off part
+    // way, and is left out.
+    let complete = log.rfind('\n').map_or(0, |i| i + 1);
+    let mut events = 0;
+    let mut last = covers.unwrap_or(0);
+    for (i, line) in log[..complete].lines().enumerate() {
+        let record: Record = serde_json::from_str(line).map_err(|e| {
+            let message = format!("line {} of {}: {}", i + 1, path.display(), e);
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        events += 1;
+        if covers.is_some_and(|covers| record.seq <= covers) {
+            continue;
+        }
+        apply(&mut collection, record.event);
+        last = last.max(record.seq);
+    }
+    collection.reindex();
+    Ok((collection, events, complete as u64, last))
+}
+
+// Replays one event without recording it in the audit log again.
+fn apply(collection: &mut Collection, event: Event) {
+    match event {
+        Event::ItemAdded { item } | Event::ItemChanged { item } => {
+            collection._items.insert(item.sku.to_string(), item);
+        }
+        Event::QuantityChanged { sku, quantity, locations, updated_at } => {
+            if let Some(item) = collection._items.get_mut(&sku) {
+                item.quantity = quantity;
+                item.locations = locations;
+                item.updated_at = updated_at;
+            }
+        }
+        Event::ItemRemoved { sku } => {
+            collection._items.remove(&sku);
+        }
+        Event::ItemArchived { item } => {
+            collection._items.remove(&item.sku);
+            collection.archived.insert(item.sku.to_string(), item);
+        }
+        Event::ItemRestored { sku } => {
+            if let Some(item) = collection.archived.remove(&sku) {
+                collection._items.insert(sku, item);
+            }
+        }
+        Event::ArchivedItemPurged { sku } => {
+            collection.archived.remove(&sku);
+        }
+        Event::CollectionChanged { settings } => settings.apply(collection),
+        Event::Moved { movement } => collection.movements.push(movement),
+        Event::LedgerReplaced { movements } => collection.movements = movements,
+    }
+}
+
+// The events that turn `before` into `after`, items in SKU order. Items go
+// in and out of the archive first, so the item events after that compare a
+// restored item with what it was when archived.
+fn diff(before: &Collection, after: &Collection) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut archived: Vec<&String> =
+        after.archived.keys().filter(|sku| !before.archived.contains_key(*sku)).collect();
+    archived.sort();
+    for sku in archived {
+        events.push(Event::ItemArchived { item: after.archived[sku].clone() });
+    }
+    let mut unarchived: Vec<&String> =
+        before.archived.keys().filter(|sku| !after.archived.contains_key(*sku)).collect();
+    unarchived.sort();
+    for sku in unarchived {
+        let sku = sku.to_string();
+        match after._items.contains_key(&sku) {
+            true => events.push(Event::ItemRestored { sku }),
+            false => events.push(Event::ArchivedItemPurged { sku }),
+        }
+    }
+
+    let mut skus: Vec<&String> = after._items.keys().collect();
+    skus.sort();
+    for sku in skus {
+        let item = &after._items[sku];
+        let restored = before.archived.get(sku).filter(|_| !after.archived.contains_key(sku));
+        match before._items.get(sku).or(restored) {
+            None => events.push(Event::ItemAdded { item: item.clone() }),
+            Some(old) if old == item => {}
+            Some(old) => {
+                let mut restocked = old.clone();
+                restocked.quantity = item.quantity;
+                restocked.locations = item.locations.clone();
+                restocked.updated_at = item.updated_at;
+                if restocked == *item {
+                    events.push(Event::QuantityChanged {
+                        sku: sku.to_string(),
+                        quantity: item.quantity,
+                        locations: item.locations.clone(),
+                        updated_at: item.updated_at,
+                    });
+                } else {
+                    events.push(Event::ItemChanged { item: item.clone() });
+                }
+            }
+        }
+    }
+    let mut removed: Vec<&String> = before
+        ._items
+        .keys()
+        .filter(|sku| !after._items.contains_key(*sku) && !after.archived.contains_key(*sku))
+        .collect();
+    removed.sort();
+    for sku in removed {
+        events.push(Event::ItemRemoved { sku: sku.to_string() });
+    }
+    let settings = Settings::of(after);
+    if Settings::of(before) != settings {
+        events.push(Event::CollectionChanged { settings });
+    }
+    let logged = before.movements.len();
+    match after.movements.get(..logged) {
+        Some(kept) if kept == before.movements => {
+            for movement in &after.movements[logged..] {
+                events.push(Event::Moved { movement: movement.clone() });
+            }
+        }
+        _ => events.push(Event::LedgerReplaced { movements: after.movements.clone() }),
+    }
+    events
+}
+
+// Appends what changed since the state on disk. Once the log would hold more
+// than `compact_after` events, a snapshot of the whole collection replaces
+// it instead, so loading doesn't get slower forever.
+pub fn save(path: &Path, collection: &Collection, compact_after: usize) -> io::Result<()> {
+    let (on_disk, logged, complete, last) = replay(path)?;
+    let events = diff(&on_disk, collection);
+    if events.is_empty() {
+        return Ok(());
+    }
+    if logged + events.len() > compact_after {
+        write_snapshot(path, collection, last)?;
+        File::create(path)?;
+        return Ok(());
+    }
+
+    let at = Utc::now();
+    let mut lines = String::new();
+    for (seq, event) in (last + 1..).zip(events) {
+        lines.push_str(&serde_json::to_string(&Record { seq, at, event })?);
+        lines.push('\n');
+    }
+    let mut file = OpenOptions::new().create(true).write(true).truncate(false).open(path)?;
+    file.set_len(complete)?;
+    file.seek(SeekFrom::End(0))?;
+    file.write_all(lines.as_bytes())?;
+    file.sync_data()
+}
+
+// Writes a snapshot covering the events up to `covers`, for an empty log to
+// follow. It is written beside the old one and renamed over it, so a crash
+// leaves the old snapshot, or the new one with a log whose events it skips.
+fn write_snapshot(path: &Path, collection: &Collection, covers: u64) -> io::Result<()> {
+    let snapshot = snapshot_path(path);
+    let mut partial = snapshot.as_os_str().to_owned();
+    partial.push(".tmp");
+    let partial = PathBuf::from(partial);
+
+    let mut file = File::create(&partial)?;
+    serde_json::to_writer(&mut file, &Snapshot { covers, collection })?;
+    file.sync_data()?;
+    fs::rename(&partial, &snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit::Unit;
+
+    // A file of its own for each test, removed with its snapshot before it
+    // starts.
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("inventory-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(snapshot_path(&path));
+        path
+    }
+
+    #[test]
+    fn a_compaction_cut_off_before_emptying_the_log_keeps_the_snapshot() {
+        let path = temp_file("cut-off-compaction.log");
+        let mut collection = Collection::new();
+        let (name, category) = (String::from("Bolt"), String::from("hardware"));
+        let sku = collection.add_item(name, 5, Unit::default(), category, 10).unwrap();
+        save(&path, &collection, 100).unwrap();
+
+        // The snapshot is in place but the log it covers is still there.
+        collection.update_item(sku.to_string(), 3, Some(20)).unwrap();
+        let (_, _, _, last) = replay(&path).unwrap();
+        write_snapshot(&path, &collection, last).unwrap();
+
+        let loaded = load(&path).unwrap();
+        let bolt = loaded.get(&sku).unwrap();
+        assert_eq!((bolt.quantity, bolt.unit_price), (3, 20));
+
+        // Saving again starts the log after the events the snapshot covers.
+        collection.update_item(sku.to_string(), 4, None).unwrap();
+        save(&path, &collection, 100).unwrap();
+        assert_eq!(load(&path).unwrap().get(&sku).unwrap().quantity, 4);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(snapshot_path(&path));
+    }
+
+    #[test]
+    fn a_cut_off_compaction_does_not_move_stock_twice() {
+        let path = temp_file("cut-off-movements.log");
+        let mut collection = Collection::new();
+        let (name, category) = (String::from("Bolt"), String::from("hardware"));
+        let sku = collection.add_item(name, 5, Unit::default(), category, 10).unwrap();
+        save(&path, &collection, 100).unwrap();
+
+        collection.update_item(sku.to_string(), 3, None).unwrap();
+        let (_, _, _, last) = replay(&path).unwrap();
+        write_snapshot(&path, &collection, last).unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.movements(&sku).unwrap().len(), 2);
+        assert!(loaded.reconcile().is_empty());
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(snapshot_path(&path));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{normalize, Collection, Item};
+
+// How many suggestions a miss comes with at most.
+const SUGGESTIONS: usize = 3;
+
+// The Levenshtein distance: how many single character insertions, deletions
+// and substitutions turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Distances from the part of `a` seen so far to each prefix of `b`.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// ", did you mean Apple?" to follow a miss, or nothing without suggestions.
+pub fn did_you_mean(suggestions: &[String]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [name] => format!(", did you mean {}?", name),
+        [rest @ .., last] => format!(", did you mean {} or {}?", rest.join(", "), last),
+    }
+}
+
+// The most edits a name can be away from `query` and still count as a match:
+// one for short queries, and one more for every three characters.
+fn max_distance(query: &str) -> usize {
+    query.chars().count() / 3 + 1
+}
+
+impl Collection {
+    // Items whose name is within a few edits of `query`, ignoring case and
+    // surrounding spaces, closest first and then by name.
+    pub fn fuzzy_search(&self, query: &str) -> Vec<(usize, &Item)> {
+        let query = normalize(query);
+        let max = max_distance(&query);
+        let mut matches: Vec<(usize, &Item)> = self
+            .iter()
+            .map(|item| (edit_distance(&query, &normalize(&item.name)), item))
+            .filter(|&(distance, _)| distance <= max)
+            .collect();
+        matches.sort_by_key(|&(distance, item)| (distance, &item.name, &item.sku));
+        matches
+    }
+
+    // The names closest to `query`, for a "did you mean" after a miss. Each
+    // name appears once however many items share it.
+    pub fn closest_names(&self, query: &str) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (_, item) in self.fuzzy_search(query) {
+            if !names.contains(&item.name) {
+                names.push(item.name.to_string());
+            }
+            if names.len() == SUGGESTIONS {
+                break;
+            }
+        }
+        names
+    }
+}
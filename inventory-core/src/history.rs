@@ -0,0 +1,89 @@
+use crate::audit::Snapshot;
+use crate::{Collection, Item};
+
+// A raw edit of a single entry, by SKU. Menu actions are recorded as the commands
+// that reverse them, and running a command gives back its own reverse, so
+// the same list moves between the undo and redo stacks.
+pub enum Command {
+    // Boxed, as an item is far bigger than a SKU.
+    Put(Box<Item>),
+    Delete(String),
+}
+
+impl Command {
+    pub fn put(item: Item) -> Command {
+        Command::Put(Box::new(item))
+    }
+
+    // The command that puts the entry `sku` back the way `before` was.
+    pub fn restoring(sku: &str, before: Option<Item>) -> Command {
+        match before {
+            Some(item) => Command::put(item),
+            None => Command::Delete(sku.to_string()),
+        }
+    }
+}
+
+impl Collection {
+    // Runs the command and returns the command that reverses it.
+    pub fn execute(&mut self, command: Command) -> Command {
+        match command {
+            Command::Put(item) => {
+                let item = *item;
+                let (sku, name) = (item.sku.to_string(), item.name.to_string());
+                let before = self.insert(item);
+                self.record_change(&sku, &name, before.as_ref().map(Snapshot::of));
+                Command::restoring(&sku, before)
+            }
+            Command::Delete(sku) => {
+                let before = self.remove_item(&sku).ok();
+                Command::restoring(&sku, before)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<Command>>,
+    redo: Vec<Vec<Command>>,
+}
+
+impl History {
+    // Remembers how to reverse an action that was just done. A new action
+    // makes anything that was undone before it unreachable, so redo is reset.
+    pub fn record(&mut self, inverse: Vec<Command>) {
+        if !inverse.is_empty() {
+            self.undo.push(inverse);
+            self.redo.clear();
+        }
+    }
+
+    // Returns false when there was nothing to undo.
+    pub fn undo(&mut self, collection: &mut Collection) -> bool {
+        match self.undo.pop() {
+            Some(commands) => {
+                self.redo.push(run(collection, commands));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Returns false when there was nothing to redo.
+    pub fn redo(&mut self, collection: &mut Collection) -> bool {
+        match self.redo.pop() {
+            Some(commands) => {
+                self.undo.push(run(collection, commands));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// Runs the commands last to first and returns their reverses, which undo
+// them when run in turn.
+fn run(collection: &mut Collection, commands: Vec<Command>) -> Vec<Command> {
+    commands.into_iter().rev().map(|command| collection.execute(command)).collect()
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use crate::ledger::Reason;
+use crate::{Collection, InventoryError, Item, LocationId};
+
+// Items made up of other items, like a gift box of three soaps and a towel.
+// Assembling kits takes their components out of stock and adds the kits;
+// disassembling does the opposite. Both happen at one location, and either
+// all of it happens or, when something is short, none of it does.
+
+// How much of one item goes into one kit, in the item's own unit.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Component {
+    pub sku: String,
+    pub quantity: u32,
+}
+
+impl Item {
+    pub fn is_kit(&self) -> bool {
+        !self.components.is_empty()
+    }
+}
+
+impl Collection {
+    // Makes the item with SKU or name `key` a kit of `components`, given by
+    // SKU or name. An item given twice counts once with both quantities, and
+    // lines with no quantity are left out. No components at all makes it an
+    // ordinary item again.
+    pub fn set_components(
+        &mut self,
+        key: &str,
+        components: &[(String, u32)],
+    ) -> Result<(), InventoryError> {
+        let sku = self.resolve(key)?;
+        let mut quantities: BTreeMap<String, u32> = BTreeMap::new();
+        for (component, quantity) in components {
+            let component = self.resolve(component)?;
+            if component == sku {
+                return Err(InventoryError::KitContainsItself { item: key.to_string() });
+            }
+            let total = quantities.entry(component).or_insert(0);
+            *total = total.saturating_add(*quantity);
+        }
+        let components = quantities
+            .into_iter()
+            .filter(|(_, quantity)| *quantity > 0)
+            .map(|(sku, quantity)| Component { sku, quantity })
+            .collect();
+        self.item_mut(&sku)?.components = components;
+        Ok(())
+    }
+
+    // The kits, by name.
+    pub fn kits(&self) -> Vec<&Item> {
+        let mut kits: Vec<&Item> = self.iter().filter(|item| item.is_kit()).collect();
+        kits.sort_by(|a, b| a.name.cmp(&b.name));
+        kits
+    }
+
+    // The components of a kit as the items they are now, with how much of
+    // each goes into one kit. Items removed since are left out.
+    pub fn kit_components<'a>(&'a self, kit: &'a Item) -> Vec<(&'a Item, u32)> {
+        kit.components
+            .iter()
+            .filter_map(|component| Some((self._items.get(&component.sku)?, component.quantity)))
+            .collect()
+    }
+
+    // Makes `count` of the kit at `location` from the components there.
+    #[tracing::instrument(skip_all, fields(key = key, location = %location, count = count))]
+    pub fn assemble(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        count: u32,
+    ) -> Result<(), InventoryError> {
+        self.rebuild(key, location, count, true)
+    }
+
+    // Takes `count` of the kit at `location` apart, putting the components
+    // back in stock there.
+    #[tracing::instrument(skip_all, fields(key = key, location = %location, count = count))]
+    pub fn disassemble(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        count: u32,
+    ) -> Result<(), InventoryError> {
+        self.rebuild(key, location, count, false)
+    }
+
+    fn rebuild(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        count: u32,
+        assemble: bool,
+    ) -> Result<(), InventoryError> {
+        let kit = self.lookup(key)?;
+        if !kit.is_kit() {
+            return Err(InventoryError::NotAKit { item: key.to_string() });
+        }
+        let sku = kit.sku.to_string();
+        let components = kit.components.clone();
+
+        let items = self._items.clone();
+        let (audited, moved) = (self.audit.len(), self.movements.len());
+        self.hold_events();
+        // The component movements refer to the kit they went into or came
+        // out of.
+        let result = components.iter().try_for_each(|component| {
+            let item = &component.sku;
+            let quantity = component
+                .quantity
+                .checked_mul(count)
+                .ok_or_else(|| InventoryError::QuantityOverflow { item: item.to_string() })?;
+            let (reason, reference) = (Reason::Assembly, Some(sku.as_str()));
+            match assemble {
+                true => self.consume_with(item, location, quantity, None, reason, reference),
+                false => self.receive_with(item, location, quantity, None, reason, reference),
+            }
+        });
+        let result = result.and_then(|()| match assemble {
+            true => self.receive_with(&sku, location, count, None, Reason::Assembly, None),
+            false => self.consume_with(&sku, location, count, None, Reason::Assembly, None),
+        });
+        if result.is_err() {
+            self._items = items;
+            self.audit.truncate(audited);
+            self.movements.truncate(moved);
+        }
+        self.release_events(result.is_ok());
+        result
+    }
+}
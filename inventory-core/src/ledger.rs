@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+#[cfg(any(feature = "serde", feature = "sqlite"))]
+use std::collections::HashSet;
+use std::fmt;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{Collection, InventoryError, LocationId};
+
+// Every change to a quantity, kept as a movement of stock in or out and why
+// it happened. An item's movements add up to its quantity; reconcile checks
+// that they still do.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Reason {
+    Received,
+    Sold,
+    Damaged,
+    // A correction, like an overwritten quantity or a removed item.
+    Adjustment,
+    // Components going into kits or coming back out of them, and the kits
+    // made or taken apart.
+    Assembly,
+    // Stock moved from one location to another, which comes as a pair of
+    // movements that add up to nothing.
+    Transfer,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Reason::Received => "received",
+            Reason::Sold => "sold",
+            Reason::Damaged => "damaged",
+            Reason::Adjustment => "adjustment",
+            Reason::Assembly => "assembly",
+            Reason::Transfer => "transfer",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Movement {
+    pub sku: String,
+    // In the item's unit; negative when stock went out.
+    pub delta: i64,
+    pub reason: Reason,
+    pub timestamp: DateTime<Utc>,
+    // What the movement was part of, like a purchase order or a sale.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reference: Option<String>,
+    // Where the stock went out of or came into, for transfers.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub location: Option<LocationId>,
+}
+
+// An item whose quantity isn't what its movements add up to.
+pub struct Discrepancy {
+    pub sku: String,
+    pub quantity: i64,
+    pub ledger: i64,
+}
+
+impl Collection {
+    pub(crate) fn record_movement(
+        &mut self,
+        sku: &str,
+        delta: i64,
+        reason: Reason,
+        reference: Option<&str>,
+    ) {
+        self.movements.push(Movement {
+            sku: sku.to_string(),
+            delta,
+            reason,
+            timestamp: Utc::now(),
+            reference: reference.map(str::to_string),
+            location: None,
+        });
+    }
+
+    // The pair of movements of a transfer of `qty` from `from` to `to`.
+    pub(crate) fn record_transfer(
+        &mut self,
+        sku: &str,
+        qty: u32,
+        from: &LocationId,
+        to: &LocationId,
+    ) {
+        let now = Utc::now();
+        for (delta, location) in [(-i64::from(qty), from), (i64::from(qty), to)] {
+            self.movements.push(Movement {
+                sku: sku.to_string(),
+                delta,
+                reason: Reason::Transfer,
+                timestamp: now,
+                reference: None,
+                location: Some(location.clone()),
+            });
+        }
+    }
+
+    // The movements of the item with SKU or name `key`, oldest first.
+    pub fn movements(&self, key: &str) -> Result<Vec<&Movement>, InventoryError> {
+        let sku = self.resolve(key)?;
+        Ok(self.movements.iter().filter(|movement| movement.sku == sku).collect())
+    }
+
+    // Every item, including archived and removed ones, whose quantity
+    // differs from the sum of its movements. Removed items should add up to
+    // nothing.
+    pub fn reconcile(&self) -> Vec<Discrepancy> {
+        let mut ledger: HashMap<&str, i64> = HashMap::new();
+        for movement in &self.movements {
+            *ledger.entry(&movement.sku).or_insert(0) += movement.delta;
+        }
+        for sku in self._items.keys().chain(self.archived.keys()) {
+            ledger.entry(sku).or_insert(0);
+        }
+        let mut discrepancies: Vec<Discrepancy> = ledger
+            .into_iter()
+            .filter_map(|(sku, ledger)| {
+                let item = self._items.get(sku).or_else(|| self.archived.get(sku));
+                let quantity = item.map_or(0, |item| i64::from(item.quantity));
+                (quantity != ledger).then(|| Discrepancy { sku: sku.to_string(), quantity, ledger })
+            })
+            .collect();
+        discrepancies.sort_by(|a, b| a.sku.cmp(&b.sku));
+        discrepancies
+    }
+
+    // How much of each item, by SKU, was sold or damaged in the last `days`
+    // days.
+    pub fn consumption(&self, days: u32) -> HashMap<String, u32> {
+        let since = Utc::now() - TimeDelta::days(i64::from(days));
+        let mut used: HashMap<String, u32> = HashMap::new();
+        for movement in &self.movements {
+            let consumed = matches!(movement.reason, Reason::Sold | Reason::Damaged);
+            if consumed && movement.timestamp >= since && movement.delta < 0 {
+                let used = used.entry(movement.sku.to_string()).or_insert(0);
+                *used += movement.delta.unsigned_abs() as u32;
+            }
+        }
+        used
+    }
+
+    // Gives items from before the ledger was kept an opening movement for
+    // the stock they already had, so they reconcile.
+    #[cfg(any(feature = "serde", feature = "sqlite"))]
+    pub(crate) fn reindex_ledger(&mut self) {
+        let moved: HashSet<&str> =
+            self.movements.iter().map(|movement| movement.sku.as_str()).collect();
+        let mut opening: Vec<Movement> = self
+            ._items
+            .values()
+            .filter(|item| !moved.contains(item.sku.as_str()) && item.quantity > 0)
+            .map(|item| Movement {
+                sku: item.sku.to_string(),
+                delta: i64::from(item.quantity),
+                reason: Reason::Adjustment,
+                timestamp: item.created_at,
+                reference: Some(String::from("opening balance")),
+                location: None,
+            })
+            .collect();
+        opening.sort_by(|a, b| a.sku.cmp(&b.sku));
+        self.movements.extend(opening);
+    }
+}
@@ -0,0 +1,2082 @@
+// The items, the collection that holds them, and everything that can be done
+// to them. The inventory binary is only a front end over this.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+#[cfg(feature = "serde")]
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::{self, BufReader, BufWriter};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, Utc};
+use thiserror::Error;
+
+pub mod aggregate;
+pub mod alerts;
+pub mod archive;
+pub mod attributes;
+pub mod audit;
+pub mod csv_file;
+pub mod dedupe;
+#[cfg(feature = "serde")]
+pub mod events;
+pub mod fuzzy;
+pub mod history;
+pub mod kits;
+pub mod ledger;
+pub mod lots;
+pub mod purchasing;
+pub mod query;
+pub mod reorder;
+pub mod sales;
+pub mod serials;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod unit;
+
+use audit::Snapshot;
+use fuzzy::did_you_mean;
+use kits::Component;
+use ledger::{Movement, Reason};
+use lots::Lot;
+use purchasing::{OrderId, PurchaseOrder, Supplier, SupplierId};
+use sales::{Sale, SaleId};
+use unit::Unit;
+
+const HISTOGRAM_WIDTH: usize = 40;
+
+// What can go wrong when changing the collection. None of these stop the
+// program; the menu prints them and carries on.
+#[derive(Debug, Error)]
+pub enum InventoryError {
+    #[error("quantity exceeds the maximum of {max} per item")]
+    QuantityExceedsMax { max: u32 },
+    // With the names closest to `item`, when there are any close enough.
+    #[error("no item named {item}{}", did_you_mean(suggestions))]
+    ItemNotFound { item: String, suggestions: Vec<String> },
+    #[error("not enough {item}: requested {requested} but only {available} available")]
+    InsufficientStock { item: String, requested: u32, available: u32 },
+    #[error("quantity of {item} would go over the most an item can hold, {}", u32::MAX)]
+    QuantityOverflow { item: String },
+    #[error("no reservation {id}")]
+    ReservationNotFound { id: ReservationId },
+    #[error("{delimiter:?} can't be used as a field separator")]
+    InvalidDelimiter { delimiter: char },
+    #[error("{} items are named {name}, use one of {}", skus.len(), skus.join(", "))]
+    AmbiguousName { name: String, skus: Vec<String> },
+    #[error("no location named {location}")]
+    LocationNotFound { location: LocationId },
+    #[error("there is already a location named {location}")]
+    LocationExists { location: LocationId },
+    #[error("unknown unit {unit}, expected pcs, box, g, kg, ml or l")]
+    UnknownUnit { unit: String },
+    #[error("{from} can't be converted to {to}")]
+    IncompatibleUnits { from: Unit, to: Unit },
+    #[error("{amount} {from} is not a whole number of {to}")]
+    InexactConversion { amount: u32, from: Unit, to: Unit },
+    #[error("{amount} {from} is more {to} than an item can hold")]
+    ConversionOverflow { amount: u32, from: Unit, to: Unit },
+    #[error("the unit of {item} can only be changed while none is in stock")]
+    UnitChangeWithStock { item: String },
+    #[error("no supplier named {name}")]
+    SupplierNotFound { name: String },
+    #[error("there is already a supplier named {name}")]
+    SupplierExists { name: String },
+    #[error("no purchase order {id}")]
+    OrderNotFound { id: OrderId },
+    #[error("purchase order {id} has been received already")]
+    OrderAlreadyReceived { id: OrderId },
+    #[error("a purchase order needs at least one item")]
+    EmptyOrder,
+    #[error("a sale needs at least one item")]
+    EmptySale,
+    #[error("{item} is tracked by serial number, so its stock only changes a serial at a time")]
+    SerialsRequired { item: String },
+    #[error("{item} isn't tracked by serial number")]
+    SerialsNotTracked { item: String },
+    #[error("serial numbers for {item} can only be tracked while none is in stock")]
+    SerialTrackingWithStock { item: String },
+    #[error("serial number {serial} is already in use by {item}")]
+    SerialInUse { serial: String, item: String },
+    #[error("no item has serial number {serial}")]
+    SerialNotFound { serial: String },
+    #[error("receiving by serial number needs at least one serial")]
+    NoSerials,
+    #[error("{item} is tracked by lot, so its stock only comes in by receiving a lot")]
+    LotsRequired { item: String },
+    #[error("{item} isn't tracked by lot")]
+    LotsNotTracked { item: String },
+    #[error("lots of {item} can only be tracked while none is in stock")]
+    LotTrackingWithStock { item: String },
+    #[error("{item} already has a lot {lot}")]
+    LotExists { lot: String, item: String },
+    #[error("{item} can't be tracked by both serial number and lot")]
+    TrackingConflict { item: String },
+    #[error("{item} isn't a kit")]
+    NotAKit { item: String },
+    #[error("{item} can't be a component of itself")]
+    KitContainsItself { item: String },
+    #[error("stock can't be transferred from {location} to itself")]
+    SameLocation { location: LocationId },
+    #[error("{item} has stock reserved, release or commit it before archiving")]
+    ArchiveReserved { item: String },
+    #[error("no archived item named {item}")]
+    NotArchived { item: String },
+    #[error("there is already an item with SKU {sku}")]
+    SkuInUse { sku: String },
+    #[error("there is already an item named {name}, {sku}")]
+    NameInUse { name: String, sku: String },
+    #[error("an attribute needs both a name and a value")]
+    EmptyAttribute,
+    #[error("{item} has no attribute {name}")]
+    AttributeNotSet { item: String, name: String },
+    #[error("{problem} (at character {position} of the filter)")]
+    InvalidFilter { position: usize, problem: String },
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Item {
+    // Stays the same for the life of the item, unlike the name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sku: String,
+    pub name: String,
+    pub quantity: u32,
+    // What the quantity, reservations and unit price are counted in.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unit: Unit,
+    pub category: String,
+    pub reserved: u32,
+    // How much of the quantity is at each location; adds up to quantity.
+    // Files from before locations have none, and reindex puts everything at
+    // the main location.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub locations: HashMap<LocationId, u32>,
+    // In cents, so totals add up exactly.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unit_price: u64,
+    // Files from before these were kept have neither; older ones store
+    // updated_at as milliseconds since the Unix epoch.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub created_at: DateTime<Utc>,
+    // The last quantity or price change.
+    #[cfg_attr(feature = "serde", serde(default, deserialize_with = "timestamp"))]
+    pub updated_at: DateTime<Utc>,
+    // The last day the item can be used, for stock that goes off.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expires_on: Option<NaiveDate>,
+    // How low stock may get before more is ordered; None leaves it to the
+    // reorder report's default.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reorder_level: Option<u32>,
+    // The serial number of every unit in stock, for goods tracked that way;
+    // None for the rest.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub serials: Option<BTreeSet<String>>,
+    // The lots the stock came in, first to be used first, for goods tracked
+    // that way; None for the rest. They add up to quantity.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lots: Option<Vec<Lot>>,
+    // What one of the item is made of, when it's a kit; empty for the rest.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub components: Vec<Component>,
+    // Anything else worth knowing, like color or size, by normalized name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attributes: HashMap<String, String>,
+}
+
+impl Item {
+    // What is left once reservations are taken out.
+    pub fn available(&self) -> u32 {
+        self.quantity.saturating_sub(self.reserved)
+    }
+
+    // Marks the item as changed now.
+    fn touch(&mut self) {
+        self.updated_at = Utc::now();
+    }
+
+    pub fn quantity_at(&self, location: &LocationId) -> u32 {
+        self.locations.get(location).copied().unwrap_or(0)
+    }
+
+    // Changes the total quantity when no location was given. Stock that is
+    // added goes to the main location; stock that is taken comes out of the
+    // main location first, then the others in name order.
+    fn set_total(&mut self, quantity: u32) {
+        let (total, current) = (quantity, self.quantity);
+        if total >= current {
+            *self.locations.entry(LocationId::main()).or_insert(0) += total - current;
+        } else {
+            let mut excess = current - total;
+            let mut locations: Vec<LocationId> = self.locations.keys().cloned().collect();
+            locations.sort_by_key(|location| (*location != LocationId::main(), location.clone()));
+            for location in locations {
+                if let Some(held) = self.locations.get_mut(&location) {
+                    let taken = excess.min(*held);
+                    *held -= taken;
+                    excess -= taken;
+                    if *held == 0 {
+                        self.locations.remove(&location);
+                    }
+                }
+            }
+        }
+        self.quantity = quantity;
+    }
+
+    // Puts whatever part of the quantity no location accounts for at the main
+    // location.
+    fn settle_locations(&mut self) {
+        // Summed wide, as a file edited by hand could hold anything.
+        let placed: u64 = self.locations.values().map(|&held| u64::from(held)).sum();
+        let quantity = u64::from(self.quantity);
+        if placed < quantity {
+            // Less than the quantity, so it fits.
+            *self.locations.entry(LocationId::main()).or_insert(0) += (quantity - placed) as u32;
+        }
+    }
+
+    // Quantity times unit price, in cents.
+    pub fn value(&self) -> u64 {
+        u64::from(self.quantity) * self.unit_price
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReservationId(pub u64);
+
+// A place stock is kept, by name. Every collection has the main location.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocationId(pub String);
+
+impl LocationId {
+    pub fn main() -> LocationId {
+        LocationId(String::from("main"))
+    }
+}
+
+impl fmt::Display for LocationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for ReservationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added(String, u32),
+    Removed(String, u32),
+    Updated { name: String, from: u32, to: u32 },
+}
+
+impl Change {
+    fn name(&self) -> &str {
+        match self {
+            Change::Added(name, _) | Change::Removed(name, _) => name,
+            Change::Updated { name, .. } => name,
+        }
+    }
+}
+
+pub enum Operation {
+    Add { name: String, qty: u32 },
+    Remove { name: String },
+    Adjust { name: String, delta: i32 },
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Collection {
+    // Keyed by SKU.
+    #[cfg_attr(feature = "serde", serde(rename = "items"))]
+    _items: HashMap<String, Item>,
+    // The SKUs of the items with each name, by its normalized form. Derived
+    // from _items, so it is rebuilt by reindex after loading rather than
+    // saved.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    names: HashMap<String, Vec<String>>,
+    max_per_item: Option<u32>,
+    // Reservation to the SKU and quantity it holds.
+    reservations: HashMap<ReservationId, (String, u32)>,
+    next_reservation: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    next_sku: u64,
+    // Always holds the main location once loaded.
+    #[cfg_attr(feature = "serde", serde(default))]
+    locations: BTreeSet<LocationId>,
+    // Files from before purchasing have none of these; reindex sets the next
+    // IDs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    suppliers: BTreeMap<SupplierId, Supplier>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    next_supplier: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    orders: BTreeMap<OrderId, PurchaseOrder>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    next_order: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    sales: BTreeMap<SaleId, Sale>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    next_sale: u64,
+    // Files from before the ledger have none; reindex opens one for each
+    // item.
+    #[cfg_attr(feature = "serde", serde(default))]
+    movements: Vec<Movement>,
+    // Archived items, keyed by SKU. They are kept out of _items, so nothing
+    // else sees them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    archived: HashMap<String, Item>,
+    // Changes not yet written to the audit log.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    audit: Vec<audit::AuditEntry>,
+    // How times are shown; a setting of the program using the collection, so
+    // it isn't saved.
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_date_format"))]
+    date_format: String,
+    // Likewise settings of the program: who is told of stock events, and
+    // when items count as low on stock for them.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    listeners: Vec<alerts::Listener>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    low_stock_threshold: Option<u32>,
+    // Stock events from the batch being applied, if one is; see apply_all.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    held_events: Option<Vec<alerts::StockEvent>>,
+}
+
+impl Collection {
+
+    pub fn new() -> Self {
+        Collection::with_capacity(0)
+    }
+
+    // Preallocates room for `n` items, e.g. when the row count of an import is known.
+    pub fn with_capacity(n: usize) -> Self {
+        Collection {
+            _items: HashMap::with_capacity(n),
+            names: HashMap::new(),
+            max_per_item: None,
+            reservations: HashMap::new(),
+            next_reservation: 1,
+            next_sku: 1,
+            locations: BTreeSet::from([LocationId::main()]),
+            suppliers: BTreeMap::new(),
+            next_supplier: 1,
+            orders: BTreeMap::new(),
+            next_order: 1,
+            sales: BTreeMap::new(),
+            next_sale: 1,
+            movements: Vec::new(),
+            archived: HashMap::new(),
+            audit: Vec::new(),
+            date_format: default_date_format(),
+            listeners: Vec::new(),
+            low_stock_threshold: None,
+            held_events: None,
+        }
+    }
+
+    pub fn set_max_per_item(&mut self, max: Option<u32>) {
+        self.max_per_item = max;
+    }
+
+    // A chrono format string used by format_time, like DEFAULT_DATE_FORMAT.
+    pub fn set_date_format(&mut self, format: String) {
+        self.date_format = format;
+    }
+
+    // Replaces the collection with `other`, e.g. one loaded again, keeping
+    // the settings of the program: the date format and the stock event
+    // listeners and threshold.
+    pub fn replace(&mut self, mut other: Collection) {
+        other.date_format = std::mem::take(&mut self.date_format);
+        other.listeners = std::mem::take(&mut self.listeners);
+        other.low_stock_threshold = self.low_stock_threshold;
+        *self = other;
+    }
+
+    // Formats a time in the local time zone with the collection's date format.
+    // The Unix epoch stands for a time that was never recorded.
+    pub fn format_time(&self, time: DateTime<Utc>) -> String {
+        if time == DateTime::UNIX_EPOCH {
+            return String::from("unknown");
+        }
+        time.with_timezone(&Local).format(&self.date_format).to_string()
+    }
+
+    // Sets or, with None, clears the day an item expires.
+    pub fn set_expiry(
+        &mut self,
+        key: &str,
+        expires_on: Option<NaiveDate>,
+    ) -> Result<(), InventoryError> {
+        self.item_mut(key)?.expires_on = expires_on;
+        Ok(())
+    }
+
+    // Sets or, with None, clears how low an item's stock may get before the
+    // reorder report suggests ordering more.
+    pub fn set_reorder_level(
+        &mut self,
+        key: &str,
+        level: Option<u32>,
+    ) -> Result<(), InventoryError> {
+        self.item_mut(key)?.reorder_level = level;
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(s: &str) -> serde_json::Result<Collection> {
+        let mut collection: Collection = serde_json::from_str(s)?;
+        collection.reindex();
+        Ok(collection)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn load_from_file(path: &Path) -> io::Result<Collection> {
+        let file = File::open(path)?;
+        let mut collection: Collection = serde_json::from_reader(BufReader::new(file))?;
+        collection.reindex();
+        Ok(collection)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Item> {
+        self._items.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self._items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self._items.is_empty()
+    }
+
+    // Looks an item up by SKU, or by name when only one item has it.
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        self.lookup(key).ok()
+    }
+
+    // Like get, but says why nothing was found.
+    pub fn lookup(&self, key: &str) -> Result<&Item, InventoryError> {
+        let sku = self.resolve(key)?;
+        Ok(&self._items[&sku])
+    }
+
+    // The SKU `key` refers to: `key` itself when it is a SKU, otherwise the
+    // SKU of the only item named `key`, ignoring case and surrounding spaces.
+    pub fn resolve(&self, key: &str) -> Result<String, InventoryError> {
+        if self._items.contains_key(key.trim()) {
+            return Ok(key.trim().to_string());
+        }
+        match self.names.get(&normalize(key)).map(Vec::as_slice) {
+            Some([sku]) => Ok(sku.to_string()),
+            Some(skus) if !skus.is_empty() => Err(InventoryError::AmbiguousName {
+                name: key.to_string(),
+                skus: skus.to_vec(),
+            }),
+            _ => Err(InventoryError::ItemNotFound {
+                item: key.to_string(),
+                suggestions: self.closest_names(key),
+            }),
+        }
+    }
+
+    fn item_mut(&mut self, key: &str) -> Result<&mut Item, InventoryError> {
+        let sku = self.resolve(key)?;
+        Ok(self._items.get_mut(&sku).expect("resolve only returns stored SKUs"))
+    }
+
+    // Stores an item whose SKU came from outside, e.g. an import, replacing
+    // any item with that SKU but keeping what it had reserved.
+    fn put_item(&mut self, mut item: Item) -> Result<(), InventoryError> {
+        self.check_max(item.quantity)?;
+        self.check_name(&item.name, Some(&item.sku))?;
+        if let Some(n) = sku_number(&item.sku) {
+            self.next_sku = self.next_sku.max(n + 1);
+        }
+        match self._items.get(&item.sku) {
+            Some(existing) => {
+                item.reserved = existing.reserved;
+                item.created_at = existing.created_at;
+                item.expires_on = existing.expires_on;
+                item.reorder_level = existing.reorder_level;
+                item.serials = existing.serials.clone();
+                item.lots = existing.lots.clone();
+                item.components = existing.components.clone();
+                // What the new item says wins, but what it leaves out stays.
+                let attributes = std::mem::take(&mut item.attributes);
+                item.attributes = existing.attributes.clone();
+                item.attributes.extend(attributes);
+                if item.quantity != existing.quantity {
+                    existing.check_unserialized()?;
+                    existing.check_unlotted()?;
+                }
+                // The new quantity is taken as a change from the old one.
+                let quantity = item.quantity;
+                item.locations = existing.locations.clone();
+                item.quantity = existing.quantity;
+                item.set_total(quantity);
+            }
+            None => {
+                item.created_at = Utc::now();
+                item.settle_locations();
+            }
+        }
+        item.touch();
+        let (sku, name) = (item.sku.to_string(), item.name.to_string());
+        let before = self.insert(item);
+        self.record_change(&sku, &name, before.as_ref().map(Snapshot::of));
+        Ok(())
+    }
+
+    fn new_sku(&mut self) -> String {
+        let sku = format!("SKU-{:06}", self.next_sku);
+        self.next_sku += 1;
+        sku
+    }
+
+    // Fails if an item other than `sku` goes by `name`, ignoring case and
+    // surrounding spaces, as no two items may.
+    fn check_name(&self, name: &str, sku: Option<&str>) -> Result<(), InventoryError> {
+        let skus = self.names.get(&normalize(name)).into_iter().flatten();
+        match skus.into_iter().find(|other| Some(other.as_str()) != sku) {
+            Some(other) => Err(InventoryError::NameInUse {
+                name: self._items[other].name.to_string(),
+                sku: other.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // Stores the item under its SKU, replacing any item with the same SKU,
+    // and keeps the name index in step. Callers check the name is free with
+    // check_name first.
+    fn insert(&mut self, mut item: Item) -> Option<Item> {
+        let before = self.take(&item.sku.to_string());
+        item.name = item.name.trim().to_string();
+        let key = normalize(&item.name);
+        let skus = self.names.entry(key).or_default();
+        skus.push(item.sku.to_string());
+        skus.sort();
+        self._items.insert(item.sku.to_string(), item);
+        before
+    }
+
+    fn take(&mut self, sku: &str) -> Option<Item> {
+        let item = self._items.remove(sku)?;
+        let key = normalize(&item.name);
+        if let Some(skus) = self.names.get_mut(&key) {
+            skus.retain(|other| other != sku);
+            if skus.is_empty() {
+                self.names.remove(&key);
+            }
+        }
+        Some(item)
+    }
+
+    // Rebuilds the name index after loading, giving a SKU, a creation time and
+    // locations to any item saved before items had them, and moves next_sku
+    // past every SKU in use.
+    #[cfg(any(feature = "serde", feature = "sqlite"))]
+    fn reindex(&mut self) {
+        let items: Vec<Item> = self._items.drain().map(|(_, item)| item).collect();
+        self.names.clear();
+        // Files from before SKUs have no next_sku, which loads as 0.
+        self.next_sku = self.next_sku.max(1);
+        for item in items.iter().chain(self.archived.values()) {
+            if let Some(n) = sku_number(&item.sku) {
+                self.next_sku = self.next_sku.max(n + 1);
+            }
+        }
+        let mut items = items;
+        for item in &mut items {
+            if item.created_at == DateTime::UNIX_EPOCH {
+                item.created_at = item.updated_at;
+            }
+        }
+        // Oldest first, so that of items whose names differ only in case or
+        // surrounding spaces, which files from before names were normalized
+        // can hold, the oldest keeps the name.
+        items.sort_by(|a, b| (a.created_at, &a.sku).cmp(&(b.created_at, &b.sku)));
+        self.locations.insert(LocationId::main());
+        self.reindex_purchasing();
+        self.reindex_sales();
+        let mut collided = Vec::new();
+        for mut item in items {
+            item.settle_locations();
+            item.settle_lots();
+            self.locations.extend(item.locations.keys().cloned());
+            if item.sku.is_empty() {
+                let sku = self.new_sku();
+                // Reservations used to be keyed by name.
+                for (reserved, _) in self.reservations.values_mut() {
+                    if *reserved == item.name {
+                        *reserved = sku.to_string();
+                    }
+                }
+                item.sku = sku;
+            }
+            // Stored apart for now, under a name of its own.
+            if let Err(InventoryError::NameInUse { sku, .. }) = self.check_name(&item.name, None) {
+                item.name = format!("{} ({})", item.name.trim(), item.sku);
+                collided.push((sku, item.sku.to_string()));
+            }
+            self.insert(item);
+        }
+        self.reindex_ledger();
+        // Merged into the item that kept the name, as dedupe would. Those that
+        // can't be, e.g. for their units, stay apart with their SKUs in their
+        // names.
+        for (keep, other) in collided {
+            if let Err(e) = self.merge_items(&keep, &other) {
+                tracing::warn!(keep, other, error = %e, "kept an item whose name was taken apart");
+            }
+        }
+    }
+
+    fn check_max(&self, quantity: u32) -> Result<(), InventoryError> {
+        match self.max_per_item {
+            Some(max) if quantity > max => {
+                Err(InventoryError::QuantityExceedsMax { max })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Adds a new item under a fresh SKU and returns the SKU. It fails if
+    // another item has the name, ignoring case and surrounding spaces.
+    #[tracing::instrument(skip_all, fields(name = %name, quantity = quantity))]
+    pub fn add_item(
+        &mut self,
+        name: String,
+        quantity: u32,
+        unit: Unit,
+        category: String,
+        unit_price: u64,
+    ) -> Result<String, InventoryError> {
+        self.check_max(quantity)?;
+        self.check_name(&name, None)?;
+
+        let sku = self.new_sku();
+        let mut item = Item {
+            sku: sku.to_string(),
+            name: name.to_string(),
+            quantity: 0,
+            unit,
+            category: category.to_string(),
+            reserved: 0,
+            locations: HashMap::new(),
+            unit_price,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            expires_on: None,
+            reorder_level: None,
+            serials: None,
+            lots: None,
+            components: Vec::new(),
+            attributes: HashMap::new(),
+        };
+        item.set_total(quantity);
+
+        self.insert(item);
+        let name = self._items[&sku].name.to_string();
+        self.record_change_as(&sku, &name, None, Reason::Received, None);
+        Ok(sku)
+    }
+    
+    // Sets the quantity, and the unit price too when one is given.
+    #[tracing::instrument(skip_all, fields(key = %key, quantity = quantity))]
+    pub fn update_item(
+        &mut self,
+        key: String,
+        quantity: u32,
+        unit_price: Option<u64>,
+    ) -> Result<(), InventoryError> {
+        self.check_max(quantity)?;
+
+        let item = self.item_mut(&key)?;
+        if item.quantity != quantity {
+            item.check_unserialized()?;
+            item.check_unlotted()?;
+        }
+        let before = Snapshot::of(item);
+        item.set_total(quantity);
+        if let Some(unit_price) = unit_price {
+            item.unit_price = unit_price;
+        }
+        item.touch();
+        let (sku, name) = (item.sku.to_string(), item.name.to_string());
+        self.record_change(&sku, &name, Some(before));
+        Ok(())
+    }
+    
+    // Adds `qty` to the stock of an item at `location`, e.g. when a delivery
+    // arrives. `qty` is in `unit`, or in the item's own unit when that is None.
+    #[tracing::instrument(skip_all, fields(key = key, location = %location, qty = qty))]
+    pub fn receive(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        qty: u32,
+        unit: Option<Unit>,
+    ) -> Result<(), InventoryError> {
+        self.receive_with(key, location, qty, unit, Reason::Received, None)
+    }
+
+    // Like receive, noting in the ledger why the stock came in and what for.
+    pub(crate) fn receive_with(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        qty: u32,
+        unit: Option<Unit>,
+        reason: Reason,
+        reference: Option<&str>,
+    ) -> Result<(), InventoryError> {
+        self.check_location(location)?;
+        let item = self.lookup(key)?;
+        item.check_unserialized()?;
+        item.check_unlotted()?;
+        let qty = in_unit_of(item, qty, unit)?;
+        let quantity = item
+            .quantity
+            .checked_add(qty)
+            .ok_or_else(|| InventoryError::QuantityOverflow { item: key.to_string() })?;
+        self.check_max(quantity)?;
+
+        let item = self.item_mut(key)?;
+        let before = Snapshot::of(item);
+        *item.locations.entry(location.clone()).or_insert(0) += qty;
+        item.quantity = quantity;
+        item.touch();
+        let (sku, name) = (item.sku.to_string(), item.name.to_string());
+        self.record_change_as(&sku, &name, Some(before), reason, reference);
+        Ok(())
+    }
+
+    // Takes `qty` out of the stock of an item at `location`, in `unit` as for
+    // receive, and notes why in the ledger. Reserved stock can't be consumed
+    // this way; commit the reservation instead.
+    #[tracing::instrument(
+        skip_all,
+        fields(key = key, location = %location, qty = qty, reason = %reason)
+    )]
+    pub fn consume(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        qty: u32,
+        unit: Option<Unit>,
+        reason: Reason,
+    ) -> Result<(), InventoryError> {
+        self.consume_with(key, location, qty, unit, reason, None)
+    }
+
+    // Like consume, noting in the ledger what the stock went to.
+    pub(crate) fn consume_with(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        qty: u32,
+        unit: Option<Unit>,
+        reason: Reason,
+        reference: Option<&str>,
+    ) -> Result<(), InventoryError> {
+        self.check_location(location)?;
+        let item = self.lookup(key)?;
+        item.check_unserialized()?;
+        let qty = in_unit_of(item, qty, unit)?;
+        let available = item.quantity_at(location).min(item.available());
+        if qty > available {
+            return Err(InventoryError::InsufficientStock {
+                item: key.to_string(),
+                requested: qty,
+                available,
+            });
+        }
+
+        let item = self.item_mut(key)?;
+        let before = Snapshot::of(item);
+        if let Some(held) = item.locations.get_mut(location) {
+            *held -= qty;
+            if *held == 0 {
+                item.locations.remove(location);
+            }
+        }
+        // qty <= available, so this can't go below zero.
+        item.quantity -= qty;
+        item.take_lots(qty);
+        item.touch();
+        let (sku, name) = (item.sku.to_string(), item.name.to_string());
+        self.record_change_as(&sku, &name, Some(before), reason, reference);
+        Ok(())
+    }
+
+    // Moves `qty` of an item from one location to another, all of it or none
+    // when `from` doesn't have that much. The quantity stays the same, so
+    // only the ledger notes it, as a movement out of `from` and one into
+    // `to`. Reservations aren't held at a location, so any stock can move.
+    #[tracing::instrument(skip_all, fields(key = key, qty = qty, from = %from, to = %to))]
+    pub fn transfer(
+        &mut self,
+        key: &str,
+        qty: u32,
+        from: &LocationId,
+        to: &LocationId,
+    ) -> Result<(), InventoryError> {
+        self.check_location(from)?;
+        self.check_location(to)?;
+        if from == to {
+            return Err(InventoryError::SameLocation { location: from.clone() });
+        }
+        let item = self.lookup(key)?;
+        let available = item.quantity_at(from);
+        if qty > available {
+            return Err(InventoryError::InsufficientStock {
+                item: key.to_string(),
+                requested: qty,
+                available,
+            });
+        }
+
+        let item = self.item_mut(key)?;
+        if let Some(held) = item.locations.get_mut(from) {
+            *held -= qty;
+            if *held == 0 {
+                item.locations.remove(from);
+            }
+        }
+        *item.locations.entry(to.clone()).or_insert(0) += qty;
+        let sku = item.sku.to_string();
+        self.record_transfer(&sku, qty, from, to);
+        Ok(())
+    }
+
+    // Changes what an item is counted in. Only allowed while none is in
+    // stock, so no quantity has to be converted.
+    pub fn set_unit(&mut self, key: &str, unit: Unit) -> Result<(), InventoryError> {
+        let item = self.item_mut(key)?;
+        if item.quantity > 0 {
+            return Err(InventoryError::UnitChangeWithStock { item: key.to_string() });
+        }
+        item.unit = unit;
+        Ok(())
+    }
+
+    pub fn add_location(&mut self, location: LocationId) -> Result<(), InventoryError> {
+        if self.locations.contains(&location) {
+            return Err(InventoryError::LocationExists { location });
+        }
+        self.locations.insert(location);
+        Ok(())
+    }
+
+    // Every location, in name order.
+    pub fn locations(&self) -> impl Iterator<Item = &LocationId> {
+        self.locations.iter()
+    }
+
+    fn check_location(&self, location: &LocationId) -> Result<(), InventoryError> {
+        match self.locations.contains(location) {
+            true => Ok(()),
+            false => Err(InventoryError::LocationNotFound { location: location.clone() }),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub fn apply(&mut self, op: &Operation) -> Result<(), InventoryError> {
+        match op {
+            Operation::Add { name, qty } => self
+                .add_item(name.to_string(), *qty, Unit::Pieces, String::from("uncategorized"), 0)
+                .map(|_| ()),
+            Operation::Remove { name } => self.remove_item(name).map(|_| ()),
+            Operation::Adjust { name, delta } => {
+                let item = &self._items[&self.resolve(name)?];
+                item.check_unserialized()?;
+                item.check_unlotted()?;
+                let adjusted = i64::from(item.quantity) + i64::from(*delta);
+                if adjusted < 0 {
+                    return Err(InventoryError::InsufficientStock {
+                        item: name.to_string(),
+                        requested: delta.unsigned_abs(),
+                        available: item.quantity,
+                    });
+                }
+                let quantity = u32::try_from(adjusted)
+                    .map_err(|_| InventoryError::QuantityOverflow { item: name.to_string() })?;
+                self.check_max(quantity)?;
+                let before = Snapshot::of(item);
+                let item = self.item_mut(name)?;
+                item.set_total(quantity);
+                item.touch();
+                let (sku, name) = (item.sku.to_string(), item.name.to_string());
+                self.record_change(&sku, &name, Some(before));
+                Ok(())
+            }
+        }
+    }
+
+    // Applies the operations in order. If one fails, the ones before it are
+    // rolled back and the index of the failing operation is returned with its
+    // error, so the batch either fully applies or leaves nothing behind.
+    pub fn apply_all(&mut self, ops: &[Operation]) -> Result<(), (usize, InventoryError)> {
+        let items = self._items.clone();
+        let names = self.names.clone();
+        let reservations = self.reservations.clone();
+        let next_sku = self.next_sku;
+        let audited = self.audit.len();
+        let moved = self.movements.len();
+        self.hold_events();
+        for (i, op) in ops.iter().enumerate() {
+            if let Err(e) = self.apply(op) {
+                self.release_events(false);
+                self._items = items;
+                self.names = names;
+                self.reservations = reservations;
+                self.next_sku = next_sku;
+                self.audit.truncate(audited);
+                self.movements.truncate(moved);
+                return Err((i, e));
+            }
+        }
+        self.release_events(true);
+        Ok(())
+    }
+
+    // Removes the item and any reservations held on it, and returns it.
+    #[tracing::instrument(skip_all, fields(key = key))]
+    pub fn remove_item(&mut self, key: &str) -> Result<Item, InventoryError> {
+        let sku = self.resolve(key)?;
+        let item = self.take(&sku).expect("resolve only returns stored SKUs");
+        self.reservations.retain(|_, (reserved, _)| *reserved != sku);
+        self.record_change(&sku, &item.name, Some(Snapshot::of(&item)));
+        Ok(item)
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub fn clear(&mut self) {
+        let removed: Vec<Item> = self._items.drain().map(|(_, item)| item).collect();
+        self.names.clear();
+        self.reservations.clear();
+        for item in removed {
+            self.record_change(&item.sku, &item.name, Some(Snapshot::of(&item)));
+        }
+    }
+
+    // Either every line of the order is fulfilled or nothing is touched: all
+    // lines are checked against the stock before any quantity is decremented.
+    #[tracing::instrument(skip_all, fields(lines = order.len()))]
+    pub fn fulfill(&mut self, order: &[(String, u32)]) -> Result<(), InventoryError> {
+        self.fulfill_with(order, None)
+    }
+
+    // Like fulfill, noting in the ledger what the stock was sold as.
+    pub(crate) fn fulfill_with(
+        &mut self,
+        order: &[(String, u32)],
+        reference: Option<&str>,
+    ) -> Result<(), InventoryError> {
+        let mut requested: HashMap<String, u32> = HashMap::new();
+        for (name, quantity) in order {
+            let sku = self.resolve(name)?;
+            let item = &self._items[&sku];
+            item.check_unserialized()?;
+            let total = requested.entry(sku).or_insert(0);
+            *total = total.saturating_add(*quantity);
+
+            let available = item.available();
+            if *total > available {
+                return Err(InventoryError::InsufficientStock {
+                    item: name.to_string(),
+                    requested: *total,
+                    available,
+                });
+            }
+        }
+
+        for (sku, quantity) in requested {
+            if let Some(item) = self._items.get_mut(&sku) {
+                let before = Snapshot::of(item);
+                // quantity <= item.quantity was checked above.
+                item.set_total(item.quantity - quantity);
+                item.take_lots(quantity);
+                item.touch();
+                let name = item.name.to_string();
+                self.record_change_as(&sku, &name, Some(before), Reason::Sold, reference);
+            }
+        }
+        Ok(())
+    }
+
+    // Holds `qty` of an item back from everything else until the reservation
+    // is released (stock returns) or committed (stock leaves for good).
+    #[tracing::instrument(skip_all, fields(name = name, qty = qty))]
+    pub fn reserve(&mut self, name: &str, qty: u32) -> Result<ReservationId, InventoryError> {
+        let item = self.item_mut(name)?;
+        item.check_unserialized()?;
+
+        let available = item.available();
+        if qty > available {
+            return Err(InventoryError::InsufficientStock {
+                item: name.to_string(),
+                requested: qty,
+                available,
+            });
+        }
+        // qty <= available, so the reserved stock stays within the quantity.
+        item.reserved += qty;
+        let sku = item.sku.to_string();
+
+        let id = ReservationId(self.next_reservation);
+        self.next_reservation += 1;
+        self.reservations.insert(id, (sku, qty));
+        Ok(id)
+    }
+
+    #[tracing::instrument(skip_all, fields(id = %id))]
+    pub fn release(&mut self, id: ReservationId) -> Result<(), InventoryError> {
+        let (sku, qty) = self
+            .reservations
+            .remove(&id)
+            .ok_or(InventoryError::ReservationNotFound { id })?;
+        if let Some(item) = self._items.get_mut(&sku) {
+            item.reserved = item.reserved.saturating_sub(qty);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(id = %id))]
+    pub fn commit(&mut self, id: ReservationId) -> Result<(), InventoryError> {
+        let (sku, qty) = self
+            .reservations
+            .remove(&id)
+            .ok_or(InventoryError::ReservationNotFound { id })?;
+        if let Some(item) = self._items.get_mut(&sku) {
+            let before = Snapshot::of(item);
+            item.reserved = item.reserved.saturating_sub(qty);
+            item.take_lots(qty.min(item.quantity));
+            item.set_total(item.quantity.saturating_sub(qty));
+            item.touch();
+            let name = item.name.to_string();
+            let reference = id.to_string();
+            self.record_change_as(&sku, &name, Some(before), Reason::Sold, Some(&reference));
+        }
+        Ok(())
+    }
+
+    // Folds `other` into this collection by name, summing the quantities of
+    // items both contain; the other items are added under new SKUs. Every sum
+    // is checked first, so an error leaves self unchanged.
+    #[tracing::instrument(skip_all, fields(items = other.len()))]
+    pub fn merge(&mut self, other: Collection) -> Result<(), InventoryError> {
+        for item in other.iter() {
+            let merged = match self.names.get(&normalize(&item.name)) {
+                Some(_) => {
+                    let existing = &self._items[&self.resolve(&item.name)?];
+                    existing.check_unserialized()?;
+                    existing.check_unlotted()?;
+                    item.check_unserialized()?;
+                    item.check_unlotted()?;
+                    existing.quantity.checked_add(item.quantity).ok_or_else(|| {
+                        InventoryError::QuantityOverflow { item: item.name.to_string() }
+                    })?
+                }
+                None => {
+                    for serial in item.serials.iter().flatten() {
+                        if let Ok(owner) = self.find_serial(serial) {
+                            return Err(InventoryError::SerialInUse {
+                                serial: serial.to_string(),
+                                item: owner.name.to_string(),
+                            });
+                        }
+                    }
+                    item.quantity
+                }
+            };
+            self.check_max(merged)?;
+        }
+
+        for (_, mut item) in other._items {
+            match self.item_mut(&item.name) {
+                // Checked above, so the sum can't overflow here.
+                Ok(existing) => {
+                    let before = Snapshot::of(existing);
+                    existing.set_total(existing.quantity + item.quantity);
+                    existing.touch();
+                    let sku = existing.sku.to_string();
+                    self.record_change(&sku, &item.name, Some(before));
+                }
+                Err(_) => {
+                    item.sku = self.new_sku();
+                    item.created_at = Utc::now();
+                    // Its reservations stay behind in `other`, so nothing is
+                    // held here.
+                    item.reserved = 0;
+                    item.settle_locations();
+                    self.locations.extend(item.locations.keys().cloned());
+                    item.touch();
+                    let (sku, name) = (item.sku.to_string(), item.name.to_string());
+                    self.insert(item);
+                    self.record_change(&sku, &name, None);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The changes that turn self into `other`, sorted by item name. Items with
+    // the same quantity in both produce no entry.
+    pub fn diff(&self, other: &Collection) -> Vec<Change> {
+        let mut changes: Vec<Change> = Vec::new();
+        for item in self.iter() {
+            let from = item.quantity;
+            match other._items.get(&item.sku) {
+                None => changes.push(Change::Removed(item.name.to_string(), from)),
+                Some(now) if now.quantity != item.quantity => changes.push(Change::Updated {
+                    name: item.name.to_string(),
+                    from,
+                    to: now.quantity,
+                }),
+                Some(_) => {}
+            }
+        }
+        for item in other.iter() {
+            if !self._items.contains_key(&item.sku) {
+                changes.push(Change::Added(item.name.to_string(), item.quantity));
+            }
+        }
+
+        changes.sort_by(|a, b| a.name().cmp(b.name()));
+        changes
+    }
+
+    // The quantity shared by the most items, and how many items share it. Ties
+    // go to the smaller quantity so the answer doesn't depend on map order.
+    pub fn most_common_quantity(&self) -> Option<(u32, usize)> {
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for item in self.iter() {
+            *counts.entry(item.quantity).or_insert(0) += 1;
+        }
+        counts
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
+
+    // Items whose quantity differs from a prior count, with the signed change.
+    // Items counted before but gone now show up with a negative delta.
+    // Counts are by name, ignoring case and surrounding spaces, and changes
+    // are named as the item is now, or as the count had it for items gone.
+    pub fn changed_since(&self, baseline: &HashMap<String, u32>) -> Vec<(String, i64)> {
+        // By normalized name, the spelling and the quantities before and now.
+        let mut counts: HashMap<String, (&str, i64, i64)> = HashMap::new();
+        for (name, &before) in baseline {
+            counts.entry(normalize(name)).or_insert((name, 0, 0)).1 += i64::from(before);
+        }
+        for item in self.iter() {
+            let count = counts.entry(normalize(&item.name)).or_insert((&item.name, 0, 0));
+            count.0 = &item.name;
+            count.2 += i64::from(item.quantity);
+        }
+        let mut changes: Vec<(String, i64)> = counts
+            .into_values()
+            .filter(|(_, before, now)| before != now)
+            .map(|(name, before, now)| (name.to_string(), now - before))
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    // For every item below `target`, how many more are needed to reach it.
+    pub fn restock_plan(&self, target: u32) -> Vec<(String, u32)> {
+        let mut plan: Vec<(String, u32)> = self
+            .iter()
+            .filter(|item| item.quantity < target)
+            .map(|item| (item.name.to_string(), target - item.quantity))
+            .collect();
+        plan.sort();
+        plan
+    }
+
+    // Every item in the given order.
+    pub fn sorted(&self, order: SortOrder) -> Vec<&Item> {
+        let mut items: Vec<&Item> = self.iter().collect();
+        items.sort_by_key(|&item| (&item.name, &item.sku));
+        // The sorts are stable, so ties stay in name order.
+        match order {
+            SortOrder::Name => {}
+            SortOrder::Quantity => items.sort_by_key(|item| item.quantity),
+            SortOrder::QuantityDesc => items.sort_by_key(|item| Reverse(item.quantity)),
+            SortOrder::Updated => items.sort_by_key(|item| Reverse(item.updated_at)),
+        }
+        items
+    }
+
+    // Returns page `page` (counting from 1) of the items sorted by name, and the
+    // total number of pages. A page past the end comes back empty.
+    pub fn list_page(&self, page: usize, per_page: usize) -> (Vec<&Item>, usize) {
+        let items = self.sorted(SortOrder::Name);
+        let (shown, total_pages) = page_of(&items, page, per_page);
+        (shown.to_vec(), total_pages)
+    }
+
+    // Items whose name contains `query`, ignoring case, sorted by name.
+    pub fn search(&self, query: &str) -> Vec<&Item> {
+        let query = normalize(query);
+        let mut items: Vec<&Item> =
+            self.iter().filter(|item| normalize(&item.name).contains(&query)).collect();
+        items.sort_by_key(|&item| (&item.name, &item.sku));
+        items
+    }
+
+    pub fn list_by_category(&self, category: &str) -> Vec<&Item> {
+        let mut items: Vec<&Item> = self.iter().filter(|item| item.category == category).collect();
+        items.sort_by_key(|&item| (&item.name, &item.sku));
+        items
+    }
+
+    // An aligned table of the items. With `low_stock` set, rows are colored
+    // for a terminal: red when out of stock and yellow at or below it.
+    pub fn to_table(&self, order: SortOrder, low_stock: Option<u32>) -> String {
+        self.table_of(&self.sorted(order), low_stock)
+    }
+
+    // Like to_table, for just `items` in the order given. When any of them
+    // has attributes, they are shown in a last column.
+    pub fn table_of(&self, items: &[&Item], low_stock: Option<u32>) -> String {
+        if items.is_empty() {
+            return String::from("(no items)\n");
+        }
+        let attributes: Vec<String> = items
+            .iter()
+            .map(|item| {
+                let attributes = item.attributes().into_iter();
+                attributes.map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>()
+            })
+            .map(|attributes| attributes.join(", "))
+            .collect();
+        let with_attributes = attributes.iter().any(|attributes| !attributes.is_empty());
+        let updated: Vec<String> =
+            items.iter().map(|item| self.format_time(item.updated_at)).collect();
+        // Only padded when a column follows it.
+        let updated_width = match with_attributes {
+            true => updated.iter().map(|time| time.chars().count()).max().unwrap_or(0),
+            false => 0,
+        };
+
+        let name_width = items
+            .iter()
+            .map(|item| item.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Name".len());
+        let category_width = items
+            .iter()
+            .map(|item| item.category.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Category".len());
+
+        let mut table = format!(
+            "{:<10}  {:<name_width$}  {:>8}  {:<4}  {:<category_width$}  {:>10}  {}",
+            "SKU", "Name", "Quantity", "Unit", "Category", "Unit price", "Updated",
+        );
+        if with_attributes {
+            let padding = updated_width.saturating_sub("Updated".len());
+            table.push_str(&format!("{}  Attributes", " ".repeat(padding)));
+        }
+        table.push('\n');
+        for ((item, updated), attributes) in items.iter().zip(&updated).zip(&attributes) {
+            let mut row = format!(
+                "{:<10}  {:<name_width$}  {:>8}  {:<4}  {:<category_width$}  {:>10}  \
+                 {:<updated_width$}",
+                item.sku,
+                item.name,
+                item.quantity,
+                item.unit,
+                item.category,
+                format_price(item.unit_price),
+                updated,
+            );
+            match attributes.is_empty() {
+                true => row.truncate(row.trim_end().len()),
+                false => row.push_str(&format!("  {}", attributes)),
+            }
+            // The codes go around the padded row so the columns still line up.
+            let color = match low_stock {
+                Some(_) if item.quantity == 0 => Some(RED),
+                Some(low_stock) if item.quantity <= low_stock => Some(YELLOW),
+                _ => None,
+            };
+            match color {
+                Some(color) => table.push_str(&format!("{}{}{}\n", color, row, RESET)),
+                None => table.push_str(&format!("{}\n", row)),
+            }
+        }
+        table
+    }
+
+    // Items whose quantity and price haven't changed in the last `days` days,
+    // the longest untouched first.
+    pub fn stale(&self, days: u32) -> Vec<&Item> {
+        let cutoff = Utc::now() - TimeDelta::days(i64::from(days));
+        let mut items: Vec<&Item> = self.iter().filter(|item| item.updated_at < cutoff).collect();
+        items.sort_by_key(|&item| (item.updated_at, &item.name, &item.sku));
+        items
+    }
+
+    // Items that expire in the next `days` days, including any that already
+    // have, soonest first.
+    pub fn expiring_within(&self, days: u32) -> Vec<&Item> {
+        let cutoff = today() + TimeDelta::days(i64::from(days));
+        self.expiring_before(cutoff + TimeDelta::days(1))
+    }
+
+    // Items whose expiry day has passed, longest expired first.
+    pub fn expired(&self) -> Vec<&Item> {
+        self.expiring_before(today())
+    }
+
+    fn expiring_before(&self, day: NaiveDate) -> Vec<&Item> {
+        let mut items: Vec<&Item> = self
+            .iter()
+            .filter(|item| item.expires_on.is_some_and(|expires_on| expires_on < day))
+            .collect();
+        items.sort_by_key(|&item| (item.expires_on, &item.name, &item.sku));
+        items
+    }
+
+    // Each location with the total it holds and the items there, then the
+    // total across all of them.
+    pub fn location_report(&self) -> String {
+        let mut items: Vec<&Item> = self.iter().collect();
+        items.sort_by_key(|&item| (&item.name, &item.sku));
+
+        let mut out = String::new();
+        let mut total: u64 = 0;
+        for location in &self.locations {
+            let held: Vec<(&Item, u32)> = items
+                .iter()
+                .map(|&item| (item, item.quantity_at(location)))
+                .filter(|&(_, quantity)| quantity > 0)
+                .collect();
+            let sum: u64 = held.iter().map(|&(_, quantity)| u64::from(quantity)).sum();
+            total += sum;
+            out.push_str(&format!("{}: {}\n", location, sum));
+            for (item, quantity) in held {
+                out.push_str(&format!("  {} ({}): {}\n", item.name, item.sku, quantity));
+            }
+        }
+        out.push_str(&format!("Total: {}\n", total));
+        out
+    }
+
+    // Total value of everything in stock, in cents.
+    pub fn total_value(&self) -> u64 {
+        self.iter().map(Item::value).sum()
+    }
+
+    // Stock value per category, sorted by category name.
+    pub fn value_by_category(&self) -> Vec<(String, u64)> {
+        let mut values: HashMap<&str, u64> = HashMap::new();
+        for item in self.iter() {
+            *values.entry(&item.category).or_insert(0) += item.value();
+        }
+        let mut values: Vec<(String, u64)> =
+            values.into_iter().map(|(category, value)| (category.to_string(), value)).collect();
+        values.sort();
+        values
+    }
+
+    // The `n` items with the highest stock value, most valuable first.
+    pub fn most_valuable(&self, n: usize) -> Vec<&Item> {
+        let mut items: Vec<&Item> = self.iter().collect();
+        items.sort_by(|a, b| b.value().cmp(&a.value()).then(a.name.cmp(&b.name)));
+        items.truncate(n);
+        items
+    }
+
+    // Checks the invariants the rest of the code relies on. Meant to be run
+    // after loading data that didn't go through add_item.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut keys: Vec<&String> = self._items.keys().collect();
+        keys.sort();
+
+        let mut problems: Vec<String> = Vec::new();
+        let mut owners: HashMap<&String, &String> = HashMap::new();
+        for key in keys {
+            let item = &self._items[key];
+            if item.name.trim().is_empty() {
+                problems.push(format!("item stored under {:?} has an empty name", key));
+            }
+            if item.sku != *key {
+                problems.push(format!("item {:?} is stored under the key {:?}", item.sku, key));
+            }
+            let indexed = self.names.get(&normalize(&item.name));
+            if !indexed.is_some_and(|skus| skus.contains(key)) {
+                problems.push(format!("item {:?} is missing from the name index", key));
+            }
+            let placed: u64 = item.locations.values().map(|&held| u64::from(held)).sum();
+            if placed != u64::from(item.quantity) {
+                problems.push(format!(
+                    "item {:?} has {} in stock but its locations hold {}",
+                    key, item.quantity, placed
+                ));
+            }
+            for location in item.locations.keys() {
+                if !self.locations.contains(location) {
+                    problems.push(format!("item {:?} is at unknown location {}", key, location));
+                }
+            }
+            if item.reserved > item.quantity {
+                problems.push(format!(
+                    "item {:?} has {} reserved but only {} in stock",
+                    key, item.reserved, item.quantity
+                ));
+            }
+            for component in &item.components {
+                let sku = &component.sku;
+                if !self._items.contains_key(sku) && !self.archived.contains_key(sku) {
+                    problems.push(format!(
+                        "kit {:?} has missing item {:?} as a component",
+                        key, component.sku
+                    ));
+                }
+            }
+            if let Some(lots) = &item.lots {
+                let held: u64 = lots.iter().map(|lot| u64::from(lot.quantity)).sum();
+                if held != u64::from(item.quantity) {
+                    problems.push(format!(
+                        "item {:?} has {} in stock but its lots hold {}",
+                        key, item.quantity, held
+                    ));
+                }
+            }
+            if let Some(serials) = &item.serials {
+                if serials.len() != item.quantity as usize {
+                    problems.push(format!(
+                        "item {:?} has {} in stock but {} serial numbers",
+                        key,
+                        item.quantity,
+                        serials.len()
+                    ));
+                }
+                for serial in serials {
+                    if let Some(owner) = owners.insert(serial, key) {
+                        problems.push(format!(
+                            "serial number {} belongs to both {:?} and {:?}",
+                            serial, owner, key
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut ids: Vec<&ReservationId> = self.reservations.keys().collect();
+        ids.sort_by_key(|id| id.0);
+        for id in ids {
+            let (sku, _) = &self.reservations[id];
+            if !self._items.contains_key(sku) {
+                problems.push(format!("reservation {} refers to missing item {:?}", id, sku));
+            }
+        }
+
+        for discrepancy in self.reconcile() {
+            problems.push(format!(
+                "item {:?} has {} in stock but its movements add up to {}",
+                discrepancy.sku, discrepancy.quantity, discrepancy.ledger
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    // One line per item with a bar of `#` proportional to its quantity. Bars
+    // are one `#` per unit until the largest quantity would pass
+    // HISTOGRAM_WIDTH, then everything is scaled down to fit.
+    pub fn histogram(&self) -> String {
+        if self._items.is_empty() {
+            return String::from("(no items)\n");
+        }
+
+        let mut items: Vec<&Item> = self.iter().collect();
+        items.sort_by_key(|&item| (&item.name, &item.sku));
+
+        let name_width = items.iter().map(|item| item.name.chars().count()).max().unwrap_or(0);
+        let max = items.iter().map(|item| item.quantity as usize).max().unwrap_or(0);
+
+        let mut out = String::new();
+        for item in items {
+            let quantity = item.quantity as usize;
+            let bar = if max <= HISTOGRAM_WIDTH {
+                quantity
+            } else {
+                quantity * HISTOGRAM_WIDTH / max
+            };
+            out.push_str(&format!(
+                "{:<width$} | {} {}\n",
+                item.name,
+                "#".repeat(bar),
+                quantity,
+                width = name_width
+            ));
+        }
+        out
+    }
+
+    // Like histogram, for `items` in the order given and with the bars
+    // scaled so the longest line is `width` characters. Any stock at all
+    // shows as at least one `#`.
+    pub fn chart_of(&self, items: &[&Item], width: usize) -> String {
+        if items.is_empty() {
+            return String::from("(no items)\n");
+        }
+
+        let name_width = items.iter().map(|item| item.name.chars().count()).max().unwrap_or(0);
+        let max = items.iter().map(|item| u64::from(item.quantity)).max().unwrap_or(0);
+        // The name, " | ", a space and the largest quantity take the rest.
+        let bar_width = width.saturating_sub(name_width + 4 + max.to_string().len()).max(10);
+
+        let mut out = String::new();
+        for item in items {
+            let quantity = u64::from(item.quantity);
+            let bar = match max {
+                0 => 0,
+                _ => (quantity * bar_width as u64).div_ceil(max) as usize,
+            };
+            out.push_str(&format!(
+                "{:<width$} | {} {}\n",
+                item.name,
+                "#".repeat(bar),
+                quantity,
+                width = name_width
+            ));
+        }
+        out
+    }
+
+    // Groups the current keys by their trimmed, lowercased form and returns
+    // the groups that would collapse into a single entry.
+    pub fn detect_collisions(&self) -> Vec<(String, Vec<String>)> {
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for item in self.iter() {
+            let normalized = normalize(&item.name);
+            groups
+                .entry(normalized)
+                .or_default()
+                .push(format!("{} ({})", item.name, item.sku));
+        }
+
+        let mut collisions: Vec<(String, Vec<String>)> = groups
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .collect();
+        for (_, names) in collisions.iter_mut() {
+            names.sort();
+        }
+        collisions.sort();
+        collisions
+    }
+
+}
+
+// How listings are ordered. Updated puts the most recently changed first.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SortOrder {
+    Name,
+    Quantity,
+    QuantityDesc,
+    Updated,
+}
+
+// The number in a generated SKU like SKU-000042.
+fn sku_number(sku: &str) -> Option<u64> {
+    sku.strip_prefix("SKU-")?.parse().ok()
+}
+
+// Reads a time written either as RFC 3339 or, as older files did, as
+// milliseconds since the Unix epoch.
+#[cfg(feature = "serde")]
+fn timestamp<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Stamp {
+        Millis(i64),
+        Time(DateTime<Utc>),
+    }
+    match <Stamp as serde::Deserialize>::deserialize(deserializer)? {
+        Stamp::Millis(millis) => DateTime::from_timestamp_millis(millis)
+            .ok_or_else(|| serde::de::Error::custom("timestamp out of range")),
+        Stamp::Time(time) => Ok(time),
+    }
+}
+
+// ANSI escape codes for to_table.
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+// Local time to the minute.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+fn default_date_format() -> String {
+    DEFAULT_DATE_FORMAT.to_string()
+}
+
+// Page `page` (counting from 1) of `items`, `per_page` to a page, and the
+// total number of pages. A page past the end comes back empty.
+pub fn page_of<T>(items: &[T], page: usize, per_page: usize) -> (&[T], usize) {
+    if per_page == 0 {
+        return (&[], 0);
+    }
+    let total_pages = items.len().div_ceil(per_page);
+    let start = page.saturating_sub(1).saturating_mul(per_page);
+    if page == 0 || start >= items.len() {
+        return (&[], total_pages);
+    }
+    (&items[start..items.len().min(start + per_page)], total_pages)
+}
+
+// Formats cents as a decimal amount, e.g. 1250 as 12.50.
+pub fn format_price(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+// `qty` of `unit` in the unit `item` is counted in.
+fn in_unit_of(item: &Item, qty: u32, unit: Option<Unit>) -> Result<u32, InventoryError> {
+    match unit {
+        Some(unit) => unit.convert(qty, item.unit),
+        None => Ok(qty),
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+// Parses a day written like 2024-03-31.
+pub fn parse_date(input: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d").ok()
+}
+
+// Parses an amount like `12`, `12.5` or `12.50` into cents.
+pub fn parse_price(input: &str) -> Option<u64> {
+    let (whole, fraction) = input.trim().split_once('.').unwrap_or((input.trim(), ""));
+    if whole.is_empty() || fraction.len() > 2 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let whole: u64 = whole.parse().ok()?;
+    let fraction: u64 = format!("{:0<2}", fraction).parse().ok()?;
+    whole.checked_mul(100)?.checked_add(fraction)
+}
+
+// The form two names are compared in: surrounding whitespace and case are ignored.
+// Case is folded rather than lowercased, so "Straße" and "STRASSE" compare equal.
+fn normalize(name: &str) -> String {
+    caseless::default_case_fold_str(name.trim())
+}
+
+impl Default for Collection {
+    fn default() -> Self {
+        Collection::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection_with(items: &[(&str, u32)]) -> Collection {
+        let mut collection = Collection::new();
+        for (name, quantity) in items {
+            let category = String::from("uncategorized");
+            collection.add_item(name.to_string(), *quantity, Unit::default(), category, 0).unwrap();
+        }
+        collection
+    }
+
+    #[test]
+    fn items_are_kept_by_sku_and_found_by_name_too() {
+        let mut collection = collection_with(&[("apple", 5), ("pear", 2)]);
+        let sku = collection.resolve("apple").unwrap();
+        assert_eq!(collection.lookup(&sku).unwrap().name, "apple");
+
+        collection.update_item(sku.to_string(), 7, Some(150)).unwrap();
+        let apple = collection.lookup("apple").unwrap();
+        assert_eq!((apple.quantity, apple.unit_price, apple.value()), (7, 150, 1050));
+
+        assert_eq!(collection.remove_item("pear").unwrap().quantity, 2);
+        assert!(collection.lookup("pear").is_err());
+        assert_eq!(collection.len(), 1);
+    }
+
+    #[test]
+    fn reserved_stock_is_unavailable_until_released_or_committed() {
+        let mut collection = collection_with(&[("apple", 5)]);
+        let first = collection.reserve("apple", 3).unwrap();
+        assert_eq!(collection.lookup("apple").unwrap().available(), 2);
+        assert!(collection.reserve("apple", 3).is_err());
+
+        let second = collection.reserve("apple", 2).unwrap();
+        collection.release(first).unwrap();
+        collection.commit(second).unwrap();
+        let apple = collection.lookup("apple").unwrap();
+        assert_eq!((apple.quantity, apple.reserved, apple.available()), (3, 0, 3));
+        assert!(collection.release(first).is_err());
+    }
+
+    #[test]
+    fn prices_are_formatted_from_cents() {
+        assert_eq!(format_price(0), "0.00");
+        assert_eq!(format_price(5), "0.05");
+        assert_eq!(format_price(123456), "1234.56");
+    }
+
+    #[test]
+    fn failed_batch_leaves_the_ledger_as_it_was() {
+        let mut collection = collection_with(&[("apple", 5)]);
+        let ops = [
+            Operation::Adjust { name: String::from("apple"), delta: 3 },
+            Operation::Remove { name: String::from("pear") },
+        ];
+        let (index, _) = collection.apply_all(&ops).unwrap_err();
+        assert_eq!(index, 1);
+        assert_eq!(collection.lookup("apple").unwrap().quantity, 5);
+        assert!(collection.reconcile().is_empty());
+        assert!(collection.validate().is_ok());
+    }
+
+    #[test]
+    fn names_differing_in_case_or_spaces_are_one_item() {
+        let mut collection = collection_with(&[("Apple", 5)]);
+        for name in ["apple", " Apple ", "APPLE"] {
+            let added =
+                collection.add_item(name.to_string(), 1, Unit::default(), String::new(), 0);
+            assert!(matches!(added, Err(InventoryError::NameInUse { .. })), "{:?}", name);
+        }
+        assert_eq!(collection.len(), 1);
+        assert_eq!(collection.lookup(" apple ").unwrap().name, "Apple");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn loading_merges_items_saved_under_one_name() {
+        let collection = collection_with(&[("apple", 5), ("pear", 2), ("plum", 1)]);
+        let json = collection.to_json().unwrap();
+        let json = json.replace("\"pear\"", "\" Apple \"").replace("\"plum\"", "\"APPLE\"");
+        let loaded = Collection::from_json(&json).unwrap();
+        assert_eq!(loaded.len(), 1);
+        let apple = loaded.lookup("apple").unwrap();
+        assert_eq!((apple.name.as_str(), apple.quantity), ("apple", 8));
+        assert!(loaded.reconcile().is_empty());
+    }
+
+    #[test]
+    fn changes_since_a_count_ignore_case() {
+        let collection = collection_with(&[("Apple", 5), ("Pear", 2)]);
+        let baseline = HashMap::from([
+            (String::from("apple"), 5),
+            (String::from("pear"), 3),
+            (String::from("plum"), 4),
+        ]);
+        let changes = collection.changed_since(&baseline);
+        assert_eq!(changes, [(String::from("Pear"), -1), (String::from("plum"), -4)]);
+    }
+
+    #[test]
+    fn table_columns_line_up_whatever_the_name_length() {
+        let collection = collection_with(&[("fig", 7), ("watermelon", 12)]);
+        let table = collection.to_table(SortOrder::Name, None);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let quantity_end = lines[0].find("Quantity").unwrap() + "Quantity".len();
+        let category = lines[0].find("Category").unwrap();
+        for (line, quantity) in lines[1..].iter().zip(["7", "12"]) {
+            assert!(line[..quantity_end].ends_with(&format!(" {}", quantity)), "{:?}", line);
+            assert_eq!(&line[category..category + "uncategorized".len()], "uncategorized");
+        }
+        assert_eq!(Collection::new().to_table(SortOrder::Name, None), "(no items)\n");
+    }
+
+    #[test]
+    fn quantities_over_the_cap_are_refused_and_change_nothing() {
+        let mut collection = collection_with(&[("apple", 50)]);
+        collection.set_max_per_item(Some(100));
+        let pear = String::from("pear");
+        let added = collection.add_item(pear, 150, Unit::default(), String::new(), 0);
+        assert!(matches!(added, Err(InventoryError::QuantityExceedsMax { max: 100 })));
+        assert!(collection.lookup("pear").is_err());
+
+        assert!(collection.update_item(String::from("apple"), 101, None).is_err());
+        let adjust = Operation::Adjust { name: String::from("apple"), delta: 51 };
+        let adjusted = collection.apply(&adjust);
+        assert!(matches!(adjusted, Err(InventoryError::QuantityExceedsMax { .. })));
+        assert_eq!(collection.lookup("apple").unwrap().quantity, 50);
+
+        collection.update_item(String::from("apple"), 100, None).unwrap();
+        collection.set_max_per_item(None);
+        collection.update_item(String::from("apple"), 1000, None).unwrap();
+    }
+
+    #[test]
+    fn iter_goes_over_every_item_once() {
+        assert_eq!(Collection::new().iter().count(), 0);
+        let mut collection = collection_with(&[("apple", 5), ("pear", 0), ("plum", 2)]);
+        assert_eq!(collection.iter().count(), 3);
+        assert_eq!(collection.iter().map(|item| item.quantity).sum::<u32>(), 7);
+        collection.remove_item("pear").unwrap();
+        assert_eq!(collection.iter().count(), collection.len());
+    }
+
+    #[test]
+    fn items_are_listed_by_their_category() {
+        let mut collection = Collection::new();
+        let items = [("hammer", "hardware"), ("apple", "produce"), ("nail", "hardware")];
+        for (name, category) in items {
+            let (name, category) = (name.to_string(), category.to_string());
+            collection.add_item(name, 1, Unit::default(), category, 0).unwrap();
+        }
+        let names = |category| -> Vec<String> {
+            let items = collection.list_by_category(category);
+            items.iter().map(|item| item.name.to_string()).collect()
+        };
+        assert_eq!(names("hardware"), ["hammer", "nail"]);
+        assert_eq!(names("produce"), ["apple"]);
+        assert!(names("dairy").is_empty());
+    }
+
+    #[test]
+    fn an_order_short_on_a_line_takes_nothing() {
+        let mut collection = collection_with(&[("apple", 5), ("pear", 2)]);
+        let order = [(String::from("apple"), 3), (String::from("pear"), 4)];
+        let fulfilled = collection.fulfill(&order);
+        assert!(matches!(
+            fulfilled,
+            Err(InventoryError::InsufficientStock { requested: 4, available: 2, .. })
+        ));
+        assert_eq!(collection.lookup("apple").unwrap().quantity, 5);
+        assert_eq!(collection.lookup("pear").unwrap().quantity, 2);
+
+        // Lines for the same item count together.
+        let order = [(String::from("apple"), 3), (String::from("apple"), 3)];
+        assert!(collection.fulfill(&order).is_err());
+        collection.fulfill(&order[..1]).unwrap();
+        assert_eq!(collection.lookup("apple").unwrap().quantity, 2);
+        assert!(collection.reconcile().is_empty());
+    }
+
+    #[test]
+    fn the_restock_plan_fills_only_what_is_below_the_target() {
+        let collection = collection_with(&[("apple", 2), ("pear", 10), ("plum", 15), ("fig", 0)]);
+        let plan = collection.restock_plan(10);
+        assert_eq!(plan, [(String::from("apple"), 8), (String::from("fig"), 10)]);
+        assert!(collection.restock_plan(0).is_empty());
+    }
+
+    #[test]
+    fn merging_sums_the_items_both_have_and_adds_the_rest() {
+        let mut collection = collection_with(&[("apple", 5), ("pear", 2)]);
+        collection.merge(collection_with(&[("Apple", 3), ("fig", 4)])).unwrap();
+        let quantities: Vec<(&str, u32)> = collection
+            .sorted(SortOrder::Name)
+            .into_iter()
+            .map(|item| (item.name.as_str(), item.quantity))
+            .collect();
+        assert_eq!(quantities, [("apple", 8), ("fig", 4), ("pear", 2)]);
+
+        let too_many = collection_with(&[("fig", 1), ("apple", u32::MAX)]);
+        let merged = collection.merge(too_many);
+        assert!(matches!(merged, Err(InventoryError::QuantityOverflow { .. })));
+        assert_eq!(collection.lookup("fig").unwrap().quantity, 4);
+    }
+
+    #[test]
+    fn a_diff_has_one_change_per_item_that_changed() {
+        let before = collection_with(&[("apple", 5), ("pear", 2), ("plum", 1)]);
+        let mut after = before.clone();
+        after.update_item(String::from("apple"), 7, None).unwrap();
+        after.remove_item("pear").unwrap();
+        after.add_item(String::from("fig"), 4, Unit::default(), String::new(), 0).unwrap();
+        let changes = before.diff(&after);
+        assert_eq!(
+            changes,
+            [
+                Change::Updated { name: String::from("apple"), from: 5, to: 7 },
+                Change::Added(String::from("fig"), 4),
+                Change::Removed(String::from("pear"), 2),
+            ]
+        );
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn releasing_a_reservation_gives_the_stock_back_and_committing_does_not() {
+        let mut collection = collection_with(&[("apple", 5)]);
+        let id = collection.reserve("apple", 4).unwrap();
+        collection.release(id).unwrap();
+        assert_eq!(collection.lookup("apple").unwrap().available(), 5);
+
+        let id = collection.reserve("apple", 4).unwrap();
+        collection.commit(id).unwrap();
+        assert_eq!(collection.lookup("apple").unwrap().available(), 1);
+        assert!(matches!(collection.commit(id), Err(InventoryError::ReservationNotFound { .. })));
+
+        // Removing an item drops what was reserved of it.
+        let id = collection.reserve("apple", 1).unwrap();
+        collection.remove_item("apple").unwrap();
+        assert!(collection.release(id).is_err());
+    }
+
+    #[test]
+    fn validate_reports_every_broken_invariant() {
+        let mut collection = collection_with(&[("apple", 5), ("pear", 2)]);
+        assert_eq!(collection.validate(), Ok(()));
+        collection._items.get_mut("SKU-000001").unwrap().reserved = 9;
+        collection._items.get_mut("SKU-000002").unwrap().name = String::from(" ");
+        assert_eq!(
+            collection.validate().unwrap_err(),
+            [
+                "item \"SKU-000001\" has 9 reserved but only 5 in stock",
+                "item stored under \"SKU-000002\" has an empty name",
+                "item \"SKU-000002\" is missing from the name index",
+            ]
+        );
+    }
+
+    #[test]
+    fn get_finds_an_item_by_sku_or_any_spelling_of_its_name() {
+        let collection = collection_with(&[("Apple", 5)]);
+        for key in ["SKU-000001", "apple", "  APPLE "] {
+            assert_eq!(collection.get(key).map(|item| item.quantity), Some(5), "{:?}", key);
+        }
+        assert!(collection.get("pear").is_none());
+    }
+
+    #[test]
+    fn a_batch_failing_on_its_third_operation_undoes_the_first_two() {
+        let mut collection = collection_with(&[("apple", 5), ("pear", 2)]);
+        let ops = [
+            Operation::Add { name: String::from("fig"), qty: 4 },
+            Operation::Remove { name: String::from("pear") },
+            Operation::Adjust { name: String::from("apple"), delta: -6 },
+        ];
+        let (index, error) = collection.apply_all(&ops).unwrap_err();
+        assert_eq!(index, 2);
+        assert!(matches!(error, InventoryError::InsufficientStock { available: 5, .. }));
+        assert!(collection.get("fig").is_none());
+        assert_eq!(collection.get("pear").map(|pear| pear.quantity), Some(2));
+        assert_eq!(collection.get("apple").map(|apple| apple.quantity), Some(5));
+        assert_eq!(collection.validate(), Ok(()));
+
+        collection.apply_all(&ops[..2]).unwrap();
+        assert!(collection.get("fig").is_some() && collection.get("pear").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips_every_item() {
+        let mut collection = collection_with(&[("apple", 5), ("pear", 0)]);
+        let rice = (String::from("rice"), String::from("dry goods"));
+        collection.add_item(rice.0, 12, Unit::Kilograms, rice.1, 350).unwrap();
+        collection.set_expiry("rice", NaiveDate::from_ymd_opt(2030, 1, 31)).unwrap();
+        collection.set_reorder_level("apple", Some(3)).unwrap();
+
+        let loaded = Collection::from_json(&collection.to_json().unwrap()).unwrap();
+        assert!(loaded.sorted(SortOrder::Name) == collection.sorted(SortOrder::Name));
+        assert_eq!(loaded.validate(), Ok(()));
+        assert!(Collection::from_json("{\"items\": [}").is_err());
+    }
+
+    #[test]
+    fn the_last_page_holds_what_is_left_and_past_it_nothing() {
+        let names = ["a", "b", "c", "d", "e", "f", "g"];
+        let collection = collection_with(&names.map(|name| (name, 1)));
+        let page = |page| -> (Vec<String>, usize) {
+            let (items, total_pages) = collection.list_page(page, 3);
+            (items.iter().map(|item| item.name.to_string()).collect(), total_pages)
+        };
+        assert_eq!(page(1), (vec![String::from("a"), String::from("b"), String::from("c")], 3));
+        assert_eq!(page(3), (vec![String::from("g")], 3));
+        assert_eq!(page(4), (vec![], 3));
+        assert_eq!(page(0), (vec![], 3));
+        assert_eq!(collection.list_page(1, 0).1, 0);
+    }
+
+    #[test]
+    fn the_most_common_quantity_goes_to_the_smaller_on_a_tie() {
+        assert_eq!(Collection::new().most_common_quantity(), None);
+        let clear = collection_with(&[("a", 3), ("b", 7), ("c", 3), ("d", 1)]);
+        assert_eq!(clear.most_common_quantity(), Some((3, 2)));
+        let tied = collection_with(&[("a", 9), ("b", 4), ("c", 9), ("d", 4), ("e", 1)]);
+        assert_eq!(tied.most_common_quantity(), Some((4, 2)));
+    }
+
+    #[test]
+    fn changes_since_a_count_cover_items_added_removed_and_changed() {
+        let collection = collection_with(&[("apple", 5), ("fig", 4), ("pear", 2)]);
+        let baseline = HashMap::from([
+            (String::from("apple"), 8),
+            (String::from("pear"), 2),
+            (String::from("plum"), 6),
+        ]);
+        let changes = collection.changed_since(&baseline);
+        let expected = [("apple", -3), ("fig", 4), ("plum", -6)];
+        assert_eq!(changes, expected.map(|(name, delta)| (name.to_string(), delta)));
+        assert!(collection.changed_since(&HashMap::new()).iter().all(|(_, delta)| *delta > 0));
+    }
+
+    #[test]
+    fn the_largest_quantity_has_the_longest_bar_within_the_width() {
+        assert_eq!(Collection::new().histogram(), "(no items)\n");
+        let collection = collection_with(&[("apple", 1000), ("fig", 250), ("pear", 0)]);
+        let histogram = collection.histogram();
+        let bars: Vec<usize> = histogram.lines().map(|line| line.matches('#').count()).collect();
+        assert_eq!(bars, [HISTOGRAM_WIDTH, HISTOGRAM_WIDTH / 4, 0]);
+
+        // Small quantities aren't stretched to fill the width.
+        let collection = collection_with(&[("apple", 3), ("fig", 1)]);
+        let bars: Vec<usize> =
+            collection.histogram().lines().map(|line| line.matches('#').count()).collect();
+        assert_eq!(bars, [3, 1]);
+    }
+
+    #[test]
+    fn merged_items_leave_their_reservations_behind() {
+        let mut collection = collection_with(&[("apple", 5)]);
+        let mut other = collection_with(&[("pear", 4)]);
+        other.reserve("pear", 3).unwrap();
+
+        collection.merge(other).unwrap();
+        let pear = collection.lookup("pear").unwrap();
+        assert_eq!((pear.quantity, pear.reserved), (4, 0));
+        assert!(collection.reserve("pear", 4).is_ok());
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::NaiveDate;
+
+use crate::audit::Snapshot;
+use crate::ledger::Reason;
+use crate::unit::Unit;
+use crate::{in_unit_of, today, Collection, InventoryError, Item, LocationId};
+
+// Stock that arrives in batches, each with its own expiry. An item tracked
+// this way holds its quantity as lots that add up to it. Stock comes in by
+// receiving a lot and goes out first-expired-first-out, whichever way it is
+// taken, and the item expires when its first lot does. Lots aren't tied to
+// a location.
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lot {
+    // Unique within the item, like a batch number from the supplier.
+    #[cfg_attr(feature = "serde", serde(rename = "lot_id"))]
+    pub id: String,
+    // In the item's unit.
+    pub quantity: u32,
+    pub received_on: NaiveDate,
+    // None for a lot that doesn't go off; those are taken last.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expires_on: Option<NaiveDate>,
+}
+
+impl Item {
+    pub fn is_lot_tracked(&self) -> bool {
+        self.lots.is_some()
+    }
+
+    // For the changes that would set the quantity without saying which lot.
+    pub(crate) fn check_unlotted(&self) -> Result<(), InventoryError> {
+        match self.is_lot_tracked() {
+            true => Err(InventoryError::LotsRequired { item: self.name.to_string() }),
+            false => Ok(()),
+        }
+    }
+
+    // Takes `qty` out of the lots that expire first. The caller has checked
+    // there is that much in stock.
+    pub(crate) fn take_lots(&mut self, mut qty: u32) {
+        if let Some(lots) = self.lots.as_mut() {
+            for lot in lots.iter_mut() {
+                let taken = lot.quantity.min(qty);
+                lot.quantity -= taken;
+                qty -= taken;
+            }
+        }
+        self.settle_lots();
+    }
+
+    // Drops empty lots, puts the rest in the order they are used and
+    // makes the item expire with the first.
+    pub(crate) fn settle_lots(&mut self) {
+        if let Some(lots) = self.lots.as_mut() {
+            lots.retain(|lot| lot.quantity > 0);
+            lots.sort_by_key(|lot| (lot.expires_on.is_none(), lot.expires_on, lot.received_on));
+            self.expires_on = lots.first().and_then(|lot| lot.expires_on);
+        }
+    }
+}
+
+impl Collection {
+    // Starts tracking lots for an item. Only allowed while none is in stock,
+    // so there is no stock outside a lot.
+    pub fn track_lots(&mut self, key: &str) -> Result<(), InventoryError> {
+        let item = self.item_mut(key)?;
+        if item.is_lot_tracked() {
+            return Ok(());
+        }
+        if item.is_serialized() {
+            return Err(InventoryError::TrackingConflict { item: key.to_string() });
+        }
+        if item.quantity > 0 {
+            return Err(InventoryError::LotTrackingWithStock { item: key.to_string() });
+        }
+        item.lots = Some(Vec::new());
+        Ok(())
+    }
+
+    // Adds `qty` to the stock of a lot-tracked item at `location` as a new
+    // lot received today. `qty` is in `unit` as for receive.
+    #[tracing::instrument(skip_all, fields(key = key, location = %location, qty = qty, lot = %lot))]
+    pub fn receive_lot(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        qty: u32,
+        unit: Option<Unit>,
+        lot: String,
+        expires_on: Option<NaiveDate>,
+    ) -> Result<(), InventoryError> {
+        self.check_location(location)?;
+        let item = self.lookup(key)?;
+        let Some(lots) = &item.lots else {
+            return Err(InventoryError::LotsNotTracked { item: key.to_string() });
+        };
+        if lots.iter().any(|other| other.id == lot) {
+            return Err(InventoryError::LotExists { lot, item: key.to_string() });
+        }
+        let qty = in_unit_of(item, qty, unit)?;
+        let quantity = item
+            .quantity
+            .checked_add(qty)
+            .ok_or_else(|| InventoryError::QuantityOverflow { item: key.to_string() })?;
+        self.check_max(quantity)?;
+
+        let item = self.item_mut(key)?;
+        let before = Snapshot::of(item);
+        *item.locations.entry(location.clone()).or_insert(0) += qty;
+        item.quantity = quantity;
+        let reference = lot.to_string();
+        let lot = Lot { id: lot, quantity: qty, received_on: today(), expires_on };
+        item.lots.get_or_insert_with(Vec::new).push(lot);
+        item.settle_lots();
+        item.touch();
+        let (sku, name) = (item.sku.to_string(), item.name.to_string());
+        self.record_change_as(&sku, &name, Some(before), Reason::Received, Some(&reference));
+        Ok(())
+    }
+
+    // The lots of the item with SKU or name `key`, in the order they will be
+    // used.
+    pub fn lots(&self, key: &str) -> Result<&[Lot], InventoryError> {
+        let item = self.lookup(key)?;
+        item.lots.as_deref().ok_or_else(|| InventoryError::LotsNotTracked { item: key.to_string() })
+    }
+}
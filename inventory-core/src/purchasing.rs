@@ -0,0 +1,195 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::ledger::Reason;
+use crate::{normalize, Collection, InventoryError, Item, LocationId};
+
+// Who stock is bought from, and the orders placed with them. Receiving an
+// order adds everything on it to stock in one go and keeps a note of when
+// and where it arrived, so the orders show which supplier delivered what.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SupplierId(pub u64);
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Supplier {
+    pub id: SupplierId,
+    // Unique, ignoring case.
+    pub name: String,
+    // Free text, like an email address or phone number.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub contact: String,
+    // How many days an order usually takes to arrive.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lead_time_days: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrderId(pub u64);
+
+impl fmt::Display for OrderId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PO-{}", self.0)
+    }
+}
+
+// How much of one item an order is for, in the item's own unit.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrderLine {
+    pub sku: String,
+    pub quantity: u32,
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PurchaseOrder {
+    pub id: OrderId,
+    pub supplier: SupplierId,
+    pub lines: Vec<OrderLine>,
+    pub created_at: DateTime<Utc>,
+    // None until the order arrives.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub receipt: Option<Receipt>,
+}
+
+// When an order arrived, and the location its stock went to.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Receipt {
+    pub at: DateTime<Utc>,
+    pub location: LocationId,
+}
+
+impl Collection {
+    pub fn add_supplier(
+        &mut self,
+        name: String,
+        contact: String,
+        lead_time_days: u32,
+    ) -> Result<SupplierId, InventoryError> {
+        if self.find_supplier(&name).is_ok() {
+            return Err(InventoryError::SupplierExists { name });
+        }
+        let id = SupplierId(self.next_supplier);
+        self.next_supplier += 1;
+        let supplier = Supplier { id, name, contact, lead_time_days };
+        self.suppliers.insert(id, supplier);
+        Ok(id)
+    }
+
+    // The supplier with this name, ignoring case.
+    pub fn find_supplier(&self, name: &str) -> Result<&Supplier, InventoryError> {
+        let wanted = normalize(name);
+        self.suppliers
+            .values()
+            .find(|supplier| normalize(&supplier.name) == wanted)
+            .ok_or_else(|| InventoryError::SupplierNotFound { name: name.to_string() })
+    }
+
+    pub fn supplier(&self, id: SupplierId) -> Option<&Supplier> {
+        self.suppliers.get(&id)
+    }
+
+    // By name.
+    pub fn suppliers(&self) -> Vec<&Supplier> {
+        let mut suppliers: Vec<&Supplier> = self.suppliers.values().collect();
+        suppliers.sort_by_key(|supplier| normalize(&supplier.name));
+        suppliers
+    }
+
+    // Places an order with the supplier named `supplier` for the items in
+    // `lines`, given by SKU or name. Nothing changes in stock until it's
+    // received.
+    pub fn create_order(
+        &mut self,
+        supplier: &str,
+        lines: &[(String, u32)],
+    ) -> Result<OrderId, InventoryError> {
+        let supplier = self.find_supplier(supplier)?.id;
+        if lines.is_empty() {
+            return Err(InventoryError::EmptyOrder);
+        }
+        let mut order_lines = Vec::new();
+        for (key, quantity) in lines {
+            order_lines.push(OrderLine { sku: self.resolve(key)?, quantity: *quantity });
+        }
+
+        let id = OrderId(self.next_order);
+        self.next_order += 1;
+        let order = PurchaseOrder {
+            id,
+            supplier,
+            lines: order_lines,
+            created_at: Utc::now(),
+            receipt: None,
+        };
+        self.orders.insert(id, order);
+        Ok(id)
+    }
+
+    pub fn order(&self, id: OrderId) -> Result<&PurchaseOrder, InventoryError> {
+        self.orders.get(&id).ok_or(InventoryError::OrderNotFound { id })
+    }
+
+    // Oldest first.
+    pub fn orders(&self) -> impl Iterator<Item = &PurchaseOrder> {
+        self.orders.values()
+    }
+
+    // Adds every line of the order to stock at `location`. Either all of
+    // them go in or, when one can't, none do and the error is returned.
+    #[tracing::instrument(skip_all, fields(id = %id, location = %location))]
+    pub fn receive_order(
+        &mut self,
+        id: OrderId,
+        location: &LocationId,
+    ) -> Result<(), InventoryError> {
+        let order = self.order(id)?;
+        if order.receipt.is_some() {
+            return Err(InventoryError::OrderAlreadyReceived { id });
+        }
+        let lines = order.lines.clone();
+
+        let items = self._items.clone();
+        let (audited, moved) = (self.audit.len(), self.movements.len());
+        let reference = id.to_string();
+        for line in &lines {
+            let (sku, quantity, reason) = (&line.sku, line.quantity, Reason::Received);
+            let received =
+                self.receive_with(sku, location, quantity, None, reason, Some(&reference));
+            if let Err(e) = received {
+                self._items = items;
+                self.audit.truncate(audited);
+                self.movements.truncate(moved);
+                return Err(e);
+            }
+        }
+        let receipt = Receipt { at: Utc::now(), location: location.clone() };
+        self.orders.get_mut(&id).expect("checked above").receipt = Some(receipt);
+        Ok(())
+    }
+
+    // The items on the order, as they are now, for showing its lines. Items
+    // removed since the order was made are left out.
+    pub fn order_items(&self, order: &PurchaseOrder) -> Vec<(&Item, u32)> {
+        order
+            .lines
+            .iter()
+            .filter_map(|line| Some((self._items.get(&line.sku)?, line.quantity)))
+            .collect()
+    }
+
+    // Moves the next IDs past every one in use, after loading.
+    #[cfg(any(feature = "serde", feature = "sqlite"))]
+    pub(crate) fn reindex_purchasing(&mut self) {
+        let last_supplier = self.suppliers.keys().next_back().map_or(0, |id| id.0);
+        self.next_supplier = self.next_supplier.max(last_supplier + 1);
+        let last_order = self.orders.keys().next_back().map_or(0, |id| id.0);
+        self.next_order = self.next_order.max(last_order + 1);
+    }
+}
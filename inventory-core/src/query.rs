@@ -0,0 +1,520 @@
+use std::cmp::Ordering;
+
+use chrono::NaiveDate;
+
+use crate::{normalize, parse_date, parse_price, InventoryError, Item};
+
+// A small language for picking items out, like
+//
+//     quantity < 5 and category = "produce" and tag has "frozen"
+//
+// Each condition compares a field of the item with a value: quantity,
+// reserved and available with a whole number, price with an amount like
+// 2.50, expires with a day like 2024-03-31, and sku, name, category and unit
+// with text. Any other name is an attribute, so `tag has "frozen"` looks at
+// the item's tag. Text is compared ignoring case, and `has` finds it
+// anywhere in the field. Conditions combine with and, or and not, and
+// brackets group them; and goes before or.
+
+// How deep not and brackets can nest, so a filter can't run the parser out
+// of stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Has,
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Has => false,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    // A field name, a keyword or an unquoted value.
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+// Where a token starts, counting characters from 1.
+type Positioned = (usize, Token);
+
+fn lex(input: &str) -> Result<Vec<Positioned>, InventoryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let token = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    // A backslash lets a quote into the text.
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        i += 1;
+                    }
+                    text.push(chars[i]);
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(invalid(start + 1, "this text has no closing quote"));
+                }
+                Token::Quoted(text)
+            }
+            '=' => Token::Op(Op::Eq),
+            '!' if chars.get(i + 1) == Some(&'=') => {
+                i += 1;
+                Token::Op(Op::Ne)
+            }
+            '<' | '>' => {
+                let or_equal = chars.get(i + 1) == Some(&'=');
+                let op = match (chars[i], or_equal) {
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    (_, false) => Op::Gt,
+                    (_, true) => Op::Ge,
+                };
+                i += usize::from(or_equal);
+                Token::Op(op)
+            }
+            // Words run on through dots and dashes, for amounts and days.
+            c if c.is_alphanumeric() || c == '_' => {
+                let word_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '-');
+                while i + 1 < chars.len() && word_char(chars[i + 1]) {
+                    i += 1;
+                }
+                Token::Word(chars[start..=i].iter().collect())
+            }
+            c => return Err(invalid(start + 1, &format!("{:?} isn't part of a filter", c))),
+        };
+        tokens.push((start + 1, token));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+fn invalid(position: usize, problem: &str) -> InventoryError {
+    InventoryError::InvalidFilter { position, problem: problem.to_string() }
+}
+
+fn is_keyword(word: &str) -> bool {
+    ["and", "or", "not", "has"].iter().any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+#[derive(Clone)]
+enum Field {
+    Sku,
+    Name,
+    Category,
+    Unit,
+    Quantity,
+    Reserved,
+    Available,
+    Price,
+    Expires,
+    // By normalized name.
+    Attribute(String),
+}
+
+impl Field {
+    fn named(name: &str) -> Field {
+        match normalize(name).as_str() {
+            "sku" => Field::Sku,
+            "name" => Field::Name,
+            "category" => Field::Category,
+            "unit" => Field::Unit,
+            "quantity" => Field::Quantity,
+            "reserved" => Field::Reserved,
+            "available" => Field::Available,
+            "price" => Field::Price,
+            "expires" => Field::Expires,
+            name => Field::Attribute(name.to_string()),
+        }
+    }
+
+    // Numbers and prices, in cents, compare as one.
+    fn number(&self, item: &Item) -> u64 {
+        match self {
+            Field::Quantity => u64::from(item.quantity),
+            Field::Reserved => u64::from(item.reserved),
+            Field::Available => u64::from(item.available()),
+            _ => item.unit_price,
+        }
+    }
+
+    fn text(&self, item: &Item) -> Option<String> {
+        let text = match self {
+            Field::Sku => item.sku.to_string(),
+            Field::Name => item.name.to_string(),
+            Field::Category => item.category.to_string(),
+            Field::Unit => item.unit.to_string(),
+            Field::Attribute(name) => item.attribute(name)?.to_string(),
+            _ => return None,
+        };
+        Some(text)
+    }
+}
+
+#[derive(Clone)]
+enum Condition {
+    Number(Field, Op, u64),
+    Date(Op, NaiveDate),
+    // The text is normalized.
+    Text(Field, Op, String),
+}
+
+impl Condition {
+    fn matches(&self, item: &Item) -> bool {
+        match self {
+            Condition::Number(field, op, value) => op.holds(field.number(item).cmp(value)),
+            // An item that doesn't expire is only ever not equal to a day.
+            Condition::Date(op, day) => match item.expires_on {
+                Some(expires_on) => op.holds(expires_on.cmp(day)),
+                None => *op == Op::Ne,
+            },
+            Condition::Text(field, op, value) => match field.text(item).as_deref().map(normalize) {
+                Some(text) if *op == Op::Has => text.contains(value.as_str()),
+                Some(text) => op.holds(text.cmp(value)),
+                // A missing attribute is only ever not equal to something.
+                None => *op == Op::Ne,
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Expr {
+    Condition(Condition),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn matches(&self, item: &Item) -> bool {
+        match self {
+            Expr::Condition(condition) => condition.matches(item),
+            Expr::Not(expr) => !expr.matches(item),
+            Expr::And(a, b) => a.matches(item) && b.matches(item),
+            Expr::Or(a, b) => a.matches(item) || b.matches(item),
+        }
+    }
+}
+
+// A parsed filter, ready to test items with.
+#[derive(Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    // Says where the filter went wrong, by character, when it can't be read.
+    pub fn parse(input: &str) -> Result<Filter, InventoryError> {
+        let end = input.chars().count() + 1;
+        let mut parser = Parser { tokens: lex(input)?, next: 0, end, depth: 0 };
+        let expr = parser.or()?;
+        match parser.tokens.get(parser.next) {
+            None => Ok(Filter { expr }),
+            Some((position, Token::Close)) => Err(invalid(*position, "this bracket isn't open")),
+            Some((position, _)) => Err(invalid(*position, "expected \"and\" or \"or\" here")),
+        }
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        self.expr.matches(item)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Positioned>,
+    next: usize,
+    // Where the input ends, for what is missing at the end of it.
+    end: usize,
+    // The nots and brackets around the next token.
+    depth: usize,
+}
+
+impl Parser {
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.end, |(position, _)| *position)
+    }
+
+    // Goes into one more not or bracket, the one at `position`.
+    fn deeper(&mut self, position: usize) -> Result<(), InventoryError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            let problem = format!("not and brackets only nest {} deep", MAX_DEPTH);
+            return Err(invalid(position, &problem));
+        }
+        Ok(())
+    }
+
+    // Takes the next token if it is `keyword`.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.next) {
+            Some((_, Token::Word(word))) if word.eq_ignore_ascii_case(keyword) => {
+                self.next += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, InventoryError> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, InventoryError> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, InventoryError> {
+        let position = self.position();
+        if self.keyword("not") {
+            self.deeper(position)?;
+            let expr = Expr::Not(Box::new(self.not()?));
+            self.depth -= 1;
+            return Ok(expr);
+        }
+        if self.tokens.get(self.next).is_some_and(|(_, token)| *token == Token::Open) {
+            self.next += 1;
+            self.deeper(position)?;
+            let expr = self.or()?;
+            self.depth -= 1;
+            return match self.tokens.get(self.next) {
+                Some((_, Token::Close)) => {
+                    self.next += 1;
+                    Ok(expr)
+                }
+                _ => Err(invalid(position, "this bracket is never closed")),
+            };
+        }
+        self.condition().map(Expr::Condition)
+    }
+
+    fn condition(&mut self) -> Result<Condition, InventoryError> {
+        let position = self.position();
+        let name = match self.tokens.get(self.next) {
+            Some((_, Token::Word(word))) if !is_keyword(word) => word.to_string(),
+            _ => return Err(invalid(position, "expected a field, like quantity or name")),
+        };
+        self.next += 1;
+
+        let op_position = self.position();
+        let op = match self.tokens.get(self.next) {
+            Some((_, Token::Op(op))) => *op,
+            Some((_, Token::Word(word))) if word.eq_ignore_ascii_case("has") => Op::Has,
+            _ => return Err(invalid(op_position, "expected =, !=, <, <=, >, >= or has")),
+        };
+        self.next += 1;
+
+        let value_position = self.position();
+        let value = match self.tokens.get(self.next) {
+            Some((_, Token::Quoted(text))) => text.to_string(),
+            Some((_, Token::Word(word))) if !is_keyword(word) => word.to_string(),
+            _ => return Err(invalid(value_position, "expected a value")),
+        };
+        self.next += 1;
+
+        let field = Field::named(&name);
+        match field {
+            Field::Quantity | Field::Reserved | Field::Available | Field::Price
+                if op == Op::Has =>
+            {
+                Err(invalid(op_position, &format!("has only works on text, not {}", name)))
+            }
+            Field::Quantity | Field::Reserved | Field::Available => {
+                let number = value.parse::<u32>().map_err(|_| {
+                    let problem = format!("{} is compared with a whole number", name);
+                    invalid(value_position, &problem)
+                })?;
+                Ok(Condition::Number(field, op, u64::from(number)))
+            }
+            Field::Price => {
+                let price = parse_price(&value).ok_or_else(|| {
+                    invalid(value_position, "price is compared with an amount like 2.50")
+                })?;
+                Ok(Condition::Number(field, op, price))
+            }
+            Field::Expires if op == Op::Has => {
+                Err(invalid(op_position, "has only works on text, not expires"))
+            }
+            Field::Expires => {
+                let day = parse_date(&value).ok_or_else(|| {
+                    invalid(value_position, "expires is compared with a day like 2024-03-31")
+                })?;
+                Ok(Condition::Date(op, day))
+            }
+            _ if matches!(op, Op::Eq | Op::Ne | Op::Has) => {
+                Ok(Condition::Text(field, op, normalize(&value)))
+            }
+            _ => Err(invalid(op_position, &format!("{} is text, so it takes =, != or has", name))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit::Unit;
+    use crate::Collection;
+
+    // Apples in stock with some held back, frozen peas that expire, and salt
+    // by the kilogram with none left.
+    fn collection() -> Collection {
+        let mut collection = Collection::new();
+        let items = [
+            ("apple", 10, Unit::Pieces, "produce", 50),
+            ("frozen peas", 4, Unit::Boxes, "frozen", 250),
+            ("sea salt", 0, Unit::Kilograms, "spices", 120),
+        ];
+        for (name, quantity, unit, category, price) in items {
+            let (name, category) = (name.to_string(), category.to_string());
+            collection.add_item(name, quantity, unit, category, price).unwrap();
+        }
+        collection.reserve("apple", 3).unwrap();
+        collection.set_expiry("frozen peas", parse_date("2024-03-31")).unwrap();
+        collection.set_attribute("frozen peas", "tag", "Frozen, Green").unwrap();
+        collection
+    }
+
+    // The names of the items the filter matches, in order.
+    fn matching(filter: &str) -> Vec<String> {
+        let filter = Filter::parse(filter).unwrap_or_else(|e| panic!("{}: {}", filter, e));
+        let collection = collection();
+        let mut names: Vec<String> = collection
+            .iter()
+            .filter(|item| filter.matches(item))
+            .map(|item| item.name.to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn problem(filter: &str) -> String {
+        match Filter::parse(filter) {
+            Ok(_) => panic!("{} parsed", filter),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn each_field_compares_with_its_own_kind_of_value() {
+        assert_eq!(matching("quantity < 5"), ["frozen peas", "sea salt"]);
+        assert_eq!(matching("reserved = 3"), ["apple"]);
+        assert_eq!(matching("available >= 7"), ["apple"]);
+        assert_eq!(matching("price > 1.20"), ["frozen peas"]);
+        assert_eq!(matching("price <= 1.2"), ["apple", "sea salt"]);
+        assert_eq!(matching("expires < 2024-04-01"), ["frozen peas"]);
+        assert_eq!(matching("expires != 2024-03-31"), ["apple", "sea salt"]);
+        assert_eq!(matching("sku = sku-000002"), ["frozen peas"]);
+        assert_eq!(matching("name has SALT"), ["sea salt"]);
+        assert_eq!(matching("category != produce"), ["frozen peas", "sea salt"]);
+        assert_eq!(matching("unit = kg"), ["sea salt"]);
+        assert_eq!(matching("tag has green"), ["frozen peas"]);
+        assert_eq!(matching("tag != green"), ["apple", "frozen peas", "sea salt"]);
+    }
+
+    #[test]
+    fn quoted_text_keeps_its_spaces_and_escaped_quotes() {
+        assert_eq!(matching("name = \"Frozen Peas\""), ["frozen peas"]);
+        assert_eq!(matching("name has \"a s\""), ["sea salt"]);
+        assert!(matching("name = \"say \\\"hi\\\"\"").is_empty());
+        assert!(matching("name = \"and\"").is_empty());
+    }
+
+    #[test]
+    fn and_goes_before_or_unless_brackets_say_otherwise() {
+        let filter = "category = spices or category = produce and quantity = 0";
+        assert_eq!(matching(filter), ["sea salt"]);
+        let filter = "(category = spices or category = produce) and quantity = 0";
+        assert_eq!(matching(filter), ["sea salt"]);
+        let filter = "(category = spices or category = produce) and quantity > 0";
+        assert_eq!(matching(filter), ["apple"]);
+        let filter = "category = spices or (category = produce and quantity = 0)";
+        assert_eq!(matching(filter), ["sea salt"]);
+    }
+
+    #[test]
+    fn not_takes_the_one_condition_or_bracket_after_it() {
+        assert_eq!(matching("not quantity = 0"), ["apple", "frozen peas"]);
+        assert_eq!(matching("not quantity = 0 and price < 1"), ["apple"]);
+        assert_eq!(matching("not (quantity = 0 or price < 1)"), ["frozen peas"]);
+        assert_eq!(matching("not not name has pea"), ["frozen peas"]);
+        assert_eq!(matching("NOT name = apple AND name != \"sea salt\""), ["frozen peas"]);
+    }
+
+    #[test]
+    fn bad_filters_say_what_is_wrong_and_where() {
+        let cases = [
+            ("", "expected a field, like quantity or name (at character 1 of the filter)"),
+            ("quantity", "expected =, !=, <, <=, >, >= or has (at character 9 of the filter)"),
+            ("quantity <", "expected a value (at character 11 of the filter)"),
+            ("quantity < few", "quantity is compared with a whole number (at character 12"),
+            ("price = cheap", "price is compared with an amount like 2.50 (at character 9"),
+            ("expires = soon", "expires is compared with a day like 2024-03-31 (at character 11"),
+            ("quantity has 5", "has only works on text, not quantity (at character 10"),
+            ("expires has 2024", "has only works on text, not expires (at character 9"),
+            ("name < b", "name is text, so it takes =, != or has (at character 6"),
+            ("name = \"apple", "this text has no closing quote (at character 8"),
+            ("name = apple;", "';' isn't part of a filter (at character 13"),
+            ("(name = apple", "this bracket is never closed (at character 1"),
+            ("name = apple)", "this bracket isn't open (at character 13"),
+            ("name = apple pear", "expected \"and\" or \"or\" here (at character 14"),
+            ("name = apple and", "expected a field, like quantity or name (at character 17"),
+        ];
+        for (filter, expected) in cases {
+            let problem = problem(filter);
+            assert!(problem.starts_with(expected), "{}: {}", filter, problem);
+        }
+    }
+
+    #[test]
+    fn nots_and_brackets_nest_only_so_deep() {
+        let filter = format!("{}name = apple{}", "(".repeat(64), ")".repeat(64));
+        assert_eq!(matching(&filter), ["apple"]);
+        let filter = format!("{}name = apple", "not ".repeat(64));
+        assert_eq!(matching(&filter), ["apple"]);
+
+        let filter = format!("{}name = apple{}", "(".repeat(65), ")".repeat(65));
+        let expected = "not and brackets only nest 64 deep (at character 65 of the filter)";
+        assert_eq!(problem(&filter), expected);
+        let filter = format!("{}(name = apple)", "not ".repeat(64));
+        assert_eq!(problem(&filter), expected.replace("65", "257"));
+        assert!(Filter::parse(&"(".repeat(100_000)).is_err());
+    }
+}
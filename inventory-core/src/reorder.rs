@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use crate::purchasing::Supplier;
+use crate::{Collection, Item};
+
+// What to order to keep each item from running out. An item needs ordering
+// once the stock it can count on, what's free plus what's on order, would
+// drop to its reorder level before a new order could arrive at the rate it
+// has been used. It is then ordered up to enough for the reorder level plus
+// the lead time and as many days again as the rate was measured over.
+
+pub struct Suggestion<'a> {
+    pub item: &'a Item,
+    // Who it was last ordered from, if it ever was.
+    pub supplier: Option<&'a Supplier>,
+    pub reorder_level: u32,
+    // Stock that isn't reserved, plus what open orders will bring.
+    pub expected: u32,
+    pub daily_use: f64,
+    pub quantity: u32,
+}
+
+impl Collection {
+    // `used` is how much of each item, by SKU, went out over the last `days`
+    // days. Items without a reorder level of their own use `default_level`.
+    // Grouped by supplier, so each group can go on one order.
+    pub fn reorder_report(
+        &self,
+        used: &HashMap<String, u32>,
+        days: u32,
+        default_level: u32,
+    ) -> Vec<Suggestion<'_>> {
+        let mut suggestions = Vec::new();
+        for item in self.iter() {
+            let supplier = self.last_supplier(&item.sku);
+            let lead_time = supplier.map_or(0, |supplier| supplier.lead_time_days);
+            let reorder_level = item.reorder_level.unwrap_or(default_level);
+            let daily_use =
+                f64::from(used.get(&item.sku).copied().unwrap_or(0)) / f64::from(days.max(1));
+            let expected = item.available() + self.on_order(&item.sku);
+
+            let reorder_point = f64::from(reorder_level) + daily_use * f64::from(lead_time);
+            if f64::from(expected) > reorder_point {
+                continue;
+            }
+            let target = reorder_point + daily_use * f64::from(days);
+            let quantity = (target.ceil() as u32).saturating_sub(expected).max(1);
+            suggestions.push(Suggestion {
+                item,
+                supplier,
+                reorder_level,
+                expected,
+                daily_use,
+                quantity,
+            });
+        }
+        suggestions.sort_by_key(|suggestion| {
+            (suggestion.supplier.map(|supplier| supplier.name.to_string()), &suggestion.item.name)
+        });
+        suggestions
+    }
+
+    // The supplier of the latest order with the item on it.
+    fn last_supplier(&self, sku: &str) -> Option<&Supplier> {
+        self.orders()
+            .filter(|order| order.lines.iter().any(|line| line.sku == sku))
+            .last()
+            .and_then(|order| self.supplier(order.supplier))
+    }
+
+    // How much of the item open orders are for.
+    fn on_order(&self, sku: &str) -> u32 {
+        self.orders()
+            .filter(|order| order.receipt.is_none())
+            .flat_map(|order| &order.lines)
+            .filter(|line| line.sku == sku)
+            .map(|line| line.quantity)
+            .sum()
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use crate::{Collection, InventoryError};
+
+// What was sold and for how much. A checkout takes the stock out the way
+// fulfill does, all lines or none, and keeps each line's price as it was at
+// the time so later price changes don't rewrite the history.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaleId(pub u64);
+
+impl fmt::Display for SaleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "S-{}", self.0)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaleLine {
+    pub sku: String,
+    // The name when it was sold, in case the item is renamed or removed.
+    pub name: String,
+    pub quantity: u32,
+    // In cents.
+    pub unit_price: u64,
+}
+
+impl SaleLine {
+    pub fn total(&self) -> u64 {
+        u64::from(self.quantity) * self.unit_price
+    }
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sale {
+    pub id: SaleId,
+    pub at: DateTime<Utc>,
+    pub lines: Vec<SaleLine>,
+    // The sum of the lines, in cents.
+    pub total: u64,
+}
+
+// The sales of one day in the local time zone.
+pub struct DailyTotal {
+    pub day: NaiveDate,
+    pub sales: usize,
+    // In cents.
+    pub total: u64,
+}
+
+impl Collection {
+    // Sells the items in `lines`, given by SKU or name. Every line is checked
+    // against the stock that isn't reserved before any is taken out, so an
+    // error leaves the collection unchanged.
+    #[tracing::instrument(skip_all, fields(lines = lines.len()))]
+    pub fn checkout(&mut self, lines: &[(String, u32)]) -> Result<SaleId, InventoryError> {
+        if lines.is_empty() {
+            return Err(InventoryError::EmptySale);
+        }
+        let mut sale_lines = Vec::new();
+        for (key, quantity) in lines {
+            let item = self.lookup(key)?;
+            sale_lines.push(SaleLine {
+                sku: item.sku.to_string(),
+                name: item.name.to_string(),
+                quantity: *quantity,
+                unit_price: item.unit_price,
+            });
+        }
+        let id = SaleId(self.next_sale);
+        self.fulfill_with(lines, Some(&id.to_string()))?;
+        self.next_sale += 1;
+
+        let total = sale_lines.iter().map(SaleLine::total).sum();
+        let sale = Sale { id, at: Utc::now(), lines: sale_lines, total };
+        self.sales.insert(id, sale);
+        Ok(id)
+    }
+
+    pub fn sale(&self, id: SaleId) -> Option<&Sale> {
+        self.sales.get(&id)
+    }
+
+    // Oldest first.
+    pub fn sales(&self) -> impl Iterator<Item = &Sale> {
+        self.sales.values()
+    }
+
+    // How many sales there were on each day with any, and what they came to,
+    // oldest day first.
+    pub fn daily_totals(&self) -> Vec<DailyTotal> {
+        let mut days: BTreeMap<NaiveDate, (usize, u64)> = BTreeMap::new();
+        for sale in self.sales.values() {
+            let day = sale.at.with_timezone(&Local).date_naive();
+            let (sales, total) = days.entry(day).or_insert((0, 0));
+            *sales += 1;
+            *total += sale.total;
+        }
+        days.into_iter().map(|(day, (sales, total))| DailyTotal { day, sales, total }).collect()
+    }
+
+    // Moves the next ID past every one in use, after loading.
+    #[cfg(any(feature = "serde", feature = "sqlite"))]
+    pub(crate) fn reindex_sales(&mut self) {
+        let last = self.sales.keys().next_back().map_or(0, |id| id.0);
+        self.next_sale = self.next_sale.max(last + 1);
+    }
+}
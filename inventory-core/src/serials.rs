@@ -0,0 +1,142 @@
+use std::collections::BTreeSet;
+
+use crate::audit::Snapshot;
+use crate::ledger::Reason;
+use crate::{Collection, InventoryError, Item, LocationId};
+
+// Serialized goods, where every unit in stock has a serial number of its own.
+// Their stock only changes a serial at a time: receiving takes the serials
+// that came in and consuming the one that went out, so the serials always
+// number as many as the quantity. Serials aren't tied to a location.
+
+impl Item {
+    pub fn is_serialized(&self) -> bool {
+        self.serials.is_some()
+    }
+
+    // For the changes that take a quantity rather than serials.
+    pub(crate) fn check_unserialized(&self) -> Result<(), InventoryError> {
+        match self.is_serialized() {
+            true => Err(InventoryError::SerialsRequired { item: self.name.to_string() }),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Collection {
+    // Starts tracking serials for an item. Only allowed while none is in
+    // stock, so there are no units without a serial.
+    pub fn track_serials(&mut self, key: &str) -> Result<(), InventoryError> {
+        let item = self.item_mut(key)?;
+        if item.is_serialized() {
+            return Ok(());
+        }
+        if item.is_lot_tracked() {
+            return Err(InventoryError::TrackingConflict { item: key.to_string() });
+        }
+        if item.quantity > 0 {
+            return Err(InventoryError::SerialTrackingWithStock { item: key.to_string() });
+        }
+        item.serials = Some(BTreeSet::new());
+        Ok(())
+    }
+
+    // Adds one unit to the stock of a serialized item at `location` for each
+    // of `serials`, none of which may be in use already.
+    #[tracing::instrument(
+        skip_all,
+        fields(key = key, location = %location, serials = serials.len())
+    )]
+    pub fn receive_serials(
+        &mut self,
+        key: &str,
+        location: &LocationId,
+        serials: &[String],
+    ) -> Result<(), InventoryError> {
+        self.check_location(location)?;
+        let item = self.lookup(key)?;
+        if !item.is_serialized() {
+            return Err(InventoryError::SerialsNotTracked { item: key.to_string() });
+        }
+        if serials.is_empty() {
+            return Err(InventoryError::NoSerials);
+        }
+        let mut seen = BTreeSet::new();
+        for serial in serials {
+            if let Ok(owner) = self.find_serial(serial) {
+                let item = owner.name.to_string();
+                return Err(InventoryError::SerialInUse { serial: serial.to_string(), item });
+            }
+            if !seen.insert(serial) {
+                let item = key.to_string();
+                return Err(InventoryError::SerialInUse { serial: serial.to_string(), item });
+            }
+        }
+        let qty = serials.len() as u32;
+        let quantity = item
+            .quantity
+            .checked_add(qty)
+            .ok_or_else(|| InventoryError::QuantityOverflow { item: key.to_string() })?;
+        self.check_max(quantity)?;
+
+        let item = self.item_mut(key)?;
+        let before = Snapshot::of(item);
+        *item.locations.entry(location.clone()).or_insert(0) += qty;
+        item.quantity = quantity;
+        item.serials.get_or_insert_with(BTreeSet::new).extend(serials.iter().cloned());
+        item.touch();
+        let (sku, name) = (item.sku.to_string(), item.name.to_string());
+        let reference = serials.join(", ");
+        self.record_change_as(&sku, &name, Some(before), Reason::Received, Some(&reference));
+        Ok(())
+    }
+
+    // Takes the unit with `serial` out of stock at `location` and notes why
+    // in the ledger. As with consume, reserved stock can't be taken.
+    #[tracing::instrument(
+        skip_all,
+        fields(serial = serial, location = %location, reason = %reason)
+    )]
+    pub fn consume_serial(
+        &mut self,
+        serial: &str,
+        location: &LocationId,
+        reason: Reason,
+    ) -> Result<(), InventoryError> {
+        self.check_location(location)?;
+        let item = self.find_serial(serial)?;
+        let available = item.quantity_at(location).min(item.available());
+        if available == 0 {
+            return Err(InventoryError::InsufficientStock {
+                item: item.name.to_string(),
+                requested: 1,
+                available,
+            });
+        }
+
+        let sku = item.sku.to_string();
+        let item = self._items.get_mut(&sku).expect("find_serial only returns stored items");
+        let before = Snapshot::of(item);
+        if let Some(held) = item.locations.get_mut(location) {
+            *held -= 1;
+            if *held == 0 {
+                item.locations.remove(location);
+            }
+        }
+        item.quantity -= 1;
+        if let Some(serials) = item.serials.as_mut() {
+            serials.remove(serial);
+        }
+        item.touch();
+        let name = item.name.to_string();
+        self.record_change_as(&sku, &name, Some(before), reason, Some(serial));
+        Ok(())
+    }
+
+    // The item the unit with `serial` belongs to.
+    pub fn find_serial(&self, serial: &str) -> Result<&Item, InventoryError> {
+        self.iter()
+            .find(|item| item.serials.as_ref().is_some_and(|serials| serials.contains(serial)))
+            .ok_or_else(|| InventoryError::SerialNotFound { serial: serial.to_string() })
+    }
+}
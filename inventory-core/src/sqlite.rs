@@ -0,0 +1,669 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::kits::Component;
+use crate::ledger::{Movement, Reason};
+use crate::lots::Lot;
+use crate::purchasing::{OrderId, OrderLine, PurchaseOrder, Receipt, Supplier, SupplierId};
+use crate::sales::{Sale, SaleId, SaleLine};
+use crate::{parse_date, Collection, Item, LocationId, ReservationId};
+
+// Each step brings the schema up one version; `PRAGMA user_version` records
+// how many have run. Only ever append to this list.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS items (
+        name     TEXT PRIMARY KEY,
+        quantity INTEGER NOT NULL,
+        category TEXT NOT NULL,
+        reserved INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS reservations (
+        id       INTEGER PRIMARY KEY,
+        item     TEXT NOT NULL,
+        quantity INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS settings (
+        key   TEXT PRIMARY KEY,
+        value INTEGER
+    );",
+    "ALTER TABLE items ADD COLUMN unit_price INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE items ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;",
+    // Items move from being keyed by name to being keyed by SKU.
+    "CREATE TABLE items_by_sku (
+        sku        TEXT PRIMARY KEY,
+        name       TEXT NOT NULL,
+        quantity   INTEGER NOT NULL,
+        category   TEXT NOT NULL,
+        reserved   INTEGER NOT NULL DEFAULT 0,
+        unit_price INTEGER NOT NULL DEFAULT 0,
+        updated_at INTEGER NOT NULL DEFAULT 0
+    );
+    INSERT INTO items_by_sku
+        SELECT printf('SKU-%06d', rowid), name, quantity, category, reserved, unit_price,
+               updated_at
+        FROM items;
+    UPDATE reservations
+        SET item = (SELECT sku FROM items_by_sku WHERE items_by_sku.name = reservations.item);
+    DROP TABLE items;
+    ALTER TABLE items_by_sku RENAME TO items;",
+    "ALTER TABLE items ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;",
+    // Stock moves from one quantity per item to one per item and location.
+    // Items without rows here have all their stock at the main location.
+    "CREATE TABLE locations (
+        name TEXT PRIMARY KEY
+    );
+    CREATE TABLE stock (
+        item     TEXT NOT NULL,
+        location TEXT NOT NULL,
+        quantity INTEGER NOT NULL,
+        PRIMARY KEY (item, location)
+    );",
+    // Days are stored as text like 2024-03-31.
+    "ALTER TABLE items ADD COLUMN expires_on TEXT;",
+    "ALTER TABLE items ADD COLUMN unit TEXT NOT NULL DEFAULT 'pcs';",
+    // An order's receipt is its received_at and location, both null until
+    // it arrives.
+    "CREATE TABLE suppliers (
+        id      INTEGER PRIMARY KEY,
+        name    TEXT NOT NULL,
+        contact TEXT NOT NULL DEFAULT ''
+    );
+    CREATE TABLE purchase_orders (
+        id          INTEGER PRIMARY KEY,
+        supplier    INTEGER NOT NULL,
+        created_at  INTEGER NOT NULL,
+        received_at INTEGER,
+        location    TEXT
+    );
+    CREATE TABLE order_lines (
+        purchase_order INTEGER NOT NULL,
+        line           INTEGER NOT NULL,
+        item           TEXT NOT NULL,
+        quantity       INTEGER NOT NULL,
+        PRIMARY KEY (purchase_order, line)
+    );",
+    "CREATE TABLE sales (
+        id    INTEGER PRIMARY KEY,
+        at    INTEGER NOT NULL,
+        total INTEGER NOT NULL
+    );
+    CREATE TABLE sale_lines (
+        sale       INTEGER NOT NULL,
+        line       INTEGER NOT NULL,
+        item       TEXT NOT NULL,
+        name       TEXT NOT NULL,
+        quantity   INTEGER NOT NULL,
+        unit_price INTEGER NOT NULL,
+        PRIMARY KEY (sale, line)
+    );",
+    "ALTER TABLE items ADD COLUMN reorder_level INTEGER;
+    ALTER TABLE suppliers ADD COLUMN lead_time_days INTEGER NOT NULL DEFAULT 0;",
+    // Movements are numbered in the order they happened. Items without any
+    // get an opening one when loaded.
+    "CREATE TABLE movements (
+        id        INTEGER PRIMARY KEY,
+        item      TEXT NOT NULL,
+        delta     INTEGER NOT NULL,
+        reason    TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        reference TEXT
+    );",
+    // Serialized items have serialized set, and a row here for each unit
+    // in stock.
+    "ALTER TABLE items ADD COLUMN serialized INTEGER NOT NULL DEFAULT 0;
+    CREATE TABLE serials (
+        serial TEXT PRIMARY KEY,
+        item   TEXT NOT NULL
+    );",
+    // Items tracked by lot have lot_tracked set, and a row here for each lot
+    // with stock left.
+    "ALTER TABLE items ADD COLUMN lot_tracked INTEGER NOT NULL DEFAULT 0;
+    CREATE TABLE lots (
+        item        TEXT NOT NULL,
+        lot         TEXT NOT NULL,
+        quantity    INTEGER NOT NULL,
+        received_on TEXT NOT NULL,
+        expires_on  TEXT,
+        PRIMARY KEY (item, lot)
+    );",
+    "CREATE TABLE kit_components (
+        kit      TEXT NOT NULL,
+        item     TEXT NOT NULL,
+        quantity INTEGER NOT NULL,
+        PRIMARY KEY (kit, item)
+    );",
+    "ALTER TABLE movements ADD COLUMN location TEXT;",
+    "ALTER TABLE items ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE attributes (
+        item  TEXT NOT NULL,
+        name  TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (item, name)
+    );",
+];
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let mut conn = Connection::open(path)?;
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let version = version as usize;
+    if version < MIGRATIONS.len() {
+        let tx = conn.transaction()?;
+        for migration in &MIGRATIONS[version..] {
+            tx.execute_batch(migration)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+        tx.commit()?;
+    }
+    Ok(conn)
+}
+
+// Times are stored as milliseconds since the Unix epoch.
+fn time(millis: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(millis).unwrap_or_default()
+}
+
+// Reasons are stored as the names Display writes.
+fn reason(name: &str) -> Reason {
+    match name {
+        "received" => Reason::Received,
+        "sold" => Reason::Sold,
+        "damaged" => Reason::Damaged,
+        "assembly" => Reason::Assembly,
+        "transfer" => Reason::Transfer,
+        _ => Reason::Adjustment,
+    }
+}
+
+fn setting(conn: &Connection, key: &str) -> rusqlite::Result<Option<i64>> {
+    conn.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+        .optional()
+        .map(Option::flatten)
+}
+
+// Loads the whole collection from the database at `path`.
+pub fn load(path: &Path) -> rusqlite::Result<Collection> {
+    let conn = open(path)?;
+    let mut collection = Collection::new();
+
+    let mut stmt = conn.prepare(
+        "SELECT sku, name, quantity, category, reserved, unit_price, created_at, updated_at,
+                expires_on, unit, reorder_level, serialized, lot_tracked, archived
+         FROM items",
+    )?;
+    // Archived items are loaded like the rest, then moved into the archive.
+    let mut archived = Vec::new();
+    let items = stmt.query_map([], |row| {
+        let item = Item {
+            sku: row.get(0)?,
+            name: row.get(1)?,
+            quantity: row.get(2)?,
+            // Units are stored as the symbols Display writes.
+            unit: row.get::<_, String>(9)?.parse().unwrap_or_default(),
+            category: row.get(3)?,
+            reserved: row.get(4)?,
+            locations: HashMap::new(),
+            unit_price: row.get::<_, i64>(5)? as u64,
+            created_at: time(row.get(6)?),
+            updated_at: time(row.get(7)?),
+            expires_on: row.get::<_, Option<String>>(8)?.as_deref().and_then(parse_date),
+            reorder_level: row.get::<_, Option<i64>>(10)?.map(|level| level as u32),
+            serials: row.get::<_, bool>(11)?.then(BTreeSet::new),
+            lots: row.get::<_, bool>(12)?.then(Vec::new),
+            components: Vec::new(),
+            attributes: HashMap::new(),
+        };
+        Ok((item, row.get::<_, bool>(13)?))
+    })?;
+    for row in items {
+        let (item, is_archived) = row?;
+        if is_archived {
+            archived.push(item.sku.to_string());
+        }
+        collection._items.insert(item.sku.to_string(), item);
+    }
+
+    let mut stmt = conn.prepare("SELECT item, location, quantity FROM stock")?;
+    let stock = stmt.query_map([], |row| {
+        let quantity: i64 = row.get(2)?;
+        Ok((row.get::<_, String>(0)?, LocationId(row.get(1)?), quantity as u32))
+    })?;
+    for row in stock {
+        let (sku, location, quantity) = row?;
+        if let Some(item) = collection._items.get_mut(&sku) {
+            item.locations.insert(location, quantity);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT serial, item FROM serials")?;
+    let serials =
+        stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for row in serials {
+        let (serial, sku) = row?;
+        let item = collection._items.get_mut(&sku);
+        if let Some(serials) = item.and_then(|item| item.serials.as_mut()) {
+            serials.insert(serial);
+        }
+    }
+
+    let mut stmt =
+        conn.prepare("SELECT item, lot, quantity, received_on, expires_on FROM lots")?;
+    let lots = stmt.query_map([], |row| {
+        let lot = Lot {
+            id: row.get(1)?,
+            quantity: row.get::<_, i64>(2)? as u32,
+            received_on: parse_date(&row.get::<_, String>(3)?).unwrap_or_default(),
+            expires_on: row.get::<_, Option<String>>(4)?.as_deref().and_then(parse_date),
+        };
+        Ok((row.get::<_, String>(0)?, lot))
+    })?;
+    for row in lots {
+        let (sku, lot) = row?;
+        if let Some(lots) = collection._items.get_mut(&sku).and_then(|item| item.lots.as_mut()) {
+            lots.push(lot);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT kit, item, quantity FROM kit_components")?;
+    let components = stmt.query_map([], |row| {
+        let quantity: i64 = row.get(2)?;
+        let component = Component { sku: row.get(1)?, quantity: quantity as u32 };
+        Ok((row.get::<_, String>(0)?, component))
+    })?;
+    for row in components {
+        let (kit, component) = row?;
+        if let Some(kit) = collection._items.get_mut(&kit) {
+            kit.components.push(component);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT item, name, value FROM attributes")?;
+    let attributes = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+    for row in attributes {
+        let (sku, name, value) = row?;
+        if let Some(item) = collection._items.get_mut(&sku) {
+            item.attributes.insert(name, value);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT name FROM locations")?;
+    let locations = stmt.query_map([], |row| row.get(0))?;
+    for location in locations {
+        collection.locations.insert(LocationId(location?));
+    }
+
+    let mut stmt = conn.prepare("SELECT id, item, quantity FROM reservations")?;
+    let reservations = stmt.query_map([], |row| {
+        let id: i64 = row.get(0)?;
+        Ok((ReservationId(id as u64), row.get::<_, String>(1)?, row.get::<_, u32>(2)?))
+    })?;
+    for reservation in reservations {
+        let (id, sku, quantity) = reservation?;
+        collection.reservations.insert(id, (sku, quantity));
+    }
+
+    let mut stmt = conn.prepare("SELECT id, name, contact, lead_time_days FROM suppliers")?;
+    let suppliers = stmt.query_map([], |row| {
+        Ok(Supplier {
+            id: SupplierId(row.get::<_, i64>(0)? as u64),
+            name: row.get(1)?,
+            contact: row.get(2)?,
+            lead_time_days: row.get::<_, i64>(3)? as u32,
+        })
+    })?;
+    for supplier in suppliers {
+        let supplier = supplier?;
+        collection.suppliers.insert(supplier.id, supplier);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT id, supplier, created_at, received_at, location FROM purchase_orders")?;
+    let orders = stmt.query_map([], |row| {
+        let received_at: Option<i64> = row.get(3)?;
+        let location: Option<String> = row.get(4)?;
+        Ok(PurchaseOrder {
+            id: OrderId(row.get::<_, i64>(0)? as u64),
+            supplier: SupplierId(row.get::<_, i64>(1)? as u64),
+            lines: Vec::new(),
+            created_at: time(row.get(2)?),
+            receipt: received_at.zip(location).map(|(at, location)| Receipt {
+                at: time(at),
+                location: LocationId(location),
+            }),
+        })
+    })?;
+    for order in orders {
+        let order = order?;
+        collection.orders.insert(order.id, order);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT purchase_order, item, quantity FROM order_lines ORDER BY purchase_order, line",
+    )?;
+    let lines = stmt.query_map([], |row| {
+        let quantity: i64 = row.get(2)?;
+        Ok((OrderId(row.get::<_, i64>(0)? as u64), row.get::<_, String>(1)?, quantity as u32))
+    })?;
+    for line in lines {
+        let (id, sku, quantity) = line?;
+        if let Some(order) = collection.orders.get_mut(&id) {
+            order.lines.push(OrderLine { sku, quantity });
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT id, at, total FROM sales")?;
+    let sales = stmt.query_map([], |row| {
+        Ok(Sale {
+            id: SaleId(row.get::<_, i64>(0)? as u64),
+            at: time(row.get(1)?),
+            lines: Vec::new(),
+            total: row.get::<_, i64>(2)? as u64,
+        })
+    })?;
+    for sale in sales {
+        let sale = sale?;
+        collection.sales.insert(sale.id, sale);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT sale, item, name, quantity, unit_price FROM sale_lines ORDER BY sale, line",
+    )?;
+    let lines = stmt.query_map([], |row| {
+        let line = SaleLine {
+            sku: row.get(1)?,
+            name: row.get(2)?,
+            quantity: row.get::<_, i64>(3)? as u32,
+            unit_price: row.get::<_, i64>(4)? as u64,
+        };
+        Ok((SaleId(row.get::<_, i64>(0)? as u64), line))
+    })?;
+    for line in lines {
+        let (id, line) = line?;
+        if let Some(sale) = collection.sales.get_mut(&id) {
+            sale.lines.push(line);
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT item, delta, reason, timestamp, reference, location FROM movements ORDER BY id",
+    )?;
+    let movements = stmt.query_map([], |row| {
+        Ok(Movement {
+            sku: row.get(0)?,
+            delta: row.get(1)?,
+            reason: reason(&row.get::<_, String>(2)?),
+            timestamp: time(row.get(3)?),
+            reference: row.get(4)?,
+            location: row.get::<_, Option<String>>(5)?.map(LocationId),
+        })
+    })?;
+    for movement in movements {
+        collection.movements.push(movement?);
+    }
+
+    collection.max_per_item = setting(&conn, "max_per_item")?.map(|max| max as u32);
+    if let Some(next) = setting(&conn, "next_reservation")? {
+        collection.next_reservation = next as u64;
+    }
+    if let Some(next) = setting(&conn, "next_sku")? {
+        collection.next_sku = next as u64;
+    }
+    if let Some(next) = setting(&conn, "next_supplier")? {
+        collection.next_supplier = next as u64;
+    }
+    if let Some(next) = setting(&conn, "next_order")? {
+        collection.next_order = next as u64;
+    }
+    if let Some(next) = setting(&conn, "next_sale")? {
+        collection.next_sale = next as u64;
+    }
+    for sku in archived {
+        if let Some(item) = collection._items.remove(&sku) {
+            collection.archived.insert(sku, item);
+        }
+    }
+    collection.reindex();
+    Ok(collection)
+}
+
+// Writes the collection to the database at `path` in one transaction:
+// items are inserted or updated, and rows for items that no longer exist
+// are deleted.
+pub fn save(path: &Path, collection: &Collection) -> rusqlite::Result<()> {
+    let mut conn = open(path)?;
+    let tx = conn.transaction()?;
+
+    let stored: Vec<String> = {
+        let mut stmt = tx.prepare("SELECT sku FROM items")?;
+        let skus = stmt.query_map([], |row| row.get(0))?;
+        skus.collect::<rusqlite::Result<_>>()?
+    };
+    for sku in stored {
+        if !collection._items.contains_key(&sku) && !collection.archived.contains_key(&sku) {
+            tx.execute("DELETE FROM items WHERE sku = ?1", params![sku])?;
+        }
+    }
+
+    let items: Vec<&Item> = collection.iter().chain(collection.archived.values()).collect();
+    for item in &items {
+        tx.execute(
+            "INSERT INTO items
+                 (sku, name, quantity, category, reserved, unit_price, created_at, updated_at,
+                  expires_on, unit, reorder_level, serialized, lot_tracked, archived)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+             ON CONFLICT(sku) DO UPDATE SET
+                 name = excluded.name,
+                 quantity = excluded.quantity,
+                 category = excluded.category,
+                 reserved = excluded.reserved,
+                 unit_price = excluded.unit_price,
+                 created_at = excluded.created_at,
+                 updated_at = excluded.updated_at,
+                 expires_on = excluded.expires_on,
+                 unit = excluded.unit,
+                 reorder_level = excluded.reorder_level,
+                 serialized = excluded.serialized,
+                 lot_tracked = excluded.lot_tracked,
+                 archived = excluded.archived",
+            params![
+                item.sku,
+                item.name,
+                item.quantity,
+                item.category,
+                item.reserved,
+                item.unit_price as i64,
+                item.created_at.timestamp_millis(),
+                item.updated_at.timestamp_millis(),
+                item.expires_on.map(|day| day.to_string()),
+                item.unit.to_string(),
+                item.reorder_level.map(i64::from),
+                item.is_serialized(),
+                item.is_lot_tracked(),
+                collection.archived.contains_key(&item.sku)
+            ],
+        )?;
+    }
+
+    tx.execute("DELETE FROM stock", [])?;
+    for item in &items {
+        for (location, quantity) in &item.locations {
+            tx.execute(
+                "INSERT INTO stock (item, location, quantity) VALUES (?1, ?2, ?3)",
+                params![item.sku, location.0, i64::from(*quantity)],
+            )?;
+        }
+    }
+
+    tx.execute("DELETE FROM serials", [])?;
+    for item in &items {
+        for serial in item.serials.iter().flatten() {
+            tx.execute(
+                "INSERT INTO serials (serial, item) VALUES (?1, ?2)",
+                params![serial, item.sku],
+            )?;
+        }
+    }
+
+    tx.execute("DELETE FROM lots", [])?;
+    for item in &items {
+        for lot in item.lots.iter().flatten() {
+            tx.execute(
+                "INSERT INTO lots (item, lot, quantity, received_on, expires_on)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    item.sku,
+                    lot.id,
+                    i64::from(lot.quantity),
+                    lot.received_on.to_string(),
+                    lot.expires_on.map(|day| day.to_string())
+                ],
+            )?;
+        }
+    }
+
+    tx.execute("DELETE FROM kit_components", [])?;
+    for item in &items {
+        for component in &item.components {
+            tx.execute(
+                "INSERT INTO kit_components (kit, item, quantity) VALUES (?1, ?2, ?3)",
+                params![item.sku, component.sku, i64::from(component.quantity)],
+            )?;
+        }
+    }
+
+    tx.execute("DELETE FROM attributes", [])?;
+    for item in &items {
+        for (name, value) in &item.attributes {
+            tx.execute(
+                "INSERT INTO attributes (item, name, value) VALUES (?1, ?2, ?3)",
+                params![item.sku, name, value],
+            )?;
+        }
+    }
+
+    tx.execute("DELETE FROM locations", [])?;
+    for location in &collection.locations {
+        tx.execute("INSERT INTO locations (name) VALUES (?1)", params![location.0])?;
+    }
+
+    tx.execute("DELETE FROM reservations", [])?;
+    for (id, (sku, quantity)) in &collection.reservations {
+        tx.execute(
+            "INSERT INTO reservations (id, item, quantity) VALUES (?1, ?2, ?3)",
+            params![id.0 as i64, sku, quantity],
+        )?;
+    }
+
+    tx.execute("DELETE FROM suppliers", [])?;
+    for supplier in collection.suppliers.values() {
+        tx.execute(
+            "INSERT INTO suppliers (id, name, contact, lead_time_days) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                supplier.id.0 as i64,
+                supplier.name,
+                supplier.contact,
+                i64::from(supplier.lead_time_days)
+            ],
+        )?;
+    }
+
+    tx.execute("DELETE FROM purchase_orders", [])?;
+    tx.execute("DELETE FROM order_lines", [])?;
+    for order in collection.orders.values() {
+        let receipt = order.receipt.as_ref();
+        tx.execute(
+            "INSERT INTO purchase_orders (id, supplier, created_at, received_at, location)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                order.id.0 as i64,
+                order.supplier.0 as i64,
+                order.created_at.timestamp_millis(),
+                receipt.map(|receipt| receipt.at.timestamp_millis()),
+                receipt.map(|receipt| receipt.location.0.to_string())
+            ],
+        )?;
+        for (line, order_line) in order.lines.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO order_lines (purchase_order, line, item, quantity)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    order.id.0 as i64,
+                    line as i64,
+                    order_line.sku,
+                    i64::from(order_line.quantity)
+                ],
+            )?;
+        }
+    }
+
+    tx.execute("DELETE FROM sales", [])?;
+    tx.execute("DELETE FROM sale_lines", [])?;
+    for sale in collection.sales.values() {
+        tx.execute(
+            "INSERT INTO sales (id, at, total) VALUES (?1, ?2, ?3)",
+            params![sale.id.0 as i64, sale.at.timestamp_millis(), sale.total as i64],
+        )?;
+        for (line, sale_line) in sale.lines.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO sale_lines (sale, line, item, name, quantity, unit_price)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    sale.id.0 as i64,
+                    line as i64,
+                    sale_line.sku,
+                    sale_line.name,
+                    i64::from(sale_line.quantity),
+                    sale_line.unit_price as i64
+                ],
+            )?;
+        }
+    }
+
+    tx.execute("DELETE FROM movements", [])?;
+    for (id, movement) in collection.movements.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO movements (id, item, delta, reason, timestamp, reference, location)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                id as i64,
+                movement.sku,
+                movement.delta,
+                movement.reason.to_string(),
+                movement.timestamp.timestamp_millis(),
+                movement.reference,
+                movement.location.as_ref().map(|location| location.0.to_string())
+            ],
+        )?;
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('max_per_item', ?1)",
+        params![collection.max_per_item],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('next_reservation', ?1)",
+        params![collection.next_reservation as i64],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('next_sku', ?1)",
+        params![collection.next_sku as i64],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('next_supplier', ?1)",
+        params![collection.next_supplier as i64],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('next_order', ?1)",
+        params![collection.next_order as i64],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES ('next_sale', ?1)",
+        params![collection.next_sale as i64],
+    )?;
+
+    tx.commit()
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::InventoryError;
+
+// What an item's quantity is counted in. Units of the same kind convert into
+// each other; boxes don't convert into anything, since how many pieces a box
+// holds differs from item to item.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Unit {
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "pcs"))]
+    Pieces,
+    #[cfg_attr(feature = "serde", serde(rename = "box"))]
+    Boxes,
+    #[cfg_attr(feature = "serde", serde(rename = "g"))]
+    Grams,
+    #[cfg_attr(feature = "serde", serde(rename = "kg"))]
+    Kilograms,
+    #[cfg_attr(feature = "serde", serde(rename = "ml"))]
+    Milliliters,
+    #[cfg_attr(feature = "serde", serde(rename = "l"))]
+    Liters,
+}
+
+#[derive(PartialEq)]
+enum Kind {
+    Count,
+    Boxes,
+    Mass,
+    Volume,
+}
+
+impl Unit {
+    // The kind of unit and how many of the smallest unit of that kind one of
+    // this is.
+    fn base(self) -> (Kind, u64) {
+        match self {
+            Unit::Pieces => (Kind::Count, 1),
+            Unit::Boxes => (Kind::Boxes, 1),
+            Unit::Grams => (Kind::Mass, 1),
+            Unit::Kilograms => (Kind::Mass, 1000),
+            Unit::Milliliters => (Kind::Volume, 1),
+            Unit::Liters => (Kind::Volume, 1000),
+        }
+    }
+
+    // `amount` of this unit in `to`. Quantities are whole numbers, so an
+    // amount that doesn't come out whole, like 500 g in kg, is an error.
+    pub fn convert(self, amount: u32, to: Unit) -> Result<u32, InventoryError> {
+        let ((from_kind, from_factor), (to_kind, to_factor)) = (self.base(), to.base());
+        if from_kind != to_kind {
+            return Err(InventoryError::IncompatibleUnits { from: self, to });
+        }
+        let base = u64::from(amount) * from_factor;
+        if base % to_factor != 0 {
+            return Err(InventoryError::InexactConversion { amount, from: self, to });
+        }
+        u32::try_from(base / to_factor)
+            .map_err(|_| InventoryError::ConversionOverflow { amount, from: self, to })
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Unit::Pieces => "pcs",
+            Unit::Boxes => "box",
+            Unit::Grams => "g",
+            Unit::Kilograms => "kg",
+            Unit::Milliliters => "ml",
+            Unit::Liters => "l",
+        };
+        // Padded to the width asked for, so table columns line up.
+        f.pad(symbol)
+    }
+}
+
+impl FromStr for Unit {
+    type Err = InventoryError;
+
+    // Takes the symbols Display writes as well as the names spelled out.
+    fn from_str(s: &str) -> Result<Unit, InventoryError> {
+        match s.trim().to_lowercase().as_str() {
+            "pcs" | "piece" | "pieces" => Ok(Unit::Pieces),
+            "box" | "boxes" => Ok(Unit::Boxes),
+            "g" | "gram" | "grams" => Ok(Unit::Grams),
+            "kg" | "kilogram" | "kilograms" => Ok(Unit::Kilograms),
+            "ml" | "milliliter" | "milliliters" | "millilitre" | "millilitres" => {
+                Ok(Unit::Milliliters)
+            }
+            "l" | "liter" | "liters" | "litre" | "litres" => Ok(Unit::Liters),
+            _ => Err(InventoryError::UnknownUnit { unit: s.to_string() }),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+// Generates the server side of the service in proto/inventory.proto. It's
+// described here instead of compiled from the file, so building needs no
+// protoc; the messages are written out in src/server/grpc.rs.
+#[cfg(feature = "grpc")]
+fn grpc() {
+    use tonic_build::manual::{Builder, Method, MethodBuilder, Service};
+
+    fn method(name: &str, route: &str, input: &str, output: &str) -> MethodBuilder {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("super::{}", input))
+            .output_type(format!("super::{}", output))
+            .codec_path("tonic_prost::ProstCodec")
+    }
+
+    let service = Service::builder()
+        .name("Inventory")
+        .package("inventory")
+        .method(method("list_items", "ListItems", "ListItemsRequest", "ListItemsResponse").build())
+        .method(method("get_item", "GetItem", "ItemKey", "Item").build())
+        .method(method("add_item", "AddItem", "NewItem", "Item").build())
+        .method(method("update_item", "UpdateItem", "ItemUpdate", "Item").build())
+        .method(method("delete_item", "DeleteItem", "ItemKey", "Item").build())
+        .method(
+            method("watch_stock", "WatchStock", "WatchStockRequest", "StockChange")
+                .server_streaming()
+                .build(),
+        )
+        .build();
+    Builder::new().build_client(false).build_transport(false).compile(&[service]);
+}
@@ -0,0 +1,130 @@
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use inventory_core::Collection;
+
+use crate::config::Config;
+use crate::Storage;
+
+// A collection shared between threads, written to its storage in the
+// background: every `interval`, or as soon as `max_changes` changes have
+// piled up, whichever comes first. Nothing is written while no change has
+// been made since the last save.
+pub struct Autosave {
+    shared: Arc<Shared>,
+    saver: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    collection: RwLock<Collection>,
+    pending: Mutex<Pending>,
+    // Held for every write to the storage, so they land in the order the
+    // changes were made.
+    saving: Mutex<()>,
+    // Signalled on every change and when stopping.
+    wake: Condvar,
+}
+
+#[derive(Default)]
+struct Pending {
+    // Changes since the last save; 0 means the file is up to date.
+    changes: usize,
+    stopping: bool,
+}
+
+impl Autosave {
+    // Without storage, or with autosave_seconds at 0, there is no saver
+    // thread and the collection is only shared.
+    pub fn start(collection: Collection, storage: Option<Storage>, config: &Config) -> Autosave {
+        let shared = Arc::new(Shared {
+            collection: RwLock::new(collection),
+            pending: Mutex::new(Pending::default()),
+            saving: Mutex::new(()),
+            wake: Condvar::new(),
+        });
+        let saver = match storage {
+            Some(storage) if config.autosave_seconds > 0 => {
+                let shared = Arc::clone(&shared);
+                let interval = Duration::from_secs(config.autosave_seconds);
+                let max_changes = config.autosave_changes.max(1);
+                Some(thread::spawn(move || shared.save_loop(&storage, interval, max_changes)))
+            }
+            _ => None,
+        };
+        Autosave { shared, saver }
+    }
+
+    // Access to change the collection. Call changed afterwards if anything
+    // did, so it gets saved.
+    pub fn write(&self) -> RwLockWriteGuard<'_, Collection> {
+        self.shared.collection.write().unwrap()
+    }
+
+    pub fn changed(&self) {
+        self.shared.pending.lock().unwrap().changes += 1;
+        self.shared.wake.notify_one();
+    }
+
+    // Hold this around a save made directly, like the menu's, so it doesn't
+    // overlap one made in the background.
+    pub fn saving(&self) -> MutexGuard<'_, ()> {
+        self.shared.saving.lock().unwrap()
+    }
+
+    // For a save made directly, while still holding saving: nothing is
+    // pending any more.
+    pub fn saved(&self) {
+        self.shared.pending.lock().unwrap().changes = 0;
+    }
+}
+
+// Stops the saver thread once it has written any changes still pending.
+impl Drop for Autosave {
+    fn drop(&mut self) {
+        self.shared.pending.lock().unwrap().stopping = true;
+        self.shared.wake.notify_one();
+        if let Some(saver) = self.saver.take() {
+            // A panic in the saver has been printed already.
+            let _ = saver.join();
+        }
+    }
+}
+
+impl Shared {
+    fn save_loop(&self, storage: &Storage, interval: Duration, max_changes: usize) {
+        loop {
+            let mut pending = self.pending.lock().unwrap();
+            // Stops waiting early when enough changes pile up or on stop;
+            // otherwise saves whatever there is when the interval is up.
+            pending = self
+                .wake
+                .wait_timeout_while(pending, interval, |pending| {
+                    pending.changes < max_changes && !pending.stopping
+                })
+                .unwrap()
+                .0;
+            let stopping = pending.stopping;
+            drop(pending);
+
+            // The changes and the copy are taken inside `saving`, so a save
+            // made meanwhile from the menu isn't overwritten with an older
+            // copy.
+            let _saving = self.saving.lock().unwrap();
+            let changes = std::mem::take(&mut self.pending.lock().unwrap().changes);
+            if changes > 0 {
+                // A copy, so the menu isn't held up while the file is written.
+                let snapshot = self.collection.read().unwrap().clone();
+                let saved = storage.save(&snapshot);
+                if let Err(e) = saved {
+                    tracing::error!(file = storage.path(), error = %e, "could not autosave");
+                    // Try again next time round.
+                    self.pending.lock().unwrap().changes += changes;
+                }
+            }
+            if stopping {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+// Backups of a file go in this folder next to it, named after the file and
+// the time they were taken, so sorting them by name sorts them by age.
+const BACKUP_DIR: &str = "backups";
+
+fn backup_dir(path: &Path) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join(BACKUP_DIR)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// Copies the file into a new backup and returns where it went.
+pub fn create(path: &Path) -> io::Result<PathBuf> {
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+    let stamp = Local::now().format("%Y%m%d-%H%M%S%.3f");
+    let backup = dir.join(format!("{}.{}", file_name(path), stamp));
+    fs::copy(path, &backup)?;
+    Ok(backup)
+}
+
+// The backups of the file, newest first. None have been made when the folder
+// doesn't exist.
+pub fn list(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let prefix = format!("{}.", file_name(path));
+    let entries = match fs::read_dir(backup_dir(path)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut backups: Vec<PathBuf> = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+// Deletes all but the newest `keep` backups of the file.
+pub fn prune(path: &Path, keep: usize) -> io::Result<()> {
+    for old in list(path)?.into_iter().skip(keep) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+// Puts `backup` in place of the file. What the file held until now is backed
+// up first, so a restore can itself be undone.
+pub fn restore(path: &Path, backup: &Path, keep: usize) -> io::Result<()> {
+    if path.exists() {
+        create(path)?;
+    }
+    fs::copy(backup, path)?;
+    if keep > 0 {
+        prune(path, keep)?;
+    }
+    Ok(())
+}
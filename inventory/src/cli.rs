@@ -0,0 +1,1191 @@
+use std::cmp::Reverse;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use clap::builder::RangedU64ValueParser;
+use clap::{Parser, Subcommand};
+
+use inventory_core::{
+    format_price, page_of, parse_date, parse_price, Collection, Item, LocationId, SortOrder,
+};
+
+use inventory_core::aggregate::{Group, GroupBy, Summary};
+use inventory_core::fuzzy::did_you_mean;
+use inventory_core::ledger::Reason;
+use inventory_core::query::Filter;
+use inventory_core::unit::Unit;
+
+use crate::backup;
+use crate::terminal_width;
+use crate::config::Config;
+use crate::prompt;
+use crate::report::{self, ReportFormat};
+use crate::{parse_quantity, Storage};
+
+// Command line front end. With no subcommand the interactive menu runs.
+#[derive(Parser)]
+#[command(name = "inventory", about = "Keep track of items and their quantities")]
+pub struct Cli {
+    #[arg(long, global = true, help = "Where the collection is kept: json, events or sqlite")]
+    pub storage: Option<String>,
+
+    #[arg(long, global = true, help = "Don't color tables, e.g. when piping them")]
+    pub no_color: bool,
+
+    #[cfg(feature = "tui")]
+    #[arg(long, help = "Browse and edit the items in a full-screen view")]
+    pub tui: bool,
+
+    #[cfg(feature = "rpc")]
+    #[arg(long, help = "Answer JSON-RPC 2.0 requests on stdin, one per line, instead")]
+    pub rpc: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Run the subcommands in FILE, one per line; - reads them from stdin"
+    )]
+    pub script: Option<String>,
+
+    #[cfg(feature = "daemon")]
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "storage",
+        help = "Send the subcommand to a running `inventory daemon` instead of loading the file"
+    )]
+    pub connect: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+// One line of a script: a subcommand without the program name.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct ScriptLine {
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+pub enum CliCommand {
+    #[command(about = "Add an item under a new SKU")]
+    Add {
+        name: String,
+        #[arg(value_parser = parse_quantity)]
+        quantity: u32,
+        #[arg(long, default_value = "uncategorized")]
+        category: String,
+        #[arg(long, default_value = "pcs", help = "pcs, box, g, kg, ml or l")]
+        unit: Unit,
+        #[arg(long, value_parser = price, default_value = "0")]
+        price: u64,
+        #[arg(long, value_parser = date, help = "Last day the item can be used, as YYYY-MM-DD")]
+        expires: Option<NaiveDate>,
+    },
+    #[command(about = "Overwrite the quantity of an existing item, given its SKU or name")]
+    Update {
+        key: String,
+        #[arg(value_parser = parse_quantity)]
+        quantity: u32,
+        #[arg(long, value_parser = price)]
+        price: Option<u64>,
+    },
+    #[command(about = "Add delivered stock to an item, given its SKU or name")]
+    Receive {
+        key: String,
+        #[arg(value_parser = parse_quantity)]
+        quantity: u32,
+        #[arg(long, default_value = "main")]
+        location: String,
+        #[arg(long, help = "What QUANTITY is in, when not the item's own unit")]
+        unit: Option<Unit>,
+    },
+    #[command(about = "Take used or sold stock out of an item, given its SKU or name")]
+    Consume {
+        key: String,
+        #[arg(value_parser = parse_quantity)]
+        quantity: u32,
+        #[arg(long, default_value = "main")]
+        location: String,
+        #[arg(long, help = "What QUANTITY is in, when not the item's own unit")]
+        unit: Option<Unit>,
+        #[arg(long, value_enum, default_value_t = Reason::Sold)]
+        reason: Reason,
+    },
+    #[command(about = "Track an item by serial number, while none is in stock")]
+    TrackSerials { key: String },
+    #[command(about = "Add one unit of an item for each serial number delivered")]
+    ReceiveSerials {
+        key: String,
+        #[arg(required = true)]
+        serials: Vec<String>,
+        #[arg(long, default_value = "main")]
+        location: String,
+    },
+    #[command(about = "Take the unit with a serial number out of stock")]
+    ConsumeSerial {
+        serial: String,
+        #[arg(long, default_value = "main")]
+        location: String,
+        #[arg(long, value_enum, default_value_t = Reason::Sold)]
+        reason: Reason,
+    },
+    #[command(about = "Show which item a serial number belongs to")]
+    FindSerial { serial: String },
+    #[command(about = "Track an item by lot, while none is in stock")]
+    TrackLots { key: String },
+    #[command(about = "Add delivered stock to an item as a new lot")]
+    ReceiveLot {
+        key: String,
+        lot: String,
+        #[arg(value_parser = parse_quantity)]
+        quantity: u32,
+        #[arg(long, value_parser = date, help = "Last day the lot can be used, as YYYY-MM-DD")]
+        expires: Option<NaiveDate>,
+        #[arg(long, default_value = "main")]
+        location: String,
+        #[arg(long, help = "What QUANTITY is in, when not the item's own unit")]
+        unit: Option<Unit>,
+    },
+    #[command(about = "Show the lots of an item, or of every item tracked by lot")]
+    Lots { key: Option<String> },
+    #[command(about = "Make an item a kit of other items, or an ordinary item with none")]
+    Kit {
+        key: String,
+        #[arg(value_parser = component, help = "Each as NAME=QUANTITY, for one kit")]
+        components: Vec<(String, u32)>,
+    },
+    #[command(about = "Make COUNT of a kit from its components")]
+    Assemble {
+        key: String,
+        count: u32,
+        #[arg(long, default_value = "main")]
+        location: String,
+    },
+    #[command(about = "Take COUNT of a kit apart into its components")]
+    Disassemble {
+        key: String,
+        count: u32,
+        #[arg(long, default_value = "main")]
+        location: String,
+    },
+    #[command(about = "Show every kit and what goes into it")]
+    Kits,
+    #[command(about = "Set an attribute of an item, like its color or size")]
+    SetAttribute { key: String, name: String, value: String },
+    #[command(about = "Remove an attribute from an item")]
+    UnsetAttribute { key: String, name: String },
+    #[command(about = "Show every change to an item's quantity and why it happened")]
+    Movements { key: String },
+    #[command(about = "Check that every item's quantity matches its movements")]
+    Reconcile,
+    #[command(about = "Change what an item is counted in, while none is in stock")]
+    SetUnit { key: String, unit: Unit },
+    #[command(about = "Move stock of an item from one location to another")]
+    Transfer {
+        key: String,
+        #[arg(value_parser = parse_quantity)]
+        quantity: u32,
+        from: String,
+        to: String,
+    },
+    #[command(about = "Add a place stock can be kept")]
+    AddLocation { name: String },
+    #[command(about = "Show the stock at each location and in total")]
+    Locations,
+    #[command(about = "Remove an item, given its SKU or name")]
+    Remove { key: String },
+    #[command(about = "Hide an item from everything but the archive, keeping its history")]
+    Archive { key: String },
+    // Not `restore`, which restores a backup.
+    #[command(about = "Bring an archived item back, given its SKU or name")]
+    Unarchive { key: String },
+    #[command(about = "Remove an archived item for good")]
+    Purge { key: String },
+    #[command(about = "Show the archived items")]
+    Archived,
+    #[command(about = "Find items that look like duplicates and merge them")]
+    Dedupe {
+        #[arg(long, help = "Merge every group into its oldest item without asking")]
+        auto: bool,
+    },
+    #[command(about = "Show every item as a table")]
+    List {
+        #[arg(long, value_enum, help = "Defaults to the sort in inventory.toml, or name")]
+        sort: Option<SortOrder>,
+        #[arg(
+            long,
+            value_parser = attribute_filter,
+            help = "Only items with this attribute, as NAME or NAME=VALUE"
+        )]
+        attribute: Option<(String, Option<String>)>,
+        #[arg(
+            long = "where",
+            value_parser = filter,
+            help = "Only items matching a filter, like 'quantity < 5 and category = produce'"
+        )]
+        filter: Option<Filter>,
+        #[arg(
+            long,
+            value_parser = RangedU64ValueParser::<usize>::new().range(1..),
+            help = "Show only this page of the items, counting from 1"
+        )]
+        page: Option<usize>,
+        #[arg(
+            long,
+            value_parser = RangedU64ValueParser::<usize>::new().range(1..),
+            help = "Items to a page; defaults to page_size in inventory.toml when --page is given"
+        )]
+        limit: Option<usize>,
+    },
+    #[command(alias = "find", about = "Show items whose name contains the query")]
+    Search {
+        query: String,
+        #[arg(long, help = "Match names within a few typos instead, closest first")]
+        fuzzy: bool,
+    },
+    #[command(about = "Show items not updated in the last DAYS days, oldest first")]
+    Stale { days: u32 },
+    #[command(about = "Set the expiry date of an item as YYYY-MM-DD, or none to clear it")]
+    Expire { key: String, date: String },
+    #[command(about = "Show items that expire in the next DAYS days, soonest first")]
+    Expiring { days: u32 },
+    #[command(about = "Set how low an item's stock may get before reordering, or none to clear it")]
+    SetReorderLevel { key: String, level: String },
+    #[command(about = "Suggest what to order from whom, from the use over the last DAYS days")]
+    ReorderReport {
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+    },
+    #[command(about = "Show the total value of the stock")]
+    Value,
+    #[command(about = "Count items, their quantity and their value by category or another field")]
+    Summary {
+        #[arg(
+            long,
+            default_value = "category",
+            help = "category, unit or the name of an attribute"
+        )]
+        by: String,
+    },
+    #[command(about = "Show quantities as bars, as wide as the terminal")]
+    Chart {
+        #[arg(long, help = "Only items in this category")]
+        category: Option<String>,
+        #[arg(long, help = "Only the N items with the most in stock, most first")]
+        top: Option<usize>,
+    },
+    #[command(about = "Copy the storage file into the backups folder")]
+    Backup {
+        #[arg(long, help = "List the backups instead, newest first")]
+        list: bool,
+    },
+    #[command(about = "Replace the storage file with a backup")]
+    Restore {
+        #[arg(help = "The backup's number in `backup --list`; asked for when left out")]
+        number: Option<usize>,
+    },
+    #[command(about = "Write the inventory by category, with totals and low stock, to a file")]
+    Report {
+        #[arg(long, value_enum, default_value_t = ReportFormat::Md)]
+        format: ReportFormat,
+        #[arg(long, help = "Defaults to report.md or report.html")]
+        output: Option<String>,
+    },
+    #[cfg(feature = "daemon")]
+    #[command(about = "Keep the items loaded and run subcommands sent with --connect")]
+    Daemon,
+    #[cfg(feature = "tcp")]
+    #[command(about = "Run subcommands sent with `inventory remote` over TCP")]
+    ServeTcp {
+        #[arg(help = "Where to listen, like 127.0.0.1:7070; anyone who can reach it can \
+                      change the items")]
+        address: String,
+    },
+    #[cfg(feature = "tcp")]
+    #[command(about = "Run a subcommand on the items of an `inventory serve-tcp` server")]
+    Remote {
+        #[arg(help = "The server, like 192.168.1.5:7070")]
+        address: String,
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            help = "The subcommand and its arguments, like list --sort quantity"
+        )]
+        args: Vec<String>,
+    },
+    #[cfg(feature = "watch")]
+    #[command(about = "Show the items as a table, redrawn whenever the storage file changes")]
+    Watch {
+        #[arg(
+            long,
+            default_value_t = 1,
+            help = "Seconds to wait after a change before redrawing, so a burst of saves \
+                    redraws once"
+        )]
+        interval: u64,
+    },
+    #[cfg(feature = "server")]
+    #[command(about = "Serve the items as a JSON API over HTTP")]
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        #[cfg(feature = "graphql")]
+        #[arg(long, help = "Serve a GraphQL API at /graphql instead of the JSON one")]
+        graphql: bool,
+
+        #[cfg(feature = "grpc")]
+        #[arg(long, help = "Serve the gRPC API in proto/inventory.proto instead of the JSON one")]
+        #[cfg_attr(feature = "graphql", arg(conflicts_with = "graphql"))]
+        grpc: bool,
+    },
+}
+
+impl CliCommand {
+    // Whether the command finishes without asking anything, so it can be run
+    // from a script or sent to the daemon or a server.
+    pub fn runs_unattended(&self) -> bool {
+        match self {
+            CliCommand::Dedupe { auto: false } | CliCommand::Restore { number: None } => false,
+            #[cfg(feature = "daemon")]
+            CliCommand::Daemon => false,
+            #[cfg(feature = "tcp")]
+            CliCommand::ServeTcp { .. } | CliCommand::Remote { .. } => false,
+            #[cfg(feature = "watch")]
+            CliCommand::Watch { .. } => false,
+            #[cfg(feature = "server")]
+            CliCommand::Serve { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+fn date(input: &str) -> Result<NaiveDate, String> {
+    parse_date(input).ok_or_else(|| format!("{:?} is not a date like 2024-03-31", input))
+}
+
+fn component(input: &str) -> Result<(String, u32), String> {
+    input
+        .rsplit_once('=')
+        .and_then(|(name, quantity)| Some((name.trim().to_string(), quantity.parse().ok()?)))
+        .ok_or_else(|| format!("{:?} is not a component like bolt=4", input))
+}
+
+fn attribute_filter(input: &str) -> Result<(String, Option<String>), String> {
+    let (name, value) = match input.split_once('=') {
+        Some((name, value)) => (name, Some(value.trim().to_string())),
+        None => (input, None),
+    };
+    match name.trim() {
+        "" => Err(format!("{:?} is not an attribute like color or color=red", input)),
+        name => Ok((name.to_string(), value)),
+    }
+}
+
+fn filter(input: &str) -> Result<Filter, String> {
+    Filter::parse(input).map_err(|e| e.to_string())
+}
+
+fn price(input: &str) -> Result<u64, String> {
+    parse_price(input).ok_or_else(|| format!("{:?} is not an amount like 12 or 12.50", input))
+}
+
+// Numbers the backups from 1, as restore takes them.
+fn print_backups(backups: &[PathBuf], out: &mut dyn Write) -> io::Result<()> {
+    if backups.is_empty() {
+        writeln!(out, "No backups yet")?;
+    }
+    for (i, backup) in backups.iter().enumerate() {
+        writeln!(out, "{}. {}", i + 1, backup.display())?;
+    }
+    Ok(())
+}
+
+// What the reorder report suggests, one group per supplier. How fast items
+// are used comes from what the ledger has as sold or damaged.
+pub fn print_reorder_report(
+    collection: &Collection,
+    days: u32,
+    default_level: u32,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let used = collection.consumption(days);
+    let suggestions = collection.reorder_report(&used, days, default_level);
+    if suggestions.is_empty() {
+        writeln!(out, "Nothing needs ordering")?;
+    }
+    let mut group = None;
+    for suggestion in suggestions {
+        let supplier = suggestion.supplier.map(|supplier| supplier.id);
+        if group != Some(supplier) {
+            match suggestion.supplier {
+                Some(supplier) => writeln!(
+                    out,
+                    "{} ({} days lead time):",
+                    supplier.name, supplier.lead_time_days
+                )?,
+                None => writeln!(out, "No supplier yet:")?,
+            }
+            group = Some(supplier);
+        }
+        let item = suggestion.item;
+        writeln!(
+            out,
+            "  {} ({}): order {} {}, {} expected, reorder level {}, using {:.1} a day",
+            item.name,
+            item.sku,
+            suggestion.quantity,
+            item.unit,
+            suggestion.expected,
+            suggestion.reorder_level,
+            suggestion.daily_use
+        )?;
+    }
+    Ok(())
+}
+
+// An item's ledger, oldest first, with the running total.
+pub fn print_movements(
+    collection: &Collection,
+    key: &str,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut total = 0;
+    for movement in collection.movements(key)? {
+        total += movement.delta;
+        let reference =
+            movement.reference.as_ref().map(|reference| format!(" ({})", reference));
+        let location = movement.location.as_ref().map(|location| format!(" at {}", location));
+        writeln!(
+            out,
+            "{} {:+} {}{}{}, {} after",
+            collection.format_time(movement.timestamp),
+            movement.delta,
+            movement.reason,
+            location.unwrap_or_default(),
+            reference.unwrap_or_default(),
+            total
+        )?;
+    }
+    Ok(())
+}
+
+// The lots of the item with SKU or name `key`, or of every item tracked by
+// lot when there is none, each item's in the order they will be used.
+pub fn print_lots(
+    collection: &Collection,
+    key: Option<&str>,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let items: Vec<&Item> = match key {
+        Some(key) => {
+            collection.lots(key)?;
+            vec![collection.lookup(key)?]
+        }
+        None => collection
+            .sorted(SortOrder::Name)
+            .into_iter()
+            .filter(|item| item.is_lot_tracked())
+            .collect(),
+    };
+    if items.is_empty() {
+        writeln!(out, "No items are tracked by lot")?;
+    }
+    for item in items {
+        writeln!(out, "{} ({}): {} {}", item.name, item.sku, item.quantity, item.unit)?;
+        for lot in item.lots.iter().flatten() {
+            let expires = match lot.expires_on {
+                Some(day) => format!("expires on {}", day),
+                None => String::from("doesn't expire"),
+            };
+            writeln!(
+                out,
+                "  lot {}: {}, received on {}, {}",
+                lot.id, lot.quantity, lot.received_on, expires
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn print_kits(collection: &Collection, out: &mut dyn Write) -> io::Result<()> {
+    let kits = collection.kits();
+    if kits.is_empty() {
+        writeln!(out, "No kits")?;
+    }
+    for kit in kits {
+        writeln!(out, "{} ({}): {} in stock", kit.name, kit.sku, kit.quantity)?;
+        for (item, quantity) in collection.kit_components(kit) {
+            writeln!(out, "  {} {} of {} ({})", quantity, item.unit, item.name, item.sku)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn print_archived(collection: &Collection, out: &mut dyn Write) -> io::Result<()> {
+    let items = collection.archived();
+    if items.is_empty() {
+        writeln!(out, "No archived items")?;
+    }
+    for item in items {
+        writeln!(out, "{} ({}): {} in {}", item.name, item.sku, item.quantity, item.category)?;
+    }
+    Ok(())
+}
+
+// Shows each group of likely duplicates side by side and merges it into its
+// oldest item, asking first unless `auto`. Returns whether anything merged.
+pub fn dedupe(
+    collection: &mut Collection,
+    auto: bool,
+    out: &mut dyn Write,
+) -> Result<bool, Box<dyn Error>> {
+    let groups: Vec<Vec<Item>> = collection
+        .likely_duplicates()
+        .into_iter()
+        .map(|group| group.into_iter().cloned().collect())
+        .collect();
+    if groups.is_empty() {
+        writeln!(out, "No likely duplicates")?;
+    }
+    let mut merged = false;
+    for group in groups {
+        print_side_by_side(collection, &group, out)?;
+        let (keep, others) = group.split_first().expect("groups have two items or more");
+        if !auto {
+            let question = format!("Merge them into {} ({})? [y/N] ", keep.sku, keep.name);
+            let answer = prompt::read_line(&question)?.unwrap_or_default();
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                writeln!(out)?;
+                continue;
+            }
+        }
+        // One that can't be merged, say for its unit, is left as it is.
+        for other in others {
+            match collection.merge_items(&keep.sku, &other.sku) {
+                Ok(()) => {
+                    writeln!(out, "Merged {} ({}) into {}", other.name, other.sku, keep.sku)?;
+                    merged = true;
+                }
+                Err(e) => writeln!(out, "Not merging {} ({}): {}", other.name, other.sku, e)?,
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(merged)
+}
+
+// A column for each item, a row for each thing worth comparing.
+fn print_side_by_side(
+    collection: &Collection,
+    items: &[Item],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let rows: Vec<(&str, Vec<String>)> = vec![
+        ("SKU", items.iter().map(|item| item.sku.to_string()).collect()),
+        ("Name", items.iter().map(|item| item.name.to_string()).collect()),
+        ("Quantity", items.iter().map(|item| format!("{} {}", item.quantity, item.unit)).collect()),
+        ("Category", items.iter().map(|item| item.category.to_string()).collect()),
+        ("Price", items.iter().map(|item| format_price(item.unit_price)).collect()),
+        (
+            "Added",
+            items.iter().map(|item| collection.format_time(item.created_at)).collect(),
+        ),
+    ];
+    let widths: Vec<usize> = (0..items.len())
+        .map(|i| rows.iter().map(|(_, cells)| cells[i].chars().count()).max().unwrap_or(0))
+        .collect();
+    for (label, cells) in rows {
+        let mut line = format!("{:<10}", label);
+        for (cell, width) in cells.iter().zip(&widths) {
+            line.push_str(&format!("{:<width$}  ", cell, width = width));
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+// The chart of the items in `category`, or of all of them, by name; or of
+// the `top` of them with the most in stock, most first.
+pub fn chart(collection: &Collection, category: Option<&str>, top: Option<usize>) -> String {
+    let mut items = match category {
+        Some(category) => collection.list_by_category(category),
+        None => collection.sorted(SortOrder::Name),
+    };
+    if let Some(top) = top {
+        items.sort_by_key(|item| Reverse(item.quantity));
+        items.truncate(top);
+    }
+    collection.chart_of(&items, terminal_width())
+}
+
+// One row per group, then the grand totals.
+pub fn print_summary(summary: &Summary, by: &GroupBy, out: &mut dyn Write) -> io::Result<()> {
+    let label = |group: &Group| match (&group.key, by) {
+        (Some(key), _) => key.to_string(),
+        (None, GroupBy::Attribute(name)) => format!("(no {})", name),
+        (None, _) => String::new(),
+    };
+    let header = match by {
+        GroupBy::Category => "Category",
+        GroupBy::Unit => "Unit",
+        GroupBy::Attribute(name) => name,
+    };
+    let mut rows: Vec<(String, &Group)> =
+        summary.groups.iter().map(|group| (label(group), group)).collect();
+    rows.push((String::from("Total"), &summary.total));
+
+    let width = rows.iter().map(|(label, _)| label.chars().count()).chain([header.chars().count()]);
+    let width = width.max().unwrap_or(0);
+    writeln!(out, "{:<width$}  {:>6}  {:>10}  {:>12}", header, "Items", "Quantity", "Value")?;
+    for (i, (label, group)) in rows.iter().enumerate() {
+        if i + 1 == rows.len() {
+            writeln!(out, "{}", "-".repeat(width + 34))?;
+        }
+        writeln!(
+            out,
+            "{:<width$}  {:>6}  {:>10}  {:>12}",
+            label,
+            group.items,
+            group.quantity,
+            format_price(group.value)
+        )?;
+    }
+    Ok(())
+}
+
+pub fn print_reconciliation(collection: &Collection, out: &mut dyn Write) -> io::Result<()> {
+    let discrepancies = collection.reconcile();
+    if discrepancies.is_empty() {
+        writeln!(out, "Every quantity matches its movements")?;
+    }
+    for discrepancy in discrepancies {
+        writeln!(
+            out,
+            "{}: {} in stock but the movements add up to {}",
+            discrepancy.sku, discrepancy.quantity, discrepancy.ledger
+        )?;
+    }
+    Ok(())
+}
+
+// An event log keeps every change already, and isn't backed up.
+fn check_backups(storage: &Storage) -> Result<(), String> {
+    match storage.format.is_log() {
+        true => Err(format!("{} keeps its own history and has no backups", storage.path())),
+        false => Ok(()),
+    }
+}
+
+// Runs one subcommand against the loaded collection, saving it afterwards
+// when the command changed anything. What it prints goes to `out`, except
+// from the subcommands that keep running.
+pub fn run_command(
+    command: CliCommand,
+    collection: &mut Collection,
+    storage: Option<&Storage>,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let changed = match command {
+        CliCommand::Add { name, quantity, unit, category, price, expires } => {
+            let sku =
+                collection.add_item(name.to_string(), quantity, unit, category.to_string(), price)?;
+            collection.set_expiry(&sku, expires)?;
+            // The name as stored, which may be spelled like an item that has it.
+            writeln!(
+                out,
+                "added an item {} as {} and quantity {} {} in {} at {}",
+                collection.lookup(&sku)?.name,
+                sku,
+                quantity,
+                unit,
+                category,
+                format_price(price)
+            )?;
+            true
+        }
+        CliCommand::Update { key, quantity, price } => {
+            collection.update_item(key.to_string(), quantity, price)?;
+            writeln!(out, "Updated item: {} and quantity {}", key, quantity)?;
+            true
+        }
+        CliCommand::Receive { key, quantity, location, unit } => {
+            collection.receive(&key, &LocationId(location.to_string()), quantity, unit)?;
+            let unit = unit.map(|unit| format!(" {}", unit)).unwrap_or_default();
+            writeln!(out, "Received {}{} of {} at {}", quantity, unit, key, location)?;
+            true
+        }
+        CliCommand::Consume { key, quantity, location, unit, reason } => {
+            collection.consume(&key, &LocationId(location.to_string()), quantity, unit, reason)?;
+            let unit = unit.map(|unit| format!(" {}", unit)).unwrap_or_default();
+            writeln!(
+                out,
+                "Consumed {}{} of {} at {} as {}",
+                quantity, unit, key, location, reason
+            )?;
+            true
+        }
+        CliCommand::TrackSerials { key } => {
+            collection.track_serials(&key)?;
+            writeln!(out, "{} is now tracked by serial number", key)?;
+            true
+        }
+        CliCommand::ReceiveSerials { key, serials, location } => {
+            collection.receive_serials(&key, &LocationId(location.to_string()), &serials)?;
+            writeln!(out, "Received {} of {} at {}", serials.join(", "), key, location)?;
+            true
+        }
+        CliCommand::ConsumeSerial { serial, location, reason } => {
+            collection.consume_serial(&serial, &LocationId(location.to_string()), reason)?;
+            writeln!(out, "Consumed {} at {} as {}", serial, location, reason)?;
+            true
+        }
+        CliCommand::FindSerial { serial } => {
+            let item = collection.find_serial(&serial)?;
+            writeln!(out, "{} belongs to {} ({})", serial, item.name, item.sku)?;
+            false
+        }
+        CliCommand::TrackLots { key } => {
+            collection.track_lots(&key)?;
+            writeln!(out, "{} is now tracked by lot", key)?;
+            true
+        }
+        CliCommand::ReceiveLot { key, lot, quantity, expires, location, unit } => {
+            let at = LocationId(location.to_string());
+            collection.receive_lot(&key, &at, quantity, unit, lot.to_string(), expires)?;
+            let unit = unit.map(|unit| format!(" {}", unit)).unwrap_or_default();
+            writeln!(
+                out,
+                "Received {}{} of {} as lot {} at {}",
+                quantity, unit, key, lot, location
+            )?;
+            true
+        }
+        CliCommand::Lots { key } => {
+            print_lots(collection, key.as_deref(), out)?;
+            false
+        }
+        CliCommand::Kit { key, components } => {
+            collection.set_components(&key, &components)?;
+            match components.is_empty() {
+                true => writeln!(out, "{} is no longer a kit", key)?,
+                false => writeln!(out, "{} is now a kit", key)?,
+            }
+            true
+        }
+        CliCommand::Assemble { key, count, location } => {
+            collection.assemble(&key, &LocationId(location.to_string()), count)?;
+            writeln!(out, "Assembled {} of {} at {}", count, key, location)?;
+            true
+        }
+        CliCommand::Disassemble { key, count, location } => {
+            collection.disassemble(&key, &LocationId(location.to_string()), count)?;
+            writeln!(out, "Disassembled {} of {} at {}", count, key, location)?;
+            true
+        }
+        CliCommand::Kits => {
+            print_kits(collection, out)?;
+            false
+        }
+        CliCommand::SetAttribute { key, name, value } => {
+            collection.set_attribute(&key, &name, &value)?;
+            writeln!(out, "Set {} of {} to {}", name, key, value.trim())?;
+            true
+        }
+        CliCommand::UnsetAttribute { key, name } => {
+            let value = collection.unset_attribute(&key, &name)?;
+            writeln!(out, "Removed {} ({}) from {}", name, value, key)?;
+            true
+        }
+        CliCommand::Movements { key } => {
+            print_movements(collection, &key, out)?;
+            false
+        }
+        CliCommand::Reconcile => {
+            print_reconciliation(collection, out)?;
+            false
+        }
+        CliCommand::SetUnit { key, unit } => {
+            collection.set_unit(&key, unit)?;
+            writeln!(out, "{} is now counted in {}", key, unit)?;
+            true
+        }
+        CliCommand::Transfer { key, quantity, from, to } => {
+            let (from, to) = (LocationId(from), LocationId(to));
+            collection.transfer(&key, quantity, &from, &to)?;
+            writeln!(out, "Transferred {} of {} from {} to {}", quantity, key, from, to)?;
+            true
+        }
+        CliCommand::AddLocation { name } => {
+            collection.add_location(LocationId(name.to_string()))?;
+            writeln!(out, "Added location {}", name)?;
+            true
+        }
+        CliCommand::Locations => {
+            write!(out, "{}", collection.location_report())?;
+            false
+        }
+        CliCommand::Remove { key } => {
+            let item = collection.remove_item(&key)?;
+            writeln!(out, "Removed {} ({})", item.name, item.sku)?;
+            true
+        }
+        CliCommand::Archive { key } => {
+            collection.archive(&key)?;
+            writeln!(out, "Archived {}", key)?;
+            true
+        }
+        CliCommand::Unarchive { key } => {
+            collection.restore(&key)?;
+            writeln!(out, "Restored {}", key)?;
+            true
+        }
+        CliCommand::Purge { key } => {
+            let item = collection.purge(&key)?;
+            writeln!(out, "Purged {} ({})", item.name, item.sku)?;
+            true
+        }
+        CliCommand::Archived => {
+            print_archived(collection, out)?;
+            false
+        }
+        CliCommand::Dedupe { auto } => dedupe(collection, auto, out)?,
+        CliCommand::List { sort, attribute, filter, page, limit } => {
+            let items: Vec<&Item> = collection
+                .sorted(sort.unwrap_or(config.sort))
+                .into_iter()
+                .filter(|item| {
+                    let attribute = attribute.as_ref();
+                    attribute.is_none_or(|(name, value)| item.has_attribute(name, value.as_deref()))
+                })
+                .filter(|item| filter.as_ref().is_none_or(|filter| filter.matches(item)))
+                .collect();
+            if page.is_none() && limit.is_none() {
+                write!(out, "{}", collection.table_of(&items, config.highlight()))?;
+            } else {
+                let (page, limit) = (page.unwrap_or(1), limit.unwrap_or(config.page_size));
+                let (shown, total_pages) = page_of(&items, page, limit);
+                // No items still makes one, empty, page.
+                let total_pages = total_pages.max(1);
+                if page > total_pages {
+                    return Err(format!("there is no page {}, the last is {}", page, total_pages)
+                        .into());
+                }
+                write!(out, "{}", collection.table_of(shown, config.highlight()))?;
+                writeln!(out, "Page {} of {}, {} items in all", page, total_pages, items.len())?;
+            }
+            false
+        }
+        CliCommand::Search { query, fuzzy: false } => {
+            let items = collection.search(&query);
+            if items.is_empty() {
+                let suggestions = did_you_mean(&collection.closest_names(&query));
+                writeln!(out, "No items match {}{}", query, suggestions)?;
+            }
+            for item in items {
+                writeln!(out, "{} ({}): {}", item.name, item.sku, item.quantity)?;
+            }
+            false
+        }
+        CliCommand::Search { query, fuzzy: true } => {
+            for (distance, item) in collection.fuzzy_search(&query) {
+                let (name, sku) = (&item.name, &item.sku);
+                writeln!(out, "{} ({}): {}, edit distance {}", name, sku, item.quantity, distance)?;
+            }
+            false
+        }
+        CliCommand::Stale { days } => {
+            for item in collection.stale(days) {
+                writeln!(
+                    out,
+                    "{} ({}): {}, last updated {}",
+                    item.name,
+                    item.sku,
+                    item.quantity,
+                    collection.format_time(item.updated_at)
+                )?;
+            }
+            false
+        }
+        CliCommand::Expire { key, date: input } => {
+            let expires_on = match input.as_str() {
+                "none" => None,
+                input => Some(date(input)?),
+            };
+            collection.set_expiry(&key, expires_on)?;
+            writeln!(out, "Set the expiry date of {} to {}", key, input)?;
+            true
+        }
+        CliCommand::Expiring { days } => {
+            for item in collection.expiring_within(days) {
+                if let Some(expires_on) = item.expires_on {
+                    let (name, sku) = (&item.name, &item.sku);
+                    writeln!(
+                        out,
+                        "{} ({}): {}, expires on {}",
+                        name, sku, item.quantity, expires_on
+                    )?;
+                }
+            }
+            false
+        }
+        CliCommand::SetReorderLevel { key, level: input } => {
+            let level = match input.as_str() {
+                "none" => None,
+                input => {
+                    Some(input.parse().map_err(|_| format!("{:?} is not a whole number", input))?)
+                }
+            };
+            collection.set_reorder_level(&key, level)?;
+            writeln!(out, "Set the reorder level of {} to {}", key, input)?;
+            true
+        }
+        CliCommand::ReorderReport { days } => {
+            print_reorder_report(collection, days, config.low_stock_threshold, out)?;
+            false
+        }
+        CliCommand::Value => {
+            writeln!(out, "Total value: {}", format_price(collection.total_value()))?;
+            false
+        }
+        CliCommand::Summary { by } => {
+            let by = GroupBy::named(&by);
+            print_summary(&collection.summarize(&by), &by, out)?;
+            false
+        }
+        CliCommand::Chart { category, top } => {
+            write!(out, "{}", chart(collection, category.as_deref(), top))?;
+            false
+        }
+        CliCommand::Backup { list } => {
+            let storage = storage.ok_or("there is no storage file in this build")?;
+            check_backups(storage)?;
+            let path = Path::new(storage.path());
+            if list {
+                print_backups(&backup::list(path)?, out)?;
+            } else {
+                writeln!(
+                    out,
+                    "Backed up {} to {}",
+                    storage.path(), backup::create(path)?.display()
+                )?;
+                if storage.backups > 0 {
+                    backup::prune(path, storage.backups)?;
+                }
+            }
+            false
+        }
+        CliCommand::Restore { number } => {
+            let storage = storage.ok_or("there is no storage file in this build")?;
+            check_backups(storage)?;
+            let path = Path::new(storage.path());
+            let backups = backup::list(path)?;
+            if backups.is_empty() {
+                return Err(format!("there are no backups of {}", storage.path()).into());
+            }
+            let number = match number {
+                Some(number) => number,
+                None => {
+                    print_backups(&backups, out)?;
+                    let input = prompt::read_line("Restore which backup? ")?.unwrap_or_default();
+                    input.trim().parse().map_err(|_| format!("{:?} is not a number", input))?
+                }
+            };
+            let chosen = number
+                .checked_sub(1)
+                .and_then(|i| backups.get(i))
+                .ok_or_else(|| format!("there is no backup {}", number))?;
+            backup::restore(path, chosen, storage.backups)?;
+            collection.replace(storage.load()?.unwrap_or_default());
+            writeln!(out, "Restored {} from {}", storage.path(), chosen.display())?;
+            false
+        }
+        CliCommand::Report { format, output } => {
+            let path = output.unwrap_or_else(|| format!("report.{}", format.extension()));
+            fs::write(&path, report::render(collection, format, config.low_stock_threshold))?;
+            writeln!(out, "Wrote the report to {}", path)?;
+            false
+        }
+        #[cfg(feature = "daemon")]
+        CliCommand::Daemon => {
+            // The daemon saves after every change itself.
+            crate::daemon::run(std::mem::take(collection), storage, config)?;
+            false
+        }
+        #[cfg(feature = "tcp")]
+        CliCommand::ServeTcp { address } => {
+            let shared = crate::remote::Shared::new(std::mem::take(collection), storage, config);
+            // The server saves after every change itself.
+            crate::remote::serve_tcp(shared, &address)?;
+            false
+        }
+        #[cfg(feature = "tcp")]
+        CliCommand::Remote { address, args } => {
+            crate::remote::call_tcp(&address, &args, config)?;
+            false
+        }
+        #[cfg(feature = "watch")]
+        CliCommand::Watch { interval } => {
+            let storage = storage.ok_or("there is no storage file to watch in this build")?;
+            crate::watch::run(storage, config, std::time::Duration::from_secs(interval))?;
+            false
+        }
+        #[cfg(feature = "server")]
+        CliCommand::Serve {
+            port,
+            #[cfg(feature = "graphql")]
+            graphql,
+            #[cfg(feature = "grpc")]
+            grpc,
+        } => {
+            use crate::server::{self, Api};
+            #[cfg_attr(not(any(feature = "graphql", feature = "grpc")), allow(unused_mut))]
+            let mut api = Api::Rest;
+            #[cfg(feature = "graphql")]
+            if graphql {
+                api = Api::Graphql;
+            }
+            #[cfg(feature = "grpc")]
+            if grpc {
+                api = Api::Grpc;
+            }
+            // The server saves after every change itself.
+            server::serve(std::mem::take(collection), storage.cloned(), port, api, config)?;
+            false
+        }
+    };
+
+    if changed {
+        if let Some(storage) = storage {
+            storage.save(collection)?;
+        }
+    }
+    Ok(())
+}
+
+// Runs a script of subcommands, one per line as they would be typed after
+// `inventory`. Empty lines and lines starting with # are skipped. A line that
+// fails is reported with its number and the rest still run. Each line saves
+// what it changes, as it would typed on its own, so backup and restore see
+// the lines before them. Subcommands that keep running or ask for input
+// can't be in a script.
+pub fn run_script(
+    path: &str,
+    collection: &mut Collection,
+    storage: Option<&Storage>,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let reader: Box<dyn BufRead> = match path {
+        "-" => Box::new(io::stdin().lock()),
+        path => Box::new(BufReader::new(File::open(path)?)),
+    };
+
+    let mut failed = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let _line = tracing::info_span!("script", line = i + 1).entered();
+        let result = split_words(line).and_then(|words| {
+            let parsed = ScriptLine::try_parse_from(words).map_err(|e| clap_message(&e))?;
+            if !parsed.command.runs_unattended() {
+                return Err(String::from("that subcommand keeps running or asks for input"));
+            }
+            run_command(parsed.command, collection, storage, config, out).map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            eprintln!("line {}: {}", i + 1, e);
+            failed += 1;
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{} lines of {} failed", failed, path).into()),
+    }
+}
+
+// The first line of a parse error; clap's message goes on to explain usage
+// over several lines.
+pub fn clap_message(e: &clap::Error) -> String {
+    let message = e.to_string();
+    let first = message.lines().next().unwrap_or_default();
+    first.trim_start_matches("error: ").to_string()
+}
+
+// Splits a line into words at whitespace. Double quotes keep a word with
+// spaces in it together.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quoted {
+        return Err(String::from("a quote is never closed"));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unattended(line: &str) -> bool {
+        let words = split_words(line).unwrap();
+        ScriptLine::try_parse_from(words).unwrap().command.runs_unattended()
+    }
+
+    #[test]
+    fn script_lines_keep_quoted_semicolons_and_empty_words() {
+        let words = split_words(r#"add "salt; fine"  3;4 --category """#).unwrap();
+        assert_eq!(words, ["add", "salt; fine", "3;4", "--category", ""]);
+        assert_eq!(split_words("  ").unwrap(), Vec::<String>::new());
+        assert!(split_words(r#"add "salt"#).is_err());
+
+        let words = split_words(r#"add "salt; fine" 3 --category """#).unwrap();
+        let parsed = ScriptLine::try_parse_from(words).unwrap();
+        assert!(matches!(parsed.command, CliCommand::Add { name, .. } if name == "salt; fine"));
+    }
+
+    #[test]
+    fn subcommands_that_ask_or_keep_running_are_not_unattended() {
+        assert!(unattended("list"));
+        assert!(unattended("dedupe --auto"));
+        assert!(!unattended("dedupe"));
+        assert!(!unattended("restore"));
+        #[cfg(feature = "daemon")]
+        assert!(!unattended("daemon"));
+    }
+}
@@ -0,0 +1,260 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use chrono::{NaiveTime, Utc};
+use clap::ValueEnum;
+use inventory_core::{SortOrder, DEFAULT_DATE_FORMAT};
+use serde::Deserialize;
+
+// Read from the working directory, like the collection itself.
+pub const CONFIG_FILE: &str = "inventory.toml";
+
+// Defaults for a run. Each setting comes from inventory.toml, then from an
+// INVENTORY_* environment variable of the same name in capitals when one is
+// set; command line flags override both.
+#[derive(Clone)]
+pub struct Config {
+    // json, events or sqlite.
+    pub storage: Option<String>,
+    pub storage_path: Option<String>,
+    // How many backups of the storage file to keep; one is made before every
+    // save. 0 turns that off.
+    pub backups: usize,
+    // Whether to encrypt the storage file with a passphrase asked for on
+    // startup. A file that is already encrypted stays so either way.
+    pub encrypt: bool,
+    // How often the menu and the server save changes in the background, in
+    // seconds, and how many changes make them save sooner. 0 seconds turns
+    // autosave off.
+    pub autosave_seconds: u64,
+    pub autosave_changes: usize,
+    // How many events the events storage's log may hold before it is replaced
+    // by a snapshot of the collection.
+    pub compact_after: usize,
+    // Items with this many or fewer count as low on stock.
+    pub low_stock_threshold: u32,
+    pub sort: SortOrder,
+    // How many items the menu's list shows at a time, and list --page takes
+    // as a page when no --limit is given.
+    pub page_size: usize,
+    // A chrono format string, like the default %Y-%m-%d %H:%M.
+    pub date_format: String,
+    // Whether tables color items that are out of or low on stock. Turned off
+    // by NO_COLOR, and by --no-color or output that isn't a terminal.
+    pub color: bool,
+    // The Unix socket `inventory daemon` listens on and --connect sends to.
+    #[cfg_attr(not(feature = "daemon"), allow(dead_code))]
+    pub socket: String,
+    // URLs each stock event is posted to as JSON: an item created, low on
+    // stock, or out of it.
+    #[cfg_attr(not(feature = "webhooks"), allow(dead_code))]
+    pub webhooks: Vec<String>,
+    // Whether stock alerts pop up as desktop notifications, and when of the
+    // day they don't.
+    #[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+    pub notifications: bool,
+    #[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+    pub quiet_hours: Option<QuietHours>,
+    // What gets logged to stderr, as a level like warn or a filter like
+    // inventory_core=debug,info, and whether as text or as JSON lines, e.g.
+    // for collecting a server's logs.
+    pub log_level: String,
+    pub log_format: LogFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+// A span of the day, like 22:00-07:00, which may run past midnight.
+#[derive(Clone, Copy)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    fn parse(text: &str) -> Option<QuietHours> {
+        let (start, end) = text.split_once('-')?;
+        let time = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+        Some(QuietHours { start: time(start)?, end: time(end)? })
+    }
+
+    #[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        match self.start <= self.end {
+            true => self.start <= time && time < self.end,
+            false => time >= self.start || time < self.end,
+        }
+    }
+}
+
+// The file as written; everything is optional.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Settings {
+    storage: Option<String>,
+    storage_path: Option<String>,
+    backups: Option<usize>,
+    encrypt: Option<bool>,
+    autosave_seconds: Option<u64>,
+    autosave_changes: Option<usize>,
+    compact_after: Option<usize>,
+    low_stock_threshold: Option<u32>,
+    sort: Option<String>,
+    page_size: Option<usize>,
+    date_format: Option<String>,
+    color: Option<bool>,
+    socket: Option<String>,
+    webhooks: Option<Vec<String>>,
+    notifications: Option<bool>,
+    quiet_hours: Option<String>,
+    log_level: Option<String>,
+    log_format: Option<String>,
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let mut settings = match fs::read_to_string(CONFIG_FILE) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", CONFIG_FILE, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Settings::default(),
+            Err(e) => return Err(format!("could not read {}: {}", CONFIG_FILE, e)),
+        };
+
+        override_with("INVENTORY_STORAGE", &mut settings.storage);
+        override_with("INVENTORY_STORAGE_PATH", &mut settings.storage_path);
+        override_with("INVENTORY_SORT", &mut settings.sort);
+        override_with("INVENTORY_DATE_FORMAT", &mut settings.date_format);
+        override_with("INVENTORY_SOCKET", &mut settings.socket);
+        override_with("INVENTORY_QUIET_HOURS", &mut settings.quiet_hours);
+        override_with("INVENTORY_LOG_LEVEL", &mut settings.log_level);
+        override_with("INVENTORY_LOG_FORMAT", &mut settings.log_format);
+        list_with("INVENTORY_WEBHOOKS", &mut settings.webhooks);
+        parse_with("INVENTORY_BACKUPS", &mut settings.backups, "a whole number")?;
+        parse_with("INVENTORY_ENCRYPT", &mut settings.encrypt, "true or false")?;
+        parse_with("INVENTORY_AUTOSAVE_SECONDS", &mut settings.autosave_seconds, "a whole number")?;
+        parse_with("INVENTORY_AUTOSAVE_CHANGES", &mut settings.autosave_changes, "a whole number")?;
+        parse_with("INVENTORY_COMPACT_AFTER", &mut settings.compact_after, "a whole number")?;
+        parse_with(
+            "INVENTORY_LOW_STOCK_THRESHOLD",
+            &mut settings.low_stock_threshold,
+            "a whole number",
+        )?;
+        parse_with("INVENTORY_PAGE_SIZE", &mut settings.page_size, "a whole number")?;
+        parse_with("INVENTORY_NOTIFICATIONS", &mut settings.notifications, "true or false")?;
+        if env_var("NO_COLOR").is_some() {
+            settings.color = Some(false);
+        }
+        Config::from_settings(settings)
+    }
+
+    // Fills in what `settings` leaves out, and checks the rest.
+    fn from_settings(settings: Settings) -> Result<Config, String> {
+        let sort = match settings.sort {
+            None => SortOrder::Name,
+            Some(sort) => SortOrder::from_str(&sort, true)
+                .map_err(|_| format!("unknown sort order {:?} in the configuration", sort))?,
+        };
+        let log_format = match settings.log_format {
+            None => LogFormat::Text,
+            Some(format) => LogFormat::from_str(&format, true)
+                .map_err(|_| format!("log_format {:?} is not text or json", format))?,
+        };
+        if settings.page_size == Some(0) {
+            return Err("page_size has to be at least 1".to_string());
+        }
+        let webhooks = settings.webhooks.unwrap_or_default();
+        let http = |url: &&String| url.starts_with("http://") || url.starts_with("https://");
+        if let Some(url) = webhooks.iter().find(|url| !http(url)) {
+            return Err(format!("webhook {:?} is not an http or https URL", url));
+        }
+        let quiet_hours = match settings.quiet_hours {
+            None => None,
+            Some(text) => Some(QuietHours::parse(&text).ok_or_else(|| {
+                format!("quiet_hours {:?} is not a span of the day like 22:00-07:00", text)
+            })?),
+        };
+        let date_format = settings.date_format.unwrap_or_else(|| DEFAULT_DATE_FORMAT.to_string());
+        // chrono only finds a bad format once something is formatted with it.
+        if write!(String::new(), "{}", Utc::now().format(&date_format)).is_err() {
+            return Err(format!("{:?} is not a usable date format", date_format));
+        }
+
+        Ok(Config {
+            storage: settings.storage,
+            storage_path: settings.storage_path,
+            backups: settings.backups.unwrap_or(5),
+            encrypt: settings.encrypt.unwrap_or(false),
+            autosave_seconds: settings.autosave_seconds.unwrap_or(30),
+            autosave_changes: settings.autosave_changes.unwrap_or(10),
+            compact_after: settings.compact_after.unwrap_or(1000),
+            low_stock_threshold: settings.low_stock_threshold.unwrap_or(5),
+            sort,
+            page_size: settings.page_size.unwrap_or(20),
+            date_format,
+            color: settings.color.unwrap_or(true),
+            socket: settings.socket.unwrap_or_else(|| String::from("inventory.sock")),
+            webhooks,
+            notifications: settings.notifications.unwrap_or(false),
+            quiet_hours,
+            log_level: settings.log_level.unwrap_or_else(|| String::from("warn")),
+            log_format,
+        })
+    }
+
+    // The threshold to_table colors rows by, when coloring is on.
+    pub fn highlight(&self) -> Option<u32> {
+        self.color.then_some(self.low_stock_threshold)
+    }
+
+    // The sort order as it's written on the command line.
+    pub fn sort_name(&self) -> String {
+        self.sort.to_possible_value().map_or_else(String::new, |value| value.get_name().to_string())
+    }
+}
+
+// Every setting at its default, as with no inventory.toml and no
+// environment variables.
+impl Default for Config {
+    fn default() -> Config {
+        Config::from_settings(Settings::default()).expect("the defaults are valid")
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn override_with(name: &str, setting: &mut Option<String>) {
+    if let Some(value) = env_var(name) {
+        *setting = Some(value);
+    }
+}
+
+// Like override_with for lists, given as values separated by commas.
+fn list_with(name: &str, setting: &mut Option<Vec<String>>) {
+    if let Some(value) = env_var(name) {
+        let values = value.split(',').map(str::trim).filter(|value| !value.is_empty());
+        *setting = Some(values.map(str::to_string).collect());
+    }
+}
+
+// Like override_with for settings that aren't text; `expected` describes
+// what the value should look like when it doesn't parse.
+fn parse_with<T: FromStr>(
+    name: &str,
+    setting: &mut Option<T>,
+    expected: &str,
+) -> Result<(), String> {
+    if let Some(value) = env_var(name) {
+        let parsed =
+            value.parse().map_err(|_| format!("{} {:?} is not {}", name, value, expected))?;
+        *setting = Some(parsed);
+    }
+    Ok(())
+}
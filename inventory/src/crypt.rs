@@ -0,0 +1,100 @@
+use std::env;
+use std::io;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+// An encrypted file is this header, the salt the key was derived with, the
+// nonce, and then the data sealed with ChaCha20-Poly1305.
+const MAGIC: &[u8] = b"inventory-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+// A key derived from a passphrase, and the salt it was derived with, which
+// is stored with everything it encrypts.
+#[derive(Clone)]
+pub struct Key {
+    cipher: ChaCha20Poly1305,
+    salt: [u8; SALT_LEN],
+}
+
+impl Key {
+    // A key with a new random salt, for a file that isn't encrypted yet.
+    pub fn new(passphrase: &str) -> Result<Key, String> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Key::derive(passphrase, salt)
+    }
+
+    // The key `data`, an encrypted file, was sealed with, if `passphrase`
+    // is the one it was made from.
+    pub fn for_file(passphrase: &str, data: &[u8]) -> Result<Key, String> {
+        let salt = salt_of(data).ok_or("the encrypted file is cut short")?;
+        let key = Key::derive(passphrase, salt)?;
+        key.decrypt(data)?;
+        Ok(key)
+    }
+
+    // Argon2id with its default cost, which takes a noticeable moment on
+    // purpose.
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Key, String> {
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| format!("could not derive a key: {}", e))?;
+        Ok(Key { cipher: ChaCha20Poly1305::new(&key.into()), salt })
+    }
+
+    // Seals `plain` under a fresh nonce, so saving the same data twice gives
+    // different files.
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self.cipher.encrypt(&nonce, plain).map_err(|_| "could not encrypt")?;
+        Ok([MAGIC, &self.salt, &nonce, &sealed].concat())
+    }
+
+    // Opens a file written by encrypt. A file sealed with another key fails
+    // the same way as one that was changed.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let body = data.get(MAGIC.len() + SALT_LEN..).ok_or("the encrypted file is cut short")?;
+        if body.len() < NONCE_LEN {
+            return Err("the encrypted file is cut short".to_string());
+        }
+        let (nonce, sealed) = body.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| "wrong passphrase, or the file is damaged".to_string())
+    }
+
+    // Whether `data` was sealed with this key's salt; a file written in
+    // another session, like a restored backup, needs its own key.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        salt_of(data) == Some(self.salt)
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn salt_of(data: &[u8]) -> Option<[u8; SALT_LEN]> {
+    data.get(MAGIC.len()..MAGIC.len() + SALT_LEN)?.try_into().ok()
+}
+
+// INVENTORY_PASSPHRASE when it's set, so scripts can run unattended;
+// otherwise asks on the terminal without echoing.
+pub fn read_passphrase(prompt: &str) -> io::Result<String> {
+    match env::var("INVENTORY_PASSPHRASE") {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => rpassword::prompt_password(prompt).map_err(|e| {
+            io::Error::new(e.kind(), format!("could not read the passphrase: {}", e))
+        }),
+    }
+}
+
+// A passphrase from the environment can't be asked for again.
+pub fn can_retry() -> bool {
+    env::var_os("INVENTORY_PASSPHRASE").is_none()
+}
@@ -0,0 +1,65 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::BufReader;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+use inventory_core::Collection;
+
+use crate::cli::CliCommand;
+use crate::config::Config;
+use crate::remote::{self, Shared};
+use crate::Storage;
+
+// Keeps the collection loaded and runs subcommands sent by `inventory
+// --connect` over a Unix socket, so a large file isn't read again for every
+// one. The requests and answers are those of the remote module.
+
+// Listens until the process is stopped. A socket left behind by a daemon
+// that was stopped is replaced.
+pub fn run(
+    collection: Collection,
+    storage: Option<&Storage>,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let path = Path::new(&config.socket);
+    if UnixStream::connect(path).is_ok() {
+        return Err(format!("a daemon is already listening on {}", config.socket).into());
+    }
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    // Like a session, the daemon starts with what has expired.
+    #[cfg(feature = "desktop")]
+    if let Some(warning) = crate::expired_warning(&collection) {
+        crate::desktop::alert(config, &warning);
+    }
+    let shared = Shared::new(collection, storage, config);
+    println!("Listening on {} with {} items", config.socket, shared.len());
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            let reader = stream.try_clone().map(BufReader::new);
+            if let Err(e) = reader.and_then(|reader| remote::answer_all(&shared, reader, stream)) {
+                tracing::warn!(error = %e, "connection failed");
+            }
+        });
+    }
+    Ok(())
+}
+
+// Sends the command line this was run with, less --connect, to the daemon and
+// prints what comes back.
+pub fn forward(command: Option<&CliCommand>, config: &Config) -> Result<(), Box<dyn Error>> {
+    remote::check(command)?;
+    let stream = UnixStream::connect(&config.socket)
+        .map_err(|e| format!("could not reach a daemon on {}: {}", config.socket, e))?;
+    let args: Vec<String> = env::args().skip(1).filter(|arg| arg != "--connect").collect();
+    remote::call(BufReader::new(stream.try_clone()?), stream, &args, config)
+}
@@ -0,0 +1,75 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use chrono::Local;
+use inventory_core::alerts::StockEvent;
+use inventory_core::Collection;
+use notify_rust::Notification;
+
+use crate::config::Config;
+
+// Stock alerts as the desktop's own notifications: an item falling low on
+// stock or running out while inventory runs, and what has expired when a
+// session or the daemon starts. The notifications setting turns them on and
+// quiet_hours holds them back; those held back aren't shown later.
+
+// The thread showing the stock alerts, as a notification daemon may be slow
+// to answer and changes shouldn't wait for it. Dropping this waits for it to
+// show what it was given.
+pub struct Desktop {
+    sender: Arc<Mutex<Option<Sender<String>>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+pub fn start(collection: &mut Collection, config: &Config) -> Option<Desktop> {
+    if !config.notifications {
+        return None;
+    }
+    let (sender, receiver) = mpsc::channel::<String>();
+    let shown = config.clone();
+    let thread = thread::spawn(move || {
+        for body in receiver {
+            alert(&shown, &body);
+        }
+    });
+
+    let sender = Arc::new(Mutex::new(Some(sender)));
+    let listening = Arc::clone(&sender);
+    collection.subscribe(move |event| {
+        let body = match event {
+            StockEvent::LowStock { name, quantity, threshold, .. } => {
+                format!("{} is low on stock: {} left, at or below {}", name, quantity, threshold)
+            }
+            StockEvent::OutOfStock { name, .. } => format!("{} is out of stock", name),
+            StockEvent::Created { .. } => return,
+        };
+        if let Some(sender) = listening.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            let _ = sender.send(body);
+        }
+    });
+    Some(Desktop { sender, thread: Some(thread) })
+}
+
+// Shows `body`, unless notifications are off or it's quiet hours. A desktop
+// that can't show it only costs a warning in the log.
+pub fn alert(config: &Config, body: &str) {
+    let quiet = config.quiet_hours.is_some_and(|hours| hours.contains(Local::now().time()));
+    if !config.notifications || quiet {
+        return;
+    }
+    let shown = Notification::new().appname("inventory").summary("Inventory").body(body).show();
+    if let Err(e) = shown {
+        tracing::warn!(error = %e, "could not show a notification");
+    }
+}
+
+impl Drop for Desktop {
+    fn drop(&mut self) {
+        // The thread stops once the channel is empty and closed.
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
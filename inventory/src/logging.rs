@@ -0,0 +1,19 @@
+use std::io::{self, IsTerminal};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{Config, LogFormat};
+
+// Writes what inventory and inventory-core log to stderr, filtered by the
+// log_level setting. Each event carries the spans it happened in, so a change
+// to an item is logged with the operation and the command that made it.
+pub fn init(config: &Config) -> Result<(), String> {
+    let filter = EnvFilter::try_new(&config.log_level)
+        .map_err(|e| format!("log_level {:?} is not usable: {}", config.log_level, e))?;
+    let logger = tracing_subscriber::fmt().with_env_filter(filter).with_writer(io::stderr);
+    match config.log_format {
+        LogFormat::Text => logger.with_ansi(config.color && io::stderr().is_terminal()).init(),
+        LogFormat::Json => logger.json().init(),
+    }
+    Ok(())
+}
@@ -1,83 +1,651 @@
-use::std::io::{self, Write};
-use::std::collections::HashMap;
+use::std::io::{self, IsTerminal};
+use::std::error::Error;
+#[cfg(feature = "encryption")]
+use::std::fs;
+use::std::num::IntErrorKind;
+use::std::path::Path;
+use::std::process;
+use::std::str::FromStr;
 
-struct Item {
-    _name: String,
-    _quantity: u8,
+mod autosave;
+mod backup;
+mod cli;
+mod config;
+#[cfg(feature = "encryption")]
+mod crypt;
+#[cfg(feature = "daemon")]
+mod daemon;
+#[cfg(feature = "desktop")]
+mod desktop;
+mod logging;
+mod menu;
+mod prompt;
+#[cfg(any(feature = "daemon", feature = "tcp"))]
+mod remote;
+mod report;
+#[cfg(feature = "rpc")]
+mod rpc;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+mod store;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "webhooks")]
+mod webhooks;
+
+use chrono::NaiveDate;
+use clap::{Parser, ValueEnum};
+use inventory_core::audit::AUDIT_LOG;
+use inventory_core::csv_file::CsvOptions;
+use inventory_core::history::History;
+use inventory_core::ledger::Reason;
+use inventory_core::unit::Unit;
+#[cfg(feature = "serde")]
+use inventory_core::events;
+#[cfg(feature = "sqlite")]
+use inventory_core::sqlite;
+use inventory_core::{parse_date, parse_price, Collection, LocationId, Operation};
+
+use autosave::Autosave;
+use cli::Cli;
+use config::Config;
+
+// Prompts until the user enters something other than whitespace and returns
+// it trimmed. End of input is an error rather than an endless re-prompt.
+fn read_nonempty(prompt: &str) -> io::Result<String> {
+    loop {
+        let input = match prompt::read_line(prompt)? {
+            Some(input) => input,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no more input")),
+        };
+
+        let input = input.trim();
+        if !input.is_empty() {
+            return Ok(input.to_string());
+        }
+    }
+}
+
+// How the collection is written to its file.
+#[derive(Clone, Copy)]
+enum Format {
+    #[cfg(feature = "serde")]
+    Json,
+    // A log of every change, replayed on load; see inventory_core::events.
+    #[cfg(feature = "serde")]
+    Events,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
 }
 
-struct Collection {
-    _items: HashMap<String, Item>,
+impl Format {
+    // Whether the file is a log of changes rather than the collection.
+    fn is_log(self) -> bool {
+        #[cfg(feature = "serde")]
+        if let Format::Events = self {
+            return true;
+        }
+        false
+    }
 }
 
-impl Collection {
+// Where the collection is loaded from at startup and saved to from the menu
+// or after a subcommand: the format picked with `--storage json|events|sqlite`,
+// the file it's kept in, and how many backups of that file to keep.
+#[derive(Clone)]
+struct Storage {
+    format: Format,
+    path: String,
+    backups: usize,
+    // How many events the log may hold before it is compacted.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    compact_after: usize,
+    // Set by unlock when the file is or is to be encrypted.
+    #[cfg(feature = "encryption")]
+    secret: Option<Secret>,
+}
 
-    fn new() -> Self {
-        Collection {
-            _items: HashMap::new(),
+// The passphrase is kept as well as the key, for backups sealed with
+// another salt.
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+struct Secret {
+    passphrase: String,
+    key: crypt::Key,
+}
+
+// With neither storage compiled in Format has no variants, and the bodies
+// below can't be reached.
+#[cfg_attr(
+    not(any(feature = "serde", feature = "sqlite")),
+    allow(unused_variables, unreachable_code)
+)]
+impl Storage {
+    // Picks the storage named by `--storage` or else the configuration.
+    // Without either the JSON file is used when it's compiled in. Each format
+    // has its own file unless the configuration gives one.
+    fn from_config(kind: Option<&str>, config: &Config) -> Result<Option<Storage>, String> {
+        let kind = match kind.or(config.storage.as_deref()) {
+            Some(kind) => kind,
+            None if cfg!(feature = "serde") => "json",
+            None => return Ok(None),
+        };
+        let (format, default_path) = match kind {
+            #[cfg(feature = "serde")]
+            "json" => (Format::Json, "inventory.json"),
+            #[cfg(feature = "serde")]
+            "events" => (Format::Events, "inventory.events"),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => (Format::Sqlite, "inventory.db"),
+            _ => return Err(format!("storage {} is not available in this build", kind)),
+        };
+        let path = config.storage_path.as_deref().unwrap_or(default_path).to_string();
+        Ok(Some(Storage {
+            format,
+            path,
+            // The log is its own history, and a copy of it would be of no use
+            // without the snapshot it follows.
+            backups: if format.is_log() { 0 } else { config.backups },
+            compact_after: config.compact_after,
+            #[cfg(feature = "encryption")]
+            secret: None,
+        }))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn unlock(&mut self, encrypt: bool) -> Result<(), Box<dyn Error>> {
+        if encrypt {
+            return Err("encryption is not available in this build".into());
         }
+        Ok(())
     }
 
-    fn add_item(&mut self, name: String, quantity: u8) {
-        let item = Item {
-            _name: name.to_string(),
-            _quantity: quantity,
+    // Asks for the passphrase of an encrypted file, up to three times, or
+    // for a new one when `encrypt` is set and the file isn't encrypted yet.
+    #[cfg(feature = "encryption")]
+    fn unlock(&mut self, encrypt: bool) -> Result<(), Box<dyn Error>> {
+        let data = match fs::read(self.path()) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
         };
 
-        self._items.insert(name.to_string(), item);
-        println!("added an item {} and quantity {}", name, quantity)
-    }
-    
-    fn update_item(&mut self, name: String, quantity: u8) {
-        if let Some(item) = self._items.get_mut(&name) {
-            item._quantity = quantity;
-            println!("Updated item: {} and quantity {}", name, quantity);
-        } else {
-            println!("NO item in the collection");
-        }
-    }
-    
-    fn list_item(&self) {
-        if self._items.is_empty() {
-            println!("There are no items in the list");
-        } else {
-            for item in self._items.values() {
-                println!("Added item: {} and quantity: {}", item._name, item._quantity);
+        if crypt::is_encrypted(&data) {
+            let mut attempts = 0;
+            loop {
+                let prompt = format!("Passphrase for {}: ", self.path());
+                let passphrase = crypt::read_passphrase(&prompt)?;
+                match crypt::Key::for_file(&passphrase, &data) {
+                    Ok(key) => {
+                        self.secret = Some(Secret { passphrase, key });
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        attempts += 1;
+                        if attempts == 3 || !crypt::can_retry() {
+                            return Err(e.into());
+                        }
+                        eprintln!("{}", e);
+                    }
+                }
             }
         }
+        if !encrypt {
+            return Ok(());
+        }
+        if !matches!(self.format, Format::Json) {
+            return Err("only json storage can be encrypted".into());
+        }
+        let passphrase = crypt::read_passphrase(&format!("New passphrase for {}: ", self.path()))?;
+        if passphrase.is_empty() {
+            return Err("the passphrase can't be empty".into());
+        }
+        if crypt::can_retry() && crypt::read_passphrase("Repeat the passphrase: ")? != passphrase {
+            return Err("the passphrases don't match".into());
+        }
+        self.secret = Some(Secret { key: crypt::Key::new(&passphrase)?, passphrase });
+        Ok(())
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    // Returns None when nothing has been saved yet.
+    fn load(&self) -> Result<Option<Collection>, Box<dyn Error>> {
+        let path = Path::new(self.path());
+        if !path.exists() {
+            return Ok(None);
+        }
+        #[cfg(feature = "encryption")]
+        if let Some(secret) = &self.secret {
+            let data = fs::read(path)?;
+            if crypt::is_encrypted(&data) {
+                let plain = match secret.key.matches(&data) {
+                    true => secret.key.decrypt(&data)?,
+                    false => crypt::Key::for_file(&secret.passphrase, &data)?.decrypt(&data)?,
+                };
+                return Ok(Some(Collection::from_json(&String::from_utf8(plain)?)?));
+            }
+        }
+        match self.format {
+            #[cfg(feature = "serde")]
+            Format::Json => Ok(Some(Collection::load_from_file(path)?)),
+            #[cfg(feature = "serde")]
+            Format::Events => Ok(Some(events::load(path)?)),
+            #[cfg(feature = "sqlite")]
+            Format::Sqlite => Ok(Some(sqlite::load(path)?)),
+        }
+    }
+
+    // Backs up the file as it was before overwriting it.
+    fn save(&self, collection: &Collection) -> Result<(), Box<dyn Error>> {
+        let path = Path::new(self.path());
+        if self.backups > 0 && path.exists() {
+            backup::create(path)?;
+            backup::prune(path, self.backups)?;
+        }
+        #[cfg(feature = "encryption")]
+        if let Some(secret) = &self.secret {
+            fs::write(path, secret.key.encrypt(collection.to_json()?.as_bytes())?)?;
+            return Ok(());
+        }
+        match self.format {
+            #[cfg(feature = "serde")]
+            Format::Json => collection.save_to_file(path)?,
+            #[cfg(feature = "serde")]
+            Format::Events => events::save(path, collection, self.compact_after)?,
+            #[cfg(feature = "sqlite")]
+            Format::Sqlite => sqlite::save(path, collection)?,
+        }
+        Ok(())
     }
+}
+
+// Prompts until the input is a price like 12 or 12.50. When `allow_empty` is
+// set an empty answer gives None.
+fn read_price(prompt: &str, allow_empty: bool) -> io::Result<Option<u64>> {
+    loop {
+        let input = match prompt::read_line(prompt)? {
+            Some(input) => input,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no more input")),
+        };
 
+        let input = input.trim();
+        if input.is_empty() && allow_empty {
+            return Ok(None);
+        }
+        match parse_price(input) {
+            Some(price) => return Ok(Some(price)),
+            None => println!("the price must be an amount like 12 or 12.50"),
+        }
+    }
 }
 
+// Prompts until the input is a whole number that fits a stored quantity.
+fn read_quantity(prompt: &str) -> io::Result<u32> {
+    loop {
+        match parse_quantity(&read_nonempty(prompt)?) {
+            Ok(quantity) => return Ok(quantity),
+            Err(problem) => println!("{}", problem),
+        }
+    }
+}
 
+// Parses a quantity, saying what is wrong with it when it isn't one: the
+// command line, the menu and the TUI all report the same way.
+fn parse_quantity(input: &str) -> Result<u32, String> {
+    let input = input.trim();
+    input.parse::<u32>().map_err(|e| match e.kind() {
+        IntErrorKind::PosOverflow => format!("{} is too large, the most is {}", input, u32::MAX),
+        _ if input.starts_with('-') => String::from("a quantity can't be negative"),
+        _ => format!("{} is not a whole number", input),
+    })
+}
 
-fn main() {
+// Prompts until the input is a whole number of type `T`.
+fn read_number<T: FromStr>(prompt: &str) -> io::Result<T> {
+    loop {
+        match read_nonempty(prompt)?.parse::<T>() {
+            Ok(number) => return Ok(number),
+            Err(_) => println!("that is not a whole number in range, try again"),
+        }
+    }
+}
 
-    let mut collection = Collection::new();
+// Prompts until the input is a date like 2024-03-31. An empty answer gives
+// None.
+fn read_date(prompt: &str) -> io::Result<Option<NaiveDate>> {
+    loop {
+        let input = match prompt::read_line(prompt)? {
+            Some(input) => input,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no more input")),
+        };
+
+        if input.trim().is_empty() {
+            return Ok(None);
+        }
+        match parse_date(&input) {
+            Some(day) => return Ok(Some(day)),
+            None => println!("the date must look like 2024-03-31"),
+        }
+    }
+}
+
+// A line warning about items past their expiry date, if there are any.
+fn expired_warning(collection: &Collection) -> Option<String> {
+    let expired = collection.expired();
+    if expired.is_empty() {
+        return None;
+    }
+    let names: Vec<String> =
+        expired.iter().map(|item| format!("{} ({})", item.name, item.sku)).collect();
+    Some(format!("Warning: {} items have expired: {}", expired.len(), names.join(", ")))
+}
+
+// Prompts until the input names a unit. An empty answer gives None.
+fn read_unit(prompt: &str) -> io::Result<Option<Unit>> {
+    loop {
+        let input = match prompt::read_line(prompt)? {
+            Some(input) => input,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no more input")),
+        };
+
+        if input.trim().is_empty() {
+            return Ok(None);
+        }
+        match input.parse::<Unit>() {
+            Ok(unit) => return Ok(Some(unit)),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
 
+// Prompts until the input names a reason stock went out. An empty answer is
+// a sale.
+fn read_reason() -> io::Result<Reason> {
     loop {
-        println!("1. Add an item");
-        println!("2. Update an item");
-        println!("3. List an item");
-        println!("4. Exit");
+        let input = match prompt::read_line(
+            "Enter the reason: sold, damaged or adjustment (empty for sold): ",
+        )? {
+            Some(input) => input,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no more input")),
+        };
+
+        if input.trim().is_empty() {
+            return Ok(Reason::Sold);
+        }
+        match Reason::from_str(input.trim(), true) {
+            Ok(reason) => return Ok(reason),
+            Err(_) => println!("{:?} is not sold, damaged or adjustment", input.trim()),
+        }
+    }
+}
+
+// Asks for a location. An empty answer is the main location.
+fn read_location() -> io::Result<LocationId> {
+    let input = prompt::read_line("Enter the location (empty for main): ")?.unwrap_or_default();
+
+    match input.trim() {
+        "" => Ok(LocationId::main()),
+        location => Ok(LocationId(location.to_string())),
+    }
+}
+
+// Asks for a CSV file and its separator. An empty answer keeps the comma, and
+// `tab` stands for a tab character.
+fn read_csv_options() -> Result<(String, CsvOptions), Box<dyn Error>> {
+    let path = read_nonempty("Enter the CSV file path: ")?;
+
+    let delimiter_input =
+        prompt::read_line("Enter the field separator (empty for a comma, tab for a tab): ")?
+            .unwrap_or_default();
+
+    let options = match delimiter_input.as_str() {
+        "" => CsvOptions::default(),
+        "tab" => CsvOptions::new('\t')?,
+        delimiter => {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(delimiter), None) => CsvOptions::new(delimiter)?,
+                _ => return Err(format!("{:?} is not a single character", delimiter).into()),
+            }
+        }
+    };
+    Ok((path, options))
+}
+
+const TOP_VALUED_ITEMS: usize = 5;
+
+// The width of the terminal in characters, or of COLUMNS when output goes
+// elsewhere, or 80.
+fn terminal_width() -> usize {
+    if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+        return usize::from(width);
+    }
+    std::env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()).unwrap_or(80)
+}
+
+// Reads `<name> <quantity>` lines until an empty line. Lines that don't parse
+// are reported and skipped.
+fn read_item_lines<T: FromStr>() -> io::Result<Vec<(String, T)>> {
+    println!("Enter one line per item as <name> <quantity>, then an empty line:");
+
+    let mut lines: Vec<(String, T)> = Vec::new();
+    loop {
+        let line_input = prompt::read_line("")?.unwrap_or_default();
+
+        let line = line_input.trim();
+        if line.is_empty() {
+            break;
+        }
+        let parsed = line
+            .rsplit_once(' ')
+            .map(|(name, qty)| (name.trim(), qty.parse::<T>()));
+        match parsed {
+            Some((name, Ok(quantity))) => lines.push((name.to_string(), quantity)),
+            _ => println!("skipping line, expected <name> <quantity>: {}", line),
+        }
+    }
+    Ok(lines)
+}
+
+// Parses one batch line: `add <name> <qty>`, `remove <name>` or
+// `adjust <name> <delta>`.
+fn parse_operation(line: &str) -> Option<Operation> {
+    let (command, rest) = line.split_once(' ')?;
+    match command {
+        "add" => {
+            let (name, qty) = rest.rsplit_once(' ')?;
+            Some(Operation::Add { name: name.trim().to_string(), qty: qty.parse().ok()? })
+        }
+        "remove" => Some(Operation::Remove { name: rest.trim().to_string() }),
+        "adjust" => {
+            let (name, delta) = rest.rsplit_once(' ')?;
+            Some(Operation::Adjust { name: name.trim().to_string(), delta: delta.parse().ok()? })
+        }
+        _ => None,
+    }
+}
 
-        print!("Enter your choice: ");
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("inventory stopped: {}", e);
+        process::exit(1);
+    }
+}
 
-        io::stdout().flush().expect("failed to flush the std out");
+fn run() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let mut config = Config::load()?;
+    if cli.no_color || !io::stdout().is_terminal() {
+        config.color = false;
+    }
+    logging::init(&config)?;
+    #[cfg(feature = "daemon")]
+    if cli.connect {
+        return daemon::forward(cli.command.as_ref(), &config);
+    }
+    // The items are on the server, so there's nothing to load.
+    #[cfg(feature = "tcp")]
+    if let Some(cli::CliCommand::Remote { address, args }) = &cli.command {
+        return remote::call_tcp(address, args, &config);
+    }
+    let mut storage = Storage::from_config(cli.storage.as_deref(), &config)?;
+    if let Some(storage) = &mut storage {
+        storage.unlock(config.encrypt)?;
+    }
 
-        let mut take_input: String = String::new();
+    // Anything else printed would get in the way of the answers.
+    #[cfg(feature = "rpc")]
+    let rpc = cli.rpc;
+    #[cfg(not(feature = "rpc"))]
+    let rpc = false;
 
-        io::stdin().read_line(&mut take_input).expect("failed to read the line");
+    let mut collection = Collection::new();
+    if let Some(storage) = &storage {
+        match storage.load() {
+            Ok(Some(loaded)) => {
+                if cli.command.is_none() && cli.script.is_none() && !rpc {
+                    println!("Loaded {} items from {}", loaded.len(), storage.path());
+                }
+                collection = loaded;
+            }
+            Ok(None) => {}
+            Err(e) => return Err(format!("could not load {}: {}", storage.path(), e).into()),
+        }
+    }
+    collection.set_date_format(config.date_format.clone());
+    collection.set_low_stock_threshold(config.low_stock_threshold);
+    // Only subscribed now, so merges made while loading aren't news.
+    #[cfg(feature = "webhooks")]
+    let _webhooks = webhooks::start(&mut collection, &config);
+    #[cfg(feature = "desktop")]
+    let _desktop = desktop::start(&mut collection, &config);
+    if let Some(script) = cli.script {
+        let mut out = io::stdout();
+        let result = cli::run_script(&script, &mut collection, storage.as_ref(), &config, &mut out);
+        collection.write_audit(Path::new(AUDIT_LOG))?;
+        return result;
+    }
+    if let Some(command) = cli.command {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let _command = tracing::info_span!("command", args = %args.join(" ")).entered();
+        cli::run_command(command, &mut collection, storage.as_ref(), &config, &mut io::stdout())?;
+        return Ok(collection.write_audit(Path::new(AUDIT_LOG))?);
+    }
+    // A session starts with what has expired, as a notification too.
+    #[cfg(feature = "desktop")]
+    if let Some(warning) = expired_warning(&collection) {
+        desktop::alert(&config, &warning);
+    }
+    #[cfg(feature = "tui")]
+    if cli.tui {
+        return tui::run(&mut collection, storage.as_ref());
+    }
+    #[cfg(feature = "rpc")]
+    if rpc {
+        return rpc::run(&mut collection, storage.as_ref());
+    }
+    if let Some(warning) = expired_warning(&collection) {
+        println!("{}", warning);
+    }
+    let commands = menu::commands(storage.as_ref());
+    let mut context = menu::Context {
+        snapshot: collection.clone(),
+        history: History::default(),
+        storage: storage.as_ref(),
+        config: &config,
+    };
+    let autosave = Autosave::start(collection, storage.clone(), &config);
+    prompt::start();
 
-        let choice: u8 = take_input.trim().parse().expect("failed to convert to integer");
+    let result = run_menu(&commands, &autosave, &mut context, prompt::read_line);
+    prompt::finish();
+    Ok(result?)
+}
 
-        match choice {
-            1 => collection.add_item(String::from("Apple"), 5),
-            2 => collection.update_item(String::from("Apple"), 8),
-            3 => collection.list_item(),
-            4 => break,
-            _ => println!("failed to recognize the choice"),
+// Shows the menu and runs what is chosen, as read by `read_choice`, until
+// quit. End of input counts as quitting.
+fn run_menu(
+    commands: &[Box<dyn menu::Command>],
+    autosave: &Autosave,
+    context: &mut menu::Context,
+    mut read_choice: impl FnMut(&str) -> io::Result<Option<String>>,
+) -> io::Result<()> {
+    loop {
+        if let Err(e) = autosave.write().write_audit(Path::new(AUDIT_LOG)) {
+            tracing::error!(file = AUDIT_LOG, error = %e, "could not write the audit log");
+        }
+        menu::print(commands);
+
+        // End of input (Ctrl-D, or the end of a piped file) is treated like Exit.
+        let take_input = match read_choice("Enter your choice (q to quit): ")? {
+            Some(input) => input,
+            None => {
+                println!();
+                break;
+            }
+        };
+
+        let input = take_input.trim();
+        let exit = (commands.len() + 1).to_string();
+        if ["q", "quit", "exit", &exit].iter().any(|word| input.eq_ignore_ascii_case(word)) {
+            break;
+        }
+        let command = match menu::find(commands, input) {
+            Some(command) => command,
+            None => {
+                println!("failed to recognize the choice");
+                continue;
+            }
+        };
 
+        // A save of its own waits for any autosave under way.
+        let saving = matches!(command.kind(), menu::Kind::Save).then(|| autosave.saving());
+        let mut collection = autosave.write();
+        let span = tracing::info_span!("menu", command = command.name());
+        let result = span.in_scope(|| command.execute(&mut collection, context));
+        drop(collection);
+        match (&result, command.kind()) {
+            (Ok(()), menu::Kind::Save) => autosave.saved(),
+            (Ok(()), menu::Kind::View) => {}
+            _ => autosave.changed(),
         }
+        drop(saving);
+        if let Err(e) = result {
+            println!("{}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_menu_ends_with_its_input_without_an_exit() {
+        let config = Config::default();
+        let commands = menu::commands(None);
+        let mut context = menu::Context {
+            snapshot: Collection::new(),
+            history: History::default(),
+            storage: None,
+            config: &config,
+        };
+        let autosave = Autosave::start(Collection::new(), None, &config);
+        let mut choices = ["validate", "nonsense", "histogram"].into_iter();
+        let mut asked = 0;
+        let read_choice = |_: &str| {
+            asked += 1;
+            Ok(choices.next().map(String::from))
+        };
+        run_menu(&commands, &autosave, &mut context, read_choice).unwrap();
+        assert_eq!(asked, 4);
     }
 }
@@ -0,0 +1,2199 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+use clap::ValueEnum;
+use inventory_core::aggregate::GroupBy;
+use inventory_core::audit::{self, AUDIT_LOG};
+use inventory_core::fuzzy::did_you_mean;
+use inventory_core::history::{self, History};
+use inventory_core::purchasing::OrderId;
+use inventory_core::query::Filter;
+use inventory_core::unit::Unit;
+use inventory_core::{
+    format_price, page_of, Change, Collection, InventoryError, Item, LocationId, Operation,
+    ReservationId, SortOrder,
+};
+
+use crate::cli::{
+    chart, dedupe, print_archived, print_kits, print_lots, print_movements,
+    print_reconciliation, print_reorder_report, print_summary,
+};
+use crate::config::Config;
+use crate::{
+    parse_operation, prompt, read_csv_options, read_date, read_item_lines, read_location,
+    read_nonempty, read_number, read_price, read_quantity, read_reason, read_unit, Storage,
+    TOP_VALUED_ITEMS,
+};
+
+// The interactive menu. Each entry is a Command, and the menu shows them in
+// the order `commands` registers them, numbered from 1; a new entry only
+// needs registering there.
+pub trait Command {
+    // What the entry can be chosen by instead of its number.
+    fn name(&self) -> &str;
+    // The line shown in the menu.
+    fn help(&self) -> &str;
+    // Errors are reported by the menu, which then shows itself again.
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome;
+    fn kind(&self) -> Kind {
+        Kind::Change
+    }
+}
+
+pub type Outcome = Result<(), Box<dyn Error>>;
+
+// What a command does to the collection, which decides whether it needs
+// autosaving afterwards.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    // Only looks at it, so there is nothing new to save.
+    View,
+    Change,
+    // Writes it to the storage file itself.
+    Save,
+}
+
+// What the commands keep between runs, besides the collection.
+pub struct Context<'a> {
+    // The collection as it was when changes were last shown.
+    pub snapshot: Collection,
+    pub history: History,
+    pub storage: Option<&'a Storage>,
+    pub config: &'a Config,
+}
+
+// Every menu entry, in the order they're shown.
+pub fn commands(storage: Option<&Storage>) -> Vec<Box<dyn Command>> {
+    let mut commands: Vec<Box<dyn Command>> = vec![
+        Box::new(Add),
+        Box::new(SetQuantity),
+        Box::new(List),
+        Box::new(Table),
+        Box::new(Duplicates),
+        Box::new(SetMax),
+        Box::new(Category),
+        Box::new(Where),
+        Box::new(Fulfill),
+        Box::new(Restock),
+        Box::new(Merge),
+        Box::new(Changes),
+        Box::new(Reserve),
+        Box::new(Settle { commit: false }),
+        Box::new(Settle { commit: true }),
+        Box::new(Validate),
+        Box::new(Lookup),
+        Box::new(SetAttribute),
+        Box::new(UnsetAttribute),
+        Box::new(WithAttribute),
+        Box::new(Batch),
+    ];
+    #[cfg(feature = "serde")]
+    commands.extend([Box::new(PrintJson) as Box<dyn Command>, Box::new(LoadJson)]);
+    commands.extend([
+        Box::new(Page) as Box<dyn Command>,
+        Box::new(CommonQuantity),
+        Box::new(StockCount),
+        Box::new(Histogram),
+        Box::new(Chart),
+    ]);
+    if let Some(storage) = storage {
+        commands.push(Box::new(Save { help: format!("Save to {}", storage.path()) }));
+    }
+    commands.extend([
+        Box::new(Remove) as Box<dyn Command>,
+        Box::new(Archive),
+        Box::new(Unarchive),
+        Box::new(Purge),
+        Box::new(Archived),
+        Box::new(Dedupe),
+        Box::new(Clear),
+        Box::new(Search),
+        Box::new(Export),
+        Box::new(Import),
+        Box::new(Undo),
+        Box::new(Redo),
+        Box::new(Value),
+        Box::new(Summarize),
+        Box::new(ItemHistory),
+        Box::new(Move { receive: true }),
+        Box::new(Move { receive: false }),
+        Box::new(Stale),
+        Box::new(AddLocation),
+        Box::new(Locations),
+        Box::new(Transfer),
+        Box::new(Expire),
+        Box::new(Expiring),
+        Box::new(AddSupplier),
+        Box::new(CreateOrder),
+        Box::new(Orders),
+        Box::new(ReceiveOrder),
+        Box::new(ReorderLevel),
+        Box::new(ReorderReport),
+        Box::new(Movements),
+        Box::new(Reconcile),
+        Box::new(Checkout),
+        Box::new(Sales),
+        Box::new(DailySales),
+        Box::new(TrackSerials),
+        Box::new(MoveSerials { receive: true }),
+        Box::new(MoveSerials { receive: false }),
+        Box::new(FindSerial),
+        Box::new(TrackLots),
+        Box::new(ReceiveLot),
+        Box::new(Lots),
+        Box::new(Kit),
+        Box::new(Assemble { assemble: true }),
+        Box::new(Assemble { assemble: false }),
+        Box::new(Kits),
+    ]);
+    commands
+}
+
+// Exit comes last, after the registered commands.
+pub fn print(commands: &[Box<dyn Command>]) {
+    for (i, command) in commands.iter().enumerate() {
+        println!("{}. {}", i + 1, command.help());
+    }
+    println!("{}. Exit", commands.len() + 1);
+}
+
+// The command chosen by `input`, a number from the menu or a command's name.
+pub fn find<'a>(commands: &'a [Box<dyn Command>], input: &str) -> Option<&'a dyn Command> {
+    let command = match input.parse::<usize>() {
+        Ok(number) => commands.get(number.checked_sub(1)?),
+        Err(_) => commands.iter().find(|command| command.name().eq_ignore_ascii_case(input)),
+    };
+    command.map(|command| command.as_ref())
+}
+
+// Shows `items` as a table, page_size of them at a time. Entering next or
+// prev moves between pages; anything else goes back to the menu.
+fn show_pages(collection: &Collection, items: &[&Item], config: &Config) -> Outcome {
+    let mut page = 1;
+    loop {
+        let (shown, total_pages) = page_of(items, page, config.page_size);
+        print!("{}", collection.table_of(shown, config.highlight()));
+        if total_pages <= 1 {
+            return Ok(());
+        }
+        let prompt = format!("Page {} of {}, next or prev (empty to stop): ", page, total_pages);
+        page = loop {
+            let input = prompt::read_line(&prompt)?.unwrap_or_default();
+            match input.trim() {
+                "next" | "n" if page < total_pages => break page + 1,
+                "prev" | "p" if page > 1 => break page - 1,
+                "next" | "n" => println!("That is the last page"),
+                "prev" | "p" => println!("That is the first page"),
+                _ => return Ok(()),
+            }
+        };
+    }
+}
+
+struct Add;
+
+impl Command for Add {
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    fn help(&self) -> &str {
+        "Add an item"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name: ")?;
+        let quantity = read_quantity("Enter the quantity: ")?;
+        let category_input =
+            prompt::read_line("Enter the category (empty for uncategorized): ")?
+                .unwrap_or_default();
+
+        let category = match category_input.trim() {
+            "" => String::from("uncategorized"),
+            category => category.to_string(),
+        };
+        let unit = read_unit("Enter the unit (empty for pcs): ")?.unwrap_or_default();
+        let unit_price = read_price("Enter the unit price: ", false)?.unwrap_or(0);
+
+        let added =
+            collection.add_item(name.to_string(), quantity, unit, category.clone(), unit_price);
+        match added {
+            Ok(sku) => {
+                println!(
+                    "added an item {} as {} and quantity {} {} in {} at {}",
+                    collection.lookup(&sku)?.name,
+                    sku,
+                    quantity,
+                    unit,
+                    category,
+                    format_price(unit_price)
+                );
+                context.history.record(vec![history::Command::Delete(sku)]);
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct SetQuantity;
+
+impl Command for SetQuantity {
+    fn name(&self) -> &str {
+        "set-quantity"
+    }
+
+    fn help(&self) -> &str {
+        "Set the quantity of an item (admin)"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        let quantity = read_quantity("Enter the new quantity: ")?;
+        let unit_price = read_price("Enter the new unit price (empty to keep): ", true)?;
+
+        let before = collection.get(&name).cloned();
+        match collection.update_item(name.to_string(), quantity, unit_price) {
+            Ok(()) => {
+                println!("Updated item: {} and quantity {}", name, quantity);
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct List;
+
+impl Command for List {
+    fn name(&self) -> &str {
+        "list"
+    }
+
+    fn help(&self) -> &str {
+        "List an item"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let config = context.config;
+        let order_input = prompt::read_line(&format!(
+            "Sort by name, quantity, quantity-desc or updated (empty for {}): ",
+            config.sort_name()
+        ))?
+        .unwrap_or_default();
+
+        let order = match order_input.trim() {
+            "" => config.sort,
+            order => match SortOrder::from_str(order, true) {
+                Ok(order) => order,
+                Err(_) => {
+                    println!("unknown sort order {}", order);
+                    return Ok(());
+                }
+            },
+        };
+        show_pages(collection, &collection.sorted(order), config)
+    }
+}
+
+struct Table;
+
+impl Command for Table {
+    fn name(&self) -> &str {
+        "table"
+    }
+
+    fn help(&self) -> &str {
+        "Show items as a table"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        show_pages(collection, &collection.sorted(context.config.sort), context.config)
+    }
+}
+
+struct Duplicates;
+
+impl Command for Duplicates {
+    fn name(&self) -> &str {
+        "duplicates"
+    }
+
+    fn help(&self) -> &str {
+        "Find duplicate names"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let collisions = collection.detect_collisions();
+        if collisions.is_empty() {
+            println!("No duplicate names found");
+        }
+        for (normalized, names) in collisions {
+            println!("{} is stored as: {}", normalized, names.join(", "));
+        }
+        Ok(())
+    }
+}
+
+struct SetMax;
+
+impl Command for SetMax {
+    fn name(&self) -> &str {
+        "set-max"
+    }
+
+    fn help(&self) -> &str {
+        "Set the maximum quantity per item"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let max_input = prompt::read_line("Enter the maximum per item (empty for no limit): ")?
+            .unwrap_or_default();
+
+        match max_input.trim() {
+            "" => collection.set_max_per_item(None),
+            max => match max.parse::<u32>() {
+                Ok(max) => collection.set_max_per_item(Some(max)),
+                Err(_) => println!("{} is not a whole number, the limit is unchanged", max),
+            },
+        }
+        Ok(())
+    }
+}
+
+struct Category;
+
+impl Command for Category {
+    fn name(&self) -> &str {
+        "category"
+    }
+
+    fn help(&self) -> &str {
+        "List items in a category"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let category = read_nonempty("Enter the category: ")?;
+
+        let items = collection.list_by_category(&category);
+        if items.is_empty() {
+            println!("There are no items in that category");
+        }
+        for item in items {
+            println!("{}: {}", item.name, item.quantity);
+        }
+        Ok(())
+    }
+}
+
+struct Where;
+
+impl Command for Where {
+    fn name(&self) -> &str {
+        "where"
+    }
+
+    fn help(&self) -> &str {
+        "List items matching a filter, like quantity < 5 and category = produce"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let input = read_nonempty("List where: ")?;
+        let filter = match Filter::parse(&input) {
+            Ok(filter) => filter,
+            Err(e) => {
+                println!("{}", e);
+                return Ok(());
+            }
+        };
+        let items: Vec<&Item> = collection
+            .sorted(context.config.sort)
+            .into_iter()
+            .filter(|item| filter.matches(item))
+            .collect();
+        show_pages(collection, &items, context.config)
+    }
+}
+
+struct Fulfill;
+
+impl Command for Fulfill {
+    fn name(&self) -> &str {
+        "fulfill"
+    }
+
+    fn help(&self) -> &str {
+        "Fulfill an order"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let order: Vec<(String, u32)> = read_item_lines()?;
+        match collection.fulfill(&order) {
+            Ok(()) => println!("Order fulfilled"),
+            Err(e) => println!("Order not fulfilled, {}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Restock;
+
+impl Command for Restock {
+    fn name(&self) -> &str {
+        "restock"
+    }
+
+    fn help(&self) -> &str {
+        "Plan a restock"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let config = context.config;
+        let target_input = prompt::read_line(&format!(
+            "Enter the target quantity (empty for {}): ",
+            config.low_stock_threshold
+        ))?
+        .unwrap_or_default();
+
+        let target: u32 = match target_input.trim() {
+            "" => config.low_stock_threshold,
+            target => match target.parse() {
+                Ok(target) => target,
+                Err(_) => {
+                    println!("{} is not a whole number", target);
+                    return Ok(());
+                }
+            },
+        };
+        let plan = collection.restock_plan(target);
+        if plan.is_empty() {
+            println!("Nothing needs restocking");
+        }
+        for (name, needed) in plan {
+            println!("Order {} more {}", needed, name);
+        }
+        Ok(())
+    }
+}
+
+struct Merge;
+
+impl Command for Merge {
+    fn name(&self) -> &str {
+        "merge"
+    }
+
+    fn help(&self) -> &str {
+        "Merge in stock from another warehouse"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let mut other = Collection::new();
+        for (name, quantity) in read_item_lines::<u32>()? {
+            // merge gives the items SKUs of its own.
+            other.add_item(name, quantity, Unit::Pieces, String::from("uncategorized"), 0)?;
+        }
+
+        match collection.merge(other) {
+            Ok(()) => println!("Stock merged"),
+            Err(e) => println!("Stock not merged, {}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Changes;
+
+impl Command for Changes {
+    fn name(&self) -> &str {
+        "changes"
+    }
+
+    fn help(&self) -> &str {
+        "Show changes since the last snapshot"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let changes = context.snapshot.diff(collection);
+        if changes.is_empty() {
+            println!("Nothing changed");
+        }
+        for change in changes {
+            match change {
+                Change::Added(name, quantity) => println!("+ {} {}", name, quantity),
+                Change::Removed(name, quantity) => println!("- {} {}", name, quantity),
+                Change::Updated { name, from, to } => {
+                    println!("~ {} {} -> {}", name, from, to)
+                }
+            }
+        }
+        context.snapshot = collection.clone();
+        Ok(())
+    }
+}
+
+struct Reserve;
+
+impl Command for Reserve {
+    fn name(&self) -> &str {
+        "reserve"
+    }
+
+    fn help(&self) -> &str {
+        "Reserve stock"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        for (name, quantity) in read_item_lines::<u32>()? {
+            match collection.reserve(&name, quantity) {
+                Ok(id) => println!("Reserved {} {} as {}", quantity, name, id),
+                Err(e) => println!("Not reserved, {}", e),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Releases a reservation, or commits it when `commit` is set.
+struct Settle {
+    commit: bool,
+}
+
+impl Command for Settle {
+    fn name(&self) -> &str {
+        if self.commit { "commit" } else { "release" }
+    }
+
+    fn help(&self) -> &str {
+        if self.commit { "Commit a reservation" } else { "Release a reservation" }
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let id_input = read_nonempty("Enter the reservation number: ")?;
+        let id = match id_input.trim_start_matches('#').parse() {
+            Ok(id) => ReservationId(id),
+            Err(_) => {
+                println!("{} is not a reservation number like #3", id_input);
+                return Ok(());
+            }
+        };
+        let result = if !self.commit {
+            collection.release(id)
+        } else {
+            collection.commit(id)
+        };
+        match result {
+            Ok(()) if !self.commit => println!("Released {}", id),
+            Ok(()) => println!("Committed {}", id),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+struct Validate;
+
+impl Command for Validate {
+    fn name(&self) -> &str {
+        "validate"
+    }
+
+    fn help(&self) -> &str {
+        "Check the collection for problems"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        match collection.validate() {
+            Ok(()) => println!("No problems found"),
+            Err(problems) => {
+                for problem in problems {
+                    println!("{}", problem);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Lookup;
+
+impl Command for Lookup {
+    fn name(&self) -> &str {
+        "lookup"
+    }
+
+    fn help(&self) -> &str {
+        "Look up an item"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+
+        match collection.lookup(&name) {
+            Ok(item) => {
+                println!("{} ({}): {} in {}", item.name, item.sku, item.quantity, item.category);
+                if let Some(serials) = &item.serials {
+                    let serials: Vec<&str> = serials.iter().map(String::as_str).collect();
+                    println!("serial numbers: {}", serials.join(", "));
+                }
+                for (name, value) in item.attributes() {
+                    println!("{}: {}", name, value);
+                }
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct SetAttribute;
+
+impl Command for SetAttribute {
+    fn name(&self) -> &str {
+        "set-attribute"
+    }
+
+    fn help(&self) -> &str {
+        "Set an attribute of an item, like its color or size"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let key = read_nonempty("Enter the item name or SKU: ")?;
+        let name = read_nonempty("Enter the attribute: ")?;
+        let value = read_nonempty("Enter its value: ")?;
+        let before = collection.get(&key).cloned();
+        match collection.set_attribute(&key, &name, &value) {
+            Ok(()) => {
+                println!("Set {} of {} to {}", name, key, value);
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct UnsetAttribute;
+
+impl Command for UnsetAttribute {
+    fn name(&self) -> &str {
+        "unset-attribute"
+    }
+
+    fn help(&self) -> &str {
+        "Remove an attribute from an item"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let key = read_nonempty("Enter the item name or SKU: ")?;
+        let name = read_nonempty("Enter the attribute: ")?;
+        let before = collection.get(&key).cloned();
+        match collection.unset_attribute(&key, &name) {
+            Ok(value) => {
+                println!("Removed {} ({}) from {}", name, value, key);
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct WithAttribute;
+
+impl Command for WithAttribute {
+    fn name(&self) -> &str {
+        "with-attribute"
+    }
+
+    fn help(&self) -> &str {
+        "List the items with an attribute"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the attribute: ")?;
+        let value = prompt::read_line("Enter the value (empty for any): ")?.unwrap_or_default();
+        let value = Some(value.trim()).filter(|value| !value.is_empty());
+        let items: Vec<&Item> = collection
+            .sorted(context.config.sort)
+            .into_iter()
+            .filter(|item| item.has_attribute(&name, value))
+            .collect();
+        print!("{}", collection.table_of(&items, context.config.highlight()));
+        Ok(())
+    }
+}
+
+struct Batch;
+
+impl Command for Batch {
+    fn name(&self) -> &str {
+        "batch"
+    }
+
+    fn help(&self) -> &str {
+        "Apply a batch of operations"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        println!("Enter add <name> <qty>, remove <name> or adjust <name> <delta>");
+        println!("one per line, then an empty line:");
+
+        let mut ops: Vec<Operation> = Vec::new();
+        loop {
+            let line_input = prompt::read_line("")?.unwrap_or_default();
+
+            let line = line_input.trim();
+            if line.is_empty() {
+                break;
+            }
+            match parse_operation(line) {
+                Some(op) => ops.push(op),
+                None => println!("skipping line, not an operation: {}", line),
+            }
+        }
+
+        match collection.apply_all(&ops) {
+            Ok(()) => println!("Applied {} operations", ops.len()),
+            Err((i, e)) => println!("Operation {} failed, nothing applied: {}", i + 1, e),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+struct PrintJson;
+
+#[cfg(feature = "serde")]
+impl Command for PrintJson {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn help(&self) -> &str {
+        "Print the collection as JSON"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        println!("{}", collection.to_json()?);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+struct LoadJson;
+
+#[cfg(feature = "serde")]
+impl Command for LoadJson {
+    fn name(&self) -> &str {
+        "load-json"
+    }
+
+    fn help(&self) -> &str {
+        "Replace the collection from a line of JSON"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let json = read_nonempty("Enter the JSON: ")?;
+
+        match Collection::from_json(&json) {
+            Ok(loaded) => {
+                collection.replace(loaded);
+                println!("Collection replaced");
+            }
+            Err(e) => println!("Collection not replaced, {}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Page;
+
+impl Command for Page {
+    fn name(&self) -> &str {
+        "page"
+    }
+
+    fn help(&self) -> &str {
+        "List a page of items"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let page: usize = read_number("Enter the page number: ")?;
+        let (items, total_pages) = collection.list_page(page, context.config.page_size);
+        for item in items {
+            println!("{}: {} in {}", item.name, item.quantity, item.category);
+        }
+        println!("page {} of {}", page, total_pages);
+        Ok(())
+    }
+}
+
+struct CommonQuantity;
+
+impl Command for CommonQuantity {
+    fn name(&self) -> &str {
+        "common"
+    }
+
+    fn help(&self) -> &str {
+        "Show the most common quantity"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        match collection.most_common_quantity() {
+            Some((quantity, count)) => println!("{} items have quantity {}", count, quantity),
+            None => println!("There are no items in the list"),
+        }
+        Ok(())
+    }
+}
+
+struct StockCount;
+
+impl Command for StockCount {
+    fn name(&self) -> &str {
+        "count"
+    }
+
+    fn help(&self) -> &str {
+        "Compare against a stock count"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let baseline: HashMap<String, u32> =
+            read_item_lines::<u32>()?.into_iter().collect();
+        let changes = collection.changed_since(&baseline);
+        if changes.is_empty() {
+            println!("Stock matches the count");
+        }
+        for (name, delta) in changes {
+            println!("{} {:+}", name, delta);
+        }
+        Ok(())
+    }
+}
+
+struct Histogram;
+
+impl Command for Histogram {
+    fn name(&self) -> &str {
+        "histogram"
+    }
+
+    fn help(&self) -> &str {
+        "Show a quantity histogram"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        print!("{}", collection.histogram());
+        Ok(())
+    }
+}
+
+struct Chart;
+
+impl Command for Chart {
+    fn name(&self) -> &str {
+        "chart"
+    }
+
+    fn help(&self) -> &str {
+        "Show quantities as a bar chart"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let category =
+            prompt::read_line("Only the category (empty for all): ")?.unwrap_or_default();
+        let top = prompt::read_line("How many of the most stocked items (empty for all): ")?
+            .unwrap_or_default();
+        let top = match top.trim() {
+            "" => None,
+            top => match top.parse() {
+                Ok(top) => Some(top),
+                Err(_) => {
+                    println!("{} is not a whole number", top);
+                    return Ok(());
+                }
+            },
+        };
+        let category = Some(category.trim()).filter(|category| !category.is_empty());
+        print!("{}", chart(collection, category, top));
+        Ok(())
+    }
+}
+
+// Only registered when there is a storage file.
+struct Save {
+    help: String,
+}
+
+impl Command for Save {
+    fn name(&self) -> &str {
+        "save"
+    }
+
+    fn help(&self) -> &str {
+        &self.help
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::Save
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let storage = context.storage.ok_or("there is no storage file to save to")?;
+        storage
+            .save(collection)
+            .map_err(|e| format!("Could not save to {}: {}", storage.path(), e))?;
+        println!("Saved {} items to {}", collection.len(), storage.path());
+        Ok(())
+    }
+}
+struct Remove;
+
+impl Command for Remove {
+    fn name(&self) -> &str {
+        "remove"
+    }
+
+    fn help(&self) -> &str {
+        "Remove an item"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        match collection.remove_item(&name) {
+            Ok(item) => {
+                println!("Removed item: {} and quantity {}", item.name, item.quantity);
+                context.history.record(vec![history::Command::put(item)]);
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+// Archive, restore and purge aren't undoable: undo only knows how to put
+// items back into the collection, not into the archive.
+struct Archive;
+
+impl Command for Archive {
+    fn name(&self) -> &str {
+        "archive"
+    }
+
+    fn help(&self) -> &str {
+        "Archive an item"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        match collection.archive(&name) {
+            Ok(()) => println!("Archived {}", name),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Unarchive;
+
+impl Command for Unarchive {
+    fn name(&self) -> &str {
+        "unarchive"
+    }
+
+    fn help(&self) -> &str {
+        "Restore an archived item"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the archived item's name or SKU: ")?;
+        match collection.restore(&name) {
+            Ok(()) => println!("Restored {}", name),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Purge;
+
+impl Command for Purge {
+    fn name(&self) -> &str {
+        "purge"
+    }
+
+    fn help(&self) -> &str {
+        "Purge an archived item for good"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the archived item's name or SKU: ")?;
+        match collection.purge(&name) {
+            Ok(item) => println!("Purged {} ({})", item.name, item.sku),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Archived;
+
+impl Command for Archived {
+    fn name(&self) -> &str {
+        "archived"
+    }
+
+    fn help(&self) -> &str {
+        "List the archived items"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        print_archived(collection, &mut io::stdout())?;
+        Ok(())
+    }
+}
+
+// Not undoable either: the merged item's movements and sales now carry the
+// SKU it was merged into, and undo would only put the item back.
+struct Dedupe;
+
+impl Command for Dedupe {
+    fn name(&self) -> &str {
+        "dedupe"
+    }
+
+    fn help(&self) -> &str {
+        "Find likely duplicate items and merge them"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        dedupe(collection, false, &mut io::stdout())?;
+        Ok(())
+    }
+}
+
+struct Clear;
+
+impl Command for Clear {
+    fn name(&self) -> &str {
+        "clear"
+    }
+
+    fn help(&self) -> &str {
+        "Remove all items"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let count = collection.len();
+        let prompt = format!("Type yes to remove all {} items: ", count);
+        if read_nonempty(&prompt)?.eq_ignore_ascii_case("yes") {
+            context.history.record(collection.iter().cloned().map(history::Command::put).collect());
+            collection.clear();
+            println!("Removed {} items", count);
+        } else {
+            println!("Nothing removed");
+        }
+        Ok(())
+    }
+}
+
+struct Search;
+
+impl Command for Search {
+    fn name(&self) -> &str {
+        "search"
+    }
+
+    fn help(&self) -> &str {
+        "Search items by name"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let query = read_nonempty("Enter part of the name: ")?;
+        let items = collection.search(&query);
+        if items.is_empty() {
+            let suggestions = did_you_mean(&collection.closest_names(&query));
+            println!("No items match {}{}", query, suggestions);
+        }
+        for item in items {
+            println!("{}: {} in {}", item.name, item.quantity, item.category);
+        }
+        Ok(())
+    }
+}
+
+struct Export;
+
+impl Command for Export {
+    fn name(&self) -> &str {
+        "export"
+    }
+
+    fn help(&self) -> &str {
+        "Export to a CSV file"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        match read_csv_options() {
+            Ok((path, options)) => match collection.export_csv(Path::new(&path), &options) {
+                Ok(()) => println!("Exported {} items to {}", collection.len(), path),
+                Err(e) => println!("Could not export to {}: {}", path, e),
+            },
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Import;
+
+impl Command for Import {
+    fn name(&self) -> &str {
+        "import"
+    }
+
+    fn help(&self) -> &str {
+        "Import from a CSV file"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        match read_csv_options() {
+            Ok((path, options)) => match collection.import_csv(Path::new(&path), &options) {
+                Ok(problems) => {
+                    for problem in &problems {
+                        println!("skipped {}", problem);
+                    }
+                    println!("Imported {} with {} rows skipped", path, problems.len());
+                }
+                Err(e) => println!("Could not import {}: {}", path, e),
+            },
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Undo;
+
+impl Command for Undo {
+    fn name(&self) -> &str {
+        "undo"
+    }
+
+    fn help(&self) -> &str {
+        "Undo"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        if !context.history.undo(collection) {
+            println!("Nothing to undo");
+        }
+        Ok(())
+    }
+}
+
+struct Redo;
+
+impl Command for Redo {
+    fn name(&self) -> &str {
+        "redo"
+    }
+
+    fn help(&self) -> &str {
+        "Redo"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        if !context.history.redo(collection) {
+            println!("Nothing to redo");
+        }
+        Ok(())
+    }
+}
+
+struct Value;
+
+impl Command for Value {
+    fn name(&self) -> &str {
+        "value"
+    }
+
+    fn help(&self) -> &str {
+        "Show the inventory value"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        println!("Total value: {}", format_price(collection.total_value()));
+        for (category, value) in collection.value_by_category() {
+            println!("  {}: {}", category, format_price(value));
+        }
+        println!("Most valuable items:");
+        for item in collection.most_valuable(TOP_VALUED_ITEMS) {
+            println!(
+                "  {}: {} x {} = {}",
+                item.name,
+                item.quantity,
+                format_price(item.unit_price),
+                format_price(item.value())
+            );
+        }
+        Ok(())
+    }
+}
+
+struct Summarize;
+
+impl Command for Summarize {
+    fn name(&self) -> &str {
+        "summary"
+    }
+
+    fn help(&self) -> &str {
+        "Count items, their quantity and their value by category or another field"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let by =
+            prompt::read_line("Group by category, unit or an attribute (empty for category): ")?
+                .unwrap_or_default();
+        let by = match by.trim() {
+            "" => GroupBy::Category,
+            name => GroupBy::named(name),
+        };
+        print_summary(&collection.summarize(&by), &by, &mut io::stdout())?;
+        Ok(())
+    }
+}
+
+struct ItemHistory;
+
+impl Command for ItemHistory {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn help(&self) -> &str {
+        "Show the history of an item"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, _collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+
+        match audit::item_history(Path::new(AUDIT_LOG), &name) {
+            Ok(entries) if entries.is_empty() => println!("No changes to {} logged", name),
+            Ok(entries) => {
+                for entry in entries {
+                    println!("{}", entry);
+                }
+            }
+            Err(e) => println!("Could not read {}: {}", AUDIT_LOG, e),
+        }
+        Ok(())
+    }
+}
+
+// Receives stock into a location, or consumes it from one when `receive`
+// isn't set.
+struct Move {
+    receive: bool,
+}
+
+impl Command for Move {
+    fn name(&self) -> &str {
+        if self.receive { "receive" } else { "consume" }
+    }
+
+    fn help(&self) -> &str {
+        if self.receive { "Receive stock" } else { "Consume stock" }
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        let quantity = read_quantity("Enter the quantity: ")?;
+        let unit = read_unit("Enter the unit (empty for the item's own): ")?;
+        let location = read_location()?;
+
+        let before = collection.get(&name).cloned();
+        let result = if self.receive {
+            collection.receive(&name, &location, quantity, unit)
+        } else {
+            let reason = read_reason()?;
+            collection.consume(&name, &location, quantity, unit, reason)
+        };
+        match result {
+            Ok(()) => {
+                if let Some(item) = collection.get(&name) {
+                    let (name, sku) = (&item.name, &item.sku);
+                    println!("{} ({}) now has quantity {}", name, sku, item.quantity);
+                }
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+struct Stale;
+
+impl Command for Stale {
+    fn name(&self) -> &str {
+        "stale"
+    }
+
+    fn help(&self) -> &str {
+        "List items not updated in a number of days"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let days: u32 = read_number("Enter the number of days: ")?;
+
+        let items = collection.stale(days);
+        if items.is_empty() {
+            println!("Every item was updated in the last {} days", days);
+        }
+        for item in items {
+            println!(
+                "{} ({}): {}, last updated {}",
+                item.name,
+                item.sku,
+                item.quantity,
+                collection.format_time(item.updated_at)
+            );
+        }
+        Ok(())
+    }
+}
+
+struct Transfer;
+
+impl Command for Transfer {
+    fn name(&self) -> &str {
+        "transfer"
+    }
+
+    fn help(&self) -> &str {
+        "Transfer stock between locations"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        let quantity = read_quantity("Enter the quantity: ")?;
+        let from = LocationId(read_nonempty("Enter the location to take it from: ")?);
+        let to = LocationId(read_nonempty("Enter the location to put it in: ")?);
+
+        let before = collection.get(&name).cloned();
+        match collection.transfer(&name, quantity, &from, &to) {
+            Ok(()) => {
+                println!("Transferred {} of {} from {} to {}", quantity, name, from, to);
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct AddLocation;
+
+impl Command for AddLocation {
+    fn name(&self) -> &str {
+        "add-location"
+    }
+
+    fn help(&self) -> &str {
+        "Add a location"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let location = LocationId(read_nonempty("Enter the location name: ")?);
+        match collection.add_location(location.clone()) {
+            Ok(()) => println!("Added location {}", location),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Locations;
+
+impl Command for Locations {
+    fn name(&self) -> &str {
+        "locations"
+    }
+
+    fn help(&self) -> &str {
+        "Show stock per location"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        print!("{}", collection.location_report());
+        Ok(())
+    }
+}
+
+struct Expire;
+
+impl Command for Expire {
+    fn name(&self) -> &str {
+        "expire"
+    }
+
+    fn help(&self) -> &str {
+        "Set the expiry date of an item"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        let expires_on = read_date("Enter the expiry date as YYYY-MM-DD (empty for none): ")?;
+
+        match collection.set_expiry(&name, expires_on) {
+            Ok(()) => match expires_on {
+                Some(day) => println!("{} expires on {}", name, day),
+                None => println!("{} no longer expires", name),
+            },
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Expiring;
+
+impl Command for Expiring {
+    fn name(&self) -> &str {
+        "expiring"
+    }
+
+    fn help(&self) -> &str {
+        "List items expiring soon"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let days: u32 = read_number("Enter the number of days: ")?;
+
+        let items = collection.expiring_within(days);
+        if items.is_empty() {
+            println!("Nothing expires in the next {} days", days);
+        }
+        for item in items {
+            if let Some(expires_on) = item.expires_on {
+                let (name, sku) = (&item.name, &item.sku);
+                println!("{} ({}): {}, expires on {}", name, sku, item.quantity, expires_on);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct AddSupplier;
+
+impl Command for AddSupplier {
+    fn name(&self) -> &str {
+        "add-supplier"
+    }
+
+    fn help(&self) -> &str {
+        "Add a supplier"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the supplier name: ")?;
+        let contact = prompt::read_line("Enter how to contact them (empty for none): ")?
+            .unwrap_or_default();
+        let lead_time: u32 = read_number("Enter how many days their orders take to arrive: ")?;
+
+        let contact = contact.trim().to_string();
+        match collection.add_supplier(name.to_string(), contact, lead_time) {
+            Ok(_) => println!("Added supplier {}", name),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct CreateOrder;
+
+impl Command for CreateOrder {
+    fn name(&self) -> &str {
+        "order"
+    }
+
+    fn help(&self) -> &str {
+        "Create a purchase order"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let supplier = read_nonempty("Enter the supplier name: ")?;
+        if let Err(e) = collection.find_supplier(&supplier) {
+            println!("{}", e);
+            return Ok(());
+        }
+        let lines = read_item_lines::<u32>()?;
+
+        match collection.create_order(&supplier, &lines) {
+            Ok(id) => println!("Created {}", id),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Orders;
+
+impl Command for Orders {
+    fn name(&self) -> &str {
+        "orders"
+    }
+
+    fn help(&self) -> &str {
+        "List purchase orders"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let mut any = false;
+        for order in collection.orders() {
+            any = true;
+            let supplier = collection.supplier(order.supplier).map_or("?", |s| &s.name);
+            let status = match &order.receipt {
+                Some(receipt) => format!(
+                    "received {} at {}",
+                    collection.format_time(receipt.at),
+                    receipt.location
+                ),
+                None => String::from("open"),
+            };
+            println!(
+                "{} from {}, created {}, {}",
+                order.id,
+                supplier,
+                collection.format_time(order.created_at),
+                status
+            );
+            for (item, quantity) in collection.order_items(order) {
+                println!("  {} ({}): {} {}", item.name, item.sku, quantity, item.unit);
+            }
+        }
+        if !any {
+            println!("No purchase orders");
+        }
+        Ok(())
+    }
+}
+
+struct ReceiveOrder;
+
+impl Command for ReceiveOrder {
+    fn name(&self) -> &str {
+        "receive-order"
+    }
+
+    fn help(&self) -> &str {
+        "Receive a purchase order"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let id_input = read_nonempty("Enter the purchase order number: ")?;
+        let id = match id_input.to_uppercase().trim_start_matches("PO-").parse() {
+            Ok(id) => OrderId(id),
+            Err(_) => {
+                println!("{} is not a purchase order number like PO-3", id_input);
+                return Ok(());
+            }
+        };
+        match collection.order(id) {
+            Ok(order) if order.receipt.is_some() => {
+                println!("{}", InventoryError::OrderAlreadyReceived { id });
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(e) => {
+                println!("{}", e);
+                return Ok(());
+            }
+        }
+        let location = read_location()?;
+
+        // Not undoable: undo would take the stock back out but leave the
+        // order received.
+        match collection.receive_order(id, &location) {
+            Ok(()) => println!("Received {} into {}", id, location),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct ReorderLevel;
+
+impl Command for ReorderLevel {
+    fn name(&self) -> &str {
+        "reorder-level"
+    }
+
+    fn help(&self) -> &str {
+        "Set the reorder level of an item"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        let level_input =
+            prompt::read_line("Enter the reorder level (empty for the default): ")?
+                .unwrap_or_default();
+        let level = match level_input.trim() {
+            "" => None,
+            input => match input.parse() {
+                Ok(level) => Some(level),
+                Err(_) => {
+                    println!("{} is not a whole number", input);
+                    return Ok(());
+                }
+            },
+        };
+
+        match collection.set_reorder_level(&name, level) {
+            Ok(()) => match level {
+                Some(level) => println!("{} is reordered at {}", name, level),
+                None => println!("{} is reordered at the default level", name),
+            },
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct ReorderReport;
+
+impl Command for ReorderReport {
+    fn name(&self) -> &str {
+        "reorder-report"
+    }
+
+    fn help(&self) -> &str {
+        "Suggest what to order"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let days: u32 = read_number("Enter over how many past days to measure use: ")?;
+        let threshold = context.config.low_stock_threshold;
+        print_reorder_report(collection, days, threshold, &mut io::stdout())?;
+        Ok(())
+    }
+}
+
+struct Movements;
+
+impl Command for Movements {
+    fn name(&self) -> &str {
+        "movements"
+    }
+
+    fn help(&self) -> &str {
+        "Show the movements of an item"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        if let Err(e) = print_movements(collection, &name, &mut io::stdout()) {
+            println!("{}", e);
+        }
+        Ok(())
+    }
+}
+
+struct Reconcile;
+
+impl Command for Reconcile {
+    fn name(&self) -> &str {
+        "reconcile"
+    }
+
+    fn help(&self) -> &str {
+        "Check quantities against their movements"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        print_reconciliation(collection, &mut io::stdout())?;
+        Ok(())
+    }
+}
+
+struct Checkout;
+
+impl Command for Checkout {
+    fn name(&self) -> &str {
+        "checkout"
+    }
+
+    fn help(&self) -> &str {
+        "Check out a sale"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let lines: Vec<(String, u32)> = read_item_lines()?;
+        match collection.checkout(&lines) {
+            Ok(id) => {
+                if let Some(sale) = collection.sale(id) {
+                    println!("Sold as {} for {}", id, format_price(sale.total));
+                }
+            }
+            Err(e) => println!("Not sold, {}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Sales;
+
+impl Command for Sales {
+    fn name(&self) -> &str {
+        "sales"
+    }
+
+    fn help(&self) -> &str {
+        "List sales"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let mut any = false;
+        for sale in collection.sales() {
+            any = true;
+            let at = collection.format_time(sale.at);
+            println!("{} at {}: {}", sale.id, at, format_price(sale.total));
+            for line in &sale.lines {
+                println!(
+                    "  {} ({}): {} x {} = {}",
+                    line.name,
+                    line.sku,
+                    line.quantity,
+                    format_price(line.unit_price),
+                    format_price(line.total())
+                );
+            }
+        }
+        if !any {
+            println!("No sales");
+        }
+        Ok(())
+    }
+}
+
+struct DailySales;
+
+impl Command for DailySales {
+    fn name(&self) -> &str {
+        "daily-sales"
+    }
+
+    fn help(&self) -> &str {
+        "Show sales per day"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let days = collection.daily_totals();
+        if days.is_empty() {
+            println!("No sales");
+        }
+        for day in days {
+            println!("{}: {} sales, {}", day.day, day.sales, format_price(day.total));
+        }
+        Ok(())
+    }
+}
+
+struct TrackSerials;
+
+impl Command for TrackSerials {
+    fn name(&self) -> &str {
+        "track-serials"
+    }
+
+    fn help(&self) -> &str {
+        "Track an item by serial number"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+
+        match collection.track_serials(&name) {
+            Ok(()) => println!("{} is now tracked by serial number", name),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct MoveSerials {
+    receive: bool,
+}
+
+impl Command for MoveSerials {
+    fn name(&self) -> &str {
+        if self.receive { "receive-serials" } else { "consume-serial" }
+    }
+
+    fn help(&self) -> &str {
+        if self.receive { "Receive stock by serial number" } else { "Consume a serial number" }
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let (name, result) = if self.receive {
+            let name = read_nonempty("Enter the item name or SKU: ")?;
+            let serials: Vec<String> = read_nonempty("Enter the serial numbers: ")?
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            let location = read_location()?;
+            let before = collection.get(&name).cloned();
+            let result = collection.receive_serials(&name, &location, &serials);
+            (name, result.map(|()| before))
+        } else {
+            let serial = read_nonempty("Enter the serial number: ")?;
+            let location = read_location()?;
+            let reason = read_reason()?;
+            let before = collection.find_serial(&serial).ok().cloned();
+            let name = before.as_ref().map(|item| item.sku.to_string()).unwrap_or_default();
+            let result = collection.consume_serial(&serial, &location, reason);
+            (name, result.map(|()| before))
+        };
+        match result {
+            Ok(before) => {
+                if let Some(item) = collection.get(&name) {
+                    let (name, sku) = (&item.name, &item.sku);
+                    println!("{} ({}) now has quantity {}", name, sku, item.quantity);
+                }
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct FindSerial;
+
+impl Command for FindSerial {
+    fn name(&self) -> &str {
+        "find-serial"
+    }
+
+    fn help(&self) -> &str {
+        "Find the item a serial number belongs to"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let serial = read_nonempty("Enter the serial number: ")?;
+
+        match collection.find_serial(&serial) {
+            Ok(item) => println!("{} belongs to {} ({})", serial, item.name, item.sku),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct TrackLots;
+
+impl Command for TrackLots {
+    fn name(&self) -> &str {
+        "track-lots"
+    }
+
+    fn help(&self) -> &str {
+        "Track an item by lot"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+
+        match collection.track_lots(&name) {
+            Ok(()) => println!("{} is now tracked by lot", name),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct ReceiveLot;
+
+impl Command for ReceiveLot {
+    fn name(&self) -> &str {
+        "receive-lot"
+    }
+
+    fn help(&self) -> &str {
+        "Receive stock as a new lot"
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        let lot = read_nonempty("Enter the lot number: ")?;
+        let quantity = read_quantity("Enter the quantity: ")?;
+        let unit = read_unit("Enter the unit (empty for the item's own): ")?;
+        let expires_on = read_date("Enter the expiry date as YYYY-MM-DD (empty for none): ")?;
+        let location = read_location()?;
+
+        let before = collection.get(&name).cloned();
+        match collection.receive_lot(&name, &location, quantity, unit, lot, expires_on) {
+            Ok(()) => {
+                if let Some(item) = collection.get(&name) {
+                    let (name, sku) = (&item.name, &item.sku);
+                    println!("{} ({}) now has quantity {}", name, sku, item.quantity);
+                }
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Lots;
+
+impl Command for Lots {
+    fn name(&self) -> &str {
+        "lots"
+    }
+
+    fn help(&self) -> &str {
+        "Show the lots of an item"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let input = prompt::read_line("Enter the item name or SKU (empty for every item): ")?
+            .unwrap_or_default();
+
+        let key = Some(input.trim()).filter(|key| !key.is_empty());
+        if let Err(e) = print_lots(collection, key, &mut io::stdout()) {
+            println!("{}", e);
+        }
+        Ok(())
+    }
+}
+
+struct Kit;
+
+impl Command for Kit {
+    fn name(&self) -> &str {
+        "kit"
+    }
+
+    fn help(&self) -> &str {
+        "Set what goes into a kit"
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the item name or SKU: ")?;
+        println!("Give what goes into one kit; none makes it an ordinary item again.");
+        let components = read_item_lines::<u32>()?;
+
+        match collection.set_components(&name, &components) {
+            Ok(()) if components.is_empty() => println!("{} is no longer a kit", name),
+            Ok(()) => println!("{} is now a kit", name),
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Assemble {
+    assemble: bool,
+}
+
+impl Command for Assemble {
+    fn name(&self) -> &str {
+        if self.assemble { "assemble" } else { "disassemble" }
+    }
+
+    fn help(&self) -> &str {
+        if self.assemble { "Assemble kits" } else { "Disassemble kits" }
+    }
+
+    fn execute(&self, collection: &mut Collection, context: &mut Context) -> Outcome {
+        let name = read_nonempty("Enter the kit name or SKU: ")?;
+        let count: u32 = read_number("Enter the number of kits: ")?;
+        let location = read_location()?;
+
+        // Undo puts back the kit and every component.
+        let before: Vec<Item> = match collection.lookup(&name) {
+            Ok(kit) => {
+                let components = collection.kit_components(kit).into_iter().map(|(item, _)| item);
+                std::iter::once(kit).chain(components).cloned().collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        let result = if self.assemble {
+            collection.assemble(&name, &location, count)
+        } else {
+            collection.disassemble(&name, &location, count)
+        };
+        match result {
+            Ok(()) => {
+                if let Some(item) = collection.get(&name) {
+                    let (name, sku) = (&item.name, &item.sku);
+                    println!("{} ({}) now has quantity {}", name, sku, item.quantity);
+                }
+                context.history.record(before.into_iter().map(history::Command::put).collect());
+            }
+            Err(e) => println!("{}", e),
+        }
+        Ok(())
+    }
+}
+
+struct Kits;
+
+impl Command for Kits {
+    fn name(&self) -> &str {
+        "kits"
+    }
+
+    fn help(&self) -> &str {
+        "Show the kits"
+    }
+
+    fn kind(&self) -> Kind {
+        Kind::View
+    }
+
+    fn execute(&self, collection: &mut Collection, _context: &mut Context) -> Outcome {
+        print_kits(collection, &mut io::stdout())?;
+        Ok(())
+    }
+}
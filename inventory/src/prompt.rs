@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+// Kept in the home directory so it carries over between runs and folders.
+const HISTORY_FILE: &str = ".inventory_history";
+
+thread_local! {
+    // Set up by start when stdin is a terminal. Piped input is read plainly,
+    // so scripts feeding the menu see the prompts as before.
+    static EDITOR: RefCell<Option<DefaultEditor>> = const { RefCell::new(None) };
+}
+
+fn history_path() -> Option<PathBuf> {
+    env::home_dir().map(|home| home.join(HISTORY_FILE))
+}
+
+// Turns on line editing and history (arrow keys, Ctrl+R to search) for the
+// interactive menu, loading what earlier runs saved.
+pub fn start() {
+    if !io::stdin().is_terminal() {
+        return;
+    }
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            tracing::warn!(error = %e, "line editing is off");
+            return;
+        }
+    };
+    if let Some(path) = history_path() {
+        // There is nothing to load on the first run.
+        let _ = editor.load_history(&path);
+    }
+    EDITOR.with(|cell| *cell.borrow_mut() = Some(editor));
+}
+
+// Saves the history for the next run.
+pub fn finish() {
+    EDITOR.with(|cell| {
+        if let (Some(editor), Some(path)) = (cell.borrow_mut().as_mut(), history_path()) {
+            if let Err(e) = editor.save_history(&path) {
+                tracing::warn!(file = %path.display(), error = %e, "could not save the history");
+            }
+        }
+    });
+}
+
+// Shows `prompt` and reads a line without its line ending. End of input, or
+// Ctrl+C / Ctrl+D at the prompt, gives None.
+pub fn read_line(prompt: &str) -> io::Result<Option<String>> {
+    let edited = EDITOR.with(|cell| {
+        let mut editor = cell.borrow_mut();
+        let editor = editor.as_mut()?;
+        Some(match editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    let _ = editor.add_history_entry(line.as_str());
+                }
+                Ok(Some(line))
+            }
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => Ok(None),
+            Err(ReadlineError::Io(e)) => Err(e),
+            Err(e) => Err(io::Error::other(e)),
+        })
+    });
+    if let Some(result) = edited {
+        return result;
+    }
+
+    print!("{}", prompt);
+    // The prompt may just show up late; that's no reason to stop.
+    if let Err(e) = io::stdout().flush() {
+        tracing::warn!(error = %e, "could not flush the output");
+    }
+
+    let mut input: String = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Ok(None);
+    }
+    let len = input.trim_end_matches(['\n', '\r']).len();
+    input.truncate(len);
+    Ok(Some(input))
+}
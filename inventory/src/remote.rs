@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::io::{self, BufRead, Read, Write};
+use std::iter;
+#[cfg(feature = "tcp")]
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "tcp")]
+use std::thread;
+
+use clap::Parser;
+use inventory_core::audit::AUDIT_LOG;
+use inventory_core::Collection;
+
+use crate::cli::{self, Cli, CliCommand};
+use crate::config::Config;
+use crate::Storage;
+
+// Running subcommands sent from another process, over the daemon's Unix
+// socket or over TCP, against a collection kept loaded. They run one at a
+// time and save after a change as they would on their own; what they print
+// goes back to the one that sent them.
+//
+// Each request is one line: the command line after `inventory`, with double
+// quotes around arguments that have spaces, as in a script. Each answer is a
+// line `ok LENGTH` or `error LENGTH MESSAGE`, followed by LENGTH bytes of what
+// the command printed. A connection can send any number of requests.
+
+// The collection every connection works on, and the file it's saved to.
+pub struct Shared {
+    collection: Mutex<Collection>,
+    storage: Option<Storage>,
+    config: Config,
+}
+
+impl Shared {
+    pub fn new(collection: Collection, storage: Option<&Storage>, config: &Config) -> Arc<Shared> {
+        Arc::new(Shared {
+            collection: Mutex::new(collection),
+            storage: storage.cloned(),
+            config: config.clone(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.collection.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+// Listens on `address` until the process is stopped. Anyone who can reach
+// it can change the items.
+#[cfg(feature = "tcp")]
+pub fn serve_tcp(shared: Arc<Shared>, address: &str) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(address)?;
+    println!("Listening on {} with {} items", listener.local_addr()?, shared.len());
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr()?;
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            let reader = stream.try_clone().map(io::BufReader::new);
+            if let Err(e) = reader.and_then(|reader| answer_all(&shared, reader, stream)) {
+                tracing::warn!(%peer, error = %e, "connection failed");
+            }
+        });
+    }
+    Ok(())
+}
+
+// Answers requests from `reader` on `writer` until the other side is done.
+pub fn answer_all(shared: &Shared, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (result, output) = run(shared, &line);
+        match result {
+            Ok(()) => writeln!(writer, "ok {}", output.len())?,
+            // The message has to stay on its line.
+            Err(e) => writeln!(writer, "error {} {}", output.len(), e.replace('\n', " "))?,
+        }
+        writer.write_all(&output)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+// Runs the request `line` and returns how it went and what it printed.
+fn run(shared: &Shared, line: &str) -> (Result<(), String>, Vec<u8>) {
+    let _request = tracing::info_span!("request", line).entered();
+    let mut collection = shared.collection.lock().unwrap_or_else(|e| e.into_inner());
+    let mut output = Vec::new();
+    let result =
+        execute(line, &mut collection, shared.storage.as_ref(), &shared.config, &mut output);
+    if let Err(e) = collection.write_audit(Path::new(AUDIT_LOG)) {
+        tracing::error!(file = AUDIT_LOG, error = %e, "could not write the audit log");
+    }
+    (result.map_err(|e| e.to_string()), output)
+}
+
+fn execute(
+    line: &str,
+    collection: &mut Collection,
+    storage: Option<&Storage>,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let words = cli::split_words(line)?;
+    let cli = Cli::try_parse_from(iter::once(String::from("inventory")).chain(words))
+        .map_err(|e| cli::clap_message(&e))?;
+    if cli.storage.is_some() || cli.script.is_some() {
+        return Err("the items are kept by the server, one subcommand at a time".into());
+    }
+    check(cli.command.as_ref())?;
+    let command = cli.command.expect("check fails without a command");
+    let mut config = config.clone();
+    config.color &= !cli.no_color;
+    cli::run_command(command, collection, storage, &config, out)
+}
+
+pub fn check(command: Option<&CliCommand>) -> Result<(), String> {
+    match command {
+        Some(command) if command.runs_unattended() => Ok(()),
+        Some(_) => Err(String::from("that subcommand keeps running or asks for input")),
+        None => Err(String::from("there is no subcommand to send")),
+    }
+}
+
+// Sends the subcommand `args` to the server at `address` and prints what
+// comes back.
+#[cfg(feature = "tcp")]
+pub fn call_tcp(address: &str, args: &[String], config: &Config) -> Result<(), Box<dyn Error>> {
+    let stream = TcpStream::connect(address)
+        .map_err(|e| format!("could not reach a server on {}: {}", address, e))?;
+    call(io::BufReader::new(stream.try_clone()?), stream, args, config)
+}
+
+// Sends one request and prints the answer, returning the error it carries.
+// Output that isn't a terminal asks for a table without color.
+pub fn call(
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    args: &[String],
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let mut words = args.iter().map(|arg| quote(arg)).collect::<Result<Vec<_>, _>>()?;
+    if !config.color && !args.iter().any(|arg| arg == "--no-color") {
+        words.push(String::from("--no-color"));
+    }
+    writeln!(writer, "{}", words.join(" "))?;
+    writer.flush()?;
+
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    let mut parts = status.trim_end().splitn(3, ' ');
+    let (status, length) = (parts.next(), parts.next().and_then(|length| length.parse().ok()));
+    let (Some(status), Some(length)) = (status, length) else {
+        return Err("the server closed the connection without answering".into());
+    };
+    // Read as it comes rather than set aside up front, so a wrong length
+    // can't take more memory than the answer itself.
+    let mut output = Vec::new();
+    reader.take(length).read_to_end(&mut output)?;
+    if output.len() as u64 != length {
+        return Err("the server closed the connection part way through its answer".into());
+    }
+    io::stdout().write_all(&output)?;
+    match status {
+        "ok" => Ok(()),
+        _ => Err(parts.next().unwrap_or("the command failed").into()),
+    }
+}
+
+// An argument as split_words reads it back.
+fn quote(arg: &str) -> Result<String, String> {
+    if arg.contains(['"', '\n']) {
+        return Err(format!("{:?} can't be sent, as it has a quote or a line break", arg));
+    }
+    match arg.is_empty() || arg.contains(char::is_whitespace) {
+        true => Ok(format!("\"{}\"", arg)),
+        false => Ok(arg.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(requests: &str) -> String {
+        let config = Config::default();
+        let shared = Shared::new(Collection::new(), None, &config);
+        let mut answers = Vec::new();
+        answer_all(&shared, requests.as_bytes(), &mut answers).unwrap();
+        String::from_utf8(answers).unwrap()
+    }
+
+    #[test]
+    fn what_a_request_prints_is_its_answer() {
+        let answers = answers("value\nrestore\n");
+        let mut lines = answers.lines();
+        let output = "Total value: 0.00\n";
+        assert_eq!(lines.next(), Some(format!("ok {}", output.len()).as_str()));
+        assert_eq!(lines.next(), Some(output.trim_end()));
+        assert_eq!(lines.next(), Some("error 0 that subcommand keeps running or asks for input"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn an_answer_shorter_than_its_length_is_an_error() {
+        let config = Config::default();
+        let answer = &b"ok 1000000000000\nTotal value: 0.00\n"[..];
+        let error = call(answer, io::sink(), &[String::from("value")], &config).unwrap_err();
+        let expected = "the server closed the connection part way through its answer";
+        assert_eq!(error.to_string(), expected);
+    }
+}
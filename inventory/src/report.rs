@@ -0,0 +1,218 @@
+use std::fmt::Write;
+
+use chrono::Utc;
+use clap::ValueEnum;
+use inventory_core::{format_price, Collection, Item};
+
+// What `report` writes: Markdown for a README or chat, or a standalone HTML
+// page.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Md,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Md => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+// How an item's stock compares to the low-stock threshold.
+#[derive(Clone, Copy, PartialEq)]
+enum Stock {
+    Enough,
+    Low,
+    Out,
+}
+
+impl Stock {
+    fn of(item: &Item, low_stock: u32) -> Stock {
+        match item.quantity {
+            0 => Stock::Out,
+            quantity if quantity <= low_stock => Stock::Low,
+            _ => Stock::Enough,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Stock::Enough => "",
+            Stock::Low => "low",
+            Stock::Out => "out of stock",
+        }
+    }
+}
+
+// The pieces a report is built from; each format writes them its own way.
+// Text passed in is plain and escaped here as the format needs.
+trait Render {
+    fn begin(&self, out: &mut String, title: &str);
+    fn heading(&self, out: &mut String, text: &str);
+    fn paragraph(&self, out: &mut String, text: &str);
+    fn table(&self, out: &mut String, header: &[&str], rows: &[(Stock, Vec<String>)]);
+    fn end(&self, out: &mut String);
+}
+
+struct Markdown;
+
+impl Markdown {
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if "\\`*_[]<>|#".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+}
+
+impl Render for Markdown {
+    fn begin(&self, out: &mut String, title: &str) {
+        let _ = writeln!(out, "# {}\n", Markdown::escape(title));
+    }
+
+    fn heading(&self, out: &mut String, text: &str) {
+        let _ = writeln!(out, "## {}\n", Markdown::escape(text));
+    }
+
+    fn paragraph(&self, out: &mut String, text: &str) {
+        let _ = writeln!(out, "{}\n", Markdown::escape(text));
+    }
+
+    // Rows running out of stock start with their first cell in bold.
+    fn table(&self, out: &mut String, header: &[&str], rows: &[(Stock, Vec<String>)]) {
+        let _ = writeln!(out, "| {} |", header.join(" | "));
+        let _ = writeln!(out, "|{}", "---|".repeat(header.len()));
+        for (stock, cells) in rows {
+            let mut cells: Vec<String> = cells.iter().map(|cell| Markdown::escape(cell)).collect();
+            if let (Stock::Low | Stock::Out, Some(first)) = (stock, cells.first_mut()) {
+                *first = format!("**{}**", first);
+            }
+            let _ = writeln!(out, "| {} |", cells.join(" | "));
+        }
+        out.push('\n');
+    }
+
+    fn end(&self, _out: &mut String) {}
+}
+
+struct Html;
+
+impl Html {
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
+
+const STYLE: &str = "body { font-family: sans-serif; } \
+    table { border-collapse: collapse; } \
+    th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; } \
+    tr.low { background: #fff3c4; } \
+    tr.out { background: #f8d0d0; }";
+
+impl Render for Html {
+    fn begin(&self, out: &mut String, title: &str) {
+        let title = Html::escape(title);
+        let _ = writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">");
+        let _ = writeln!(out, "<title>{}</title>\n<style>{}</style>", title, STYLE);
+        let _ = writeln!(out, "</head>\n<body>\n<h1>{}</h1>", title);
+    }
+
+    fn heading(&self, out: &mut String, text: &str) {
+        let _ = writeln!(out, "<h2>{}</h2>", Html::escape(text));
+    }
+
+    fn paragraph(&self, out: &mut String, text: &str) {
+        let _ = writeln!(out, "<p>{}</p>", Html::escape(text));
+    }
+
+    // Rows running out of stock get a class the style colors.
+    fn table(&self, out: &mut String, header: &[&str], rows: &[(Stock, Vec<String>)]) {
+        out.push_str("<table>\n<tr>");
+        for column in header {
+            let _ = write!(out, "<th>{}</th>", Html::escape(column));
+        }
+        out.push_str("</tr>\n");
+        for (stock, cells) in rows {
+            match stock {
+                Stock::Enough => out.push_str("<tr>"),
+                Stock::Low => out.push_str("<tr class=\"low\">"),
+                Stock::Out => out.push_str("<tr class=\"out\">"),
+            }
+            for cell in cells {
+                let _ = write!(out, "<td>{}</td>", Html::escape(cell));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+
+    fn end(&self, out: &mut String) {
+        out.push_str("</body>\n</html>\n");
+    }
+}
+
+// The whole inventory grouped by category, each with its items and their
+// value, after a summary of the totals and of the items at or below
+// `low_stock`.
+pub fn render(collection: &Collection, format: ReportFormat, low_stock: u32) -> String {
+    let renderer: &dyn Render = match format {
+        ReportFormat::Md => &Markdown,
+        ReportFormat::Html => &Html,
+    };
+
+    let mut out = String::new();
+    renderer.begin(&mut out, "Inventory report");
+
+    let count =
+        |stock| collection.iter().filter(|&item| Stock::of(item, low_stock) == stock).count();
+    renderer.paragraph(
+        &mut out,
+        &format!(
+            "Generated {}. {} items worth {} in total; {} out of stock and {} low \
+             (at or below {}).",
+            collection.format_time(Utc::now()),
+            collection.len(),
+            format_price(collection.total_value()),
+            count(Stock::Out),
+            count(Stock::Low),
+            low_stock
+        ),
+    );
+
+    let header = ["Name", "SKU", "Quantity", "Unit", "Unit price", "Value", "Stock"];
+    for (category, value) in collection.value_by_category() {
+        renderer.heading(&mut out, &category);
+        let rows: Vec<(Stock, Vec<String>)> = collection
+            .list_by_category(&category)
+            .into_iter()
+            .map(|item| {
+                let stock = Stock::of(item, low_stock);
+                let cells = vec![
+                    item.name.to_string(),
+                    item.sku.to_string(),
+                    item.quantity.to_string(),
+                    item.unit.to_string(),
+                    format_price(item.unit_price),
+                    format_price(item.value()),
+                    stock.label().to_string(),
+                ];
+                (stock, cells)
+            })
+            .collect();
+        renderer.table(&mut out, &header, &rows);
+        renderer.paragraph(&mut out, &format!("Value of {}: {}", category, format_price(value)));
+    }
+
+    renderer.end(&mut out);
+    out
+}
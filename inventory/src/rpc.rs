@@ -0,0 +1,370 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use inventory_core::aggregate::GroupBy;
+use inventory_core::audit::AUDIT_LOG;
+use inventory_core::ledger::Reason;
+use inventory_core::query::Filter;
+use inventory_core::unit::Unit;
+use inventory_core::{Collection, InventoryError, Item, LocationId, SortOrder};
+
+use crate::Storage;
+
+// JSON-RPC 2.0 over stdin and stdout, for editors and other programs that
+// run inventory as a subprocess. Each line read is a request, or a batch of
+// them as an array, and the answer to it is written as one line.
+// Notifications, requests without an id, get no answer. The collection is
+// saved after each line that changed it.
+//
+// The methods and their params, which are given by name:
+//
+//     list             {sort?, where?}                              -> [item]
+//     get              {key}                                        -> item
+//     search           {query}                                      -> [item]
+//     add              {name, quantity, category?, unit?, unit_price?} -> item
+//     update           {key, quantity, unit_price?}                 -> item
+//     remove           {key}                                        -> item
+//     receive          {key, quantity, location?, unit?}            -> item
+//     consume          {key, quantity, location?, unit?, reason?}   -> item
+//     set_attribute    {key, name, value}                           -> item
+//     unset_attribute  {key, name}                                  -> item
+//     summary          {by?}                                        -> {groups, total}
+//
+// `key` is a SKU, or a name only one item has. Prices are in cents.
+
+// The codes from the specification.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+// The inventory's own: the item isn't there, the name is shared, there
+// isn't enough stock, and anything else the collection refuses.
+const NOT_FOUND: i64 = -32001;
+const AMBIGUOUS_NAME: i64 = -32002;
+const INSUFFICIENT_STOCK: i64 = -32003;
+const REFUSED: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> RpcError {
+        RpcError { code, message: message.to_string() }
+    }
+}
+
+impl From<InventoryError> for RpcError {
+    fn from(e: InventoryError) -> Self {
+        let code = match e {
+            InventoryError::ItemNotFound { .. } => NOT_FOUND,
+            InventoryError::AmbiguousName { .. } => AMBIGUOUS_NAME,
+            InventoryError::InsufficientStock { .. } => INSUFFICIENT_STOCK,
+            InventoryError::InvalidFilter { .. } => INVALID_PARAMS,
+            _ => REFUSED,
+        };
+        RpcError::new(code, e)
+    }
+}
+
+// Answers requests until stdin ends.
+pub fn run(collection: &mut Collection, storage: Option<&Storage>) -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut changed = false;
+        if let Some(answer) = answer_line(collection, &line, &mut changed) {
+            writeln!(stdout, "{}", answer)?;
+            stdout.flush()?;
+        }
+        // The answers are out already, so a failed save can only be logged.
+        if let (true, Some(storage)) = (changed, storage) {
+            if let Err(e) = storage.save(collection) {
+                tracing::error!(file = storage.path(), error = %e, "could not save");
+            }
+        }
+        if let Err(e) = collection.write_audit(Path::new(AUDIT_LOG)) {
+            tracing::error!(file = AUDIT_LOG, error = %e, "could not write the audit log");
+        }
+    }
+    Ok(())
+}
+
+fn answer_line(collection: &mut Collection, line: &str, changed: &mut bool) -> Option<Value> {
+    match serde_json::from_str(line) {
+        Err(e) => Some(response(Value::Null, Err(RpcError::new(PARSE_ERROR, e)))),
+        Ok(Value::Array(requests)) if requests.is_empty() => {
+            let e = RpcError::new(INVALID_REQUEST, "a batch needs at least one request");
+            Some(response(Value::Null, Err(e)))
+        }
+        Ok(Value::Array(requests)) => {
+            let answers: Vec<Value> = requests
+                .into_iter()
+                .filter_map(|request| answer(collection, request, changed))
+                .collect();
+            (!answers.is_empty()).then_some(Value::Array(answers))
+        }
+        Ok(request) => answer(collection, request, changed),
+    }
+}
+
+// None for a notification, a well-formed request without an id. A malformed
+// one is always answered, with its id when that can be read and with id null
+// when it can't.
+fn answer(collection: &mut Collection, request: Value, changed: &mut bool) -> Option<Value> {
+    let Value::Object(mut request) = request else {
+        let e = RpcError::new(INVALID_REQUEST, "a request has to be an object");
+        return Some(response(Value::Null, Err(e)));
+    };
+    let id = request.remove("id");
+    if id.as_ref().is_some_and(|id| !matches!(id, Value::String(_) | Value::Number(_))) {
+        let e = RpcError::new(INVALID_REQUEST, "the id has to be a string or a number");
+        return Some(response(Value::Null, Err(e)));
+    }
+    let method = request.remove("method");
+    match (request.get("jsonrpc").and_then(Value::as_str), method) {
+        (Some("2.0"), Some(Value::String(method))) => {
+            let params = request.remove("params").unwrap_or(Value::Object(Map::new()));
+            let result = call(collection, &method, params, changed);
+            Some(response(id?, result))
+        }
+        _ => {
+            let e = RpcError::new(INVALID_REQUEST, "expected jsonrpc \"2.0\" and a method name");
+            Some(response(id.unwrap_or(Value::Null), Err(e)))
+        }
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "error": { "code": e.code, "message": e.message },
+            "id": id,
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListParams {
+    sort: Option<String>,
+    #[serde(rename = "where")]
+    filter: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyParams {
+    key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchParams {
+    query: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AddParams {
+    name: String,
+    quantity: u32,
+    #[serde(default = "uncategorized")]
+    category: String,
+    #[serde(default)]
+    unit: Unit,
+    #[serde(default)]
+    unit_price: u64,
+}
+
+fn uncategorized() -> String {
+    String::from("uncategorized")
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateParams {
+    key: String,
+    quantity: u32,
+    unit_price: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoveParams {
+    key: String,
+    quantity: u32,
+    #[serde(default = "main_location")]
+    location: String,
+    unit: Option<Unit>,
+    reason: Option<Reason>,
+}
+
+fn main_location() -> String {
+    LocationId::main().0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AttributeParams {
+    key: String,
+    name: String,
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SummaryParams {
+    by: Option<String>,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+fn item_value(item: &Item) -> Result<Value, RpcError> {
+    serde_json::to_value(item).map_err(|e| RpcError::new(INTERNAL_ERROR, e))
+}
+
+fn items_value(items: &[&Item]) -> Result<Value, RpcError> {
+    serde_json::to_value(items).map_err(|e| RpcError::new(INTERNAL_ERROR, e))
+}
+
+// Sets `changed` when the method changed the collection.
+fn call(
+    collection: &mut Collection,
+    method: &str,
+    params_given: Value,
+    changed: &mut bool,
+) -> Result<Value, RpcError> {
+    let _call = tracing::info_span!("rpc", method).entered();
+    match method {
+        "list" => {
+            let ListParams { sort, filter } = params(params_given)?;
+            let order = match sort {
+                Some(sort) => SortOrder::from_str(&sort, true).map_err(|_| {
+                    RpcError::new(INVALID_PARAMS, format!("unknown sort order {:?}", sort))
+                })?,
+                None => SortOrder::Name,
+            };
+            let filter = filter.as_deref().map(Filter::parse).transpose()?;
+            let items: Vec<&Item> = collection
+                .sorted(order)
+                .into_iter()
+                .filter(|item| filter.as_ref().is_none_or(|filter| filter.matches(item)))
+                .collect();
+            items_value(&items)
+        }
+        "get" => {
+            let KeyParams { key } = params(params_given)?;
+            item_value(collection.lookup(&key)?)
+        }
+        "search" => {
+            let SearchParams { query } = params(params_given)?;
+            items_value(&collection.search(&query))
+        }
+        "add" => {
+            let AddParams { name, quantity, category, unit, unit_price } = params(params_given)?;
+            let sku = collection.add_item(name, quantity, unit, category, unit_price)?;
+            *changed = true;
+            item_value(collection.lookup(&sku)?)
+        }
+        "update" => {
+            let UpdateParams { key, quantity, unit_price } = params(params_given)?;
+            let sku = collection.resolve(&key)?;
+            collection.update_item(sku.to_string(), quantity, unit_price)?;
+            *changed = true;
+            item_value(collection.lookup(&sku)?)
+        }
+        "remove" => {
+            let KeyParams { key } = params(params_given)?;
+            let item = collection.remove_item(&key)?;
+            *changed = true;
+            item_value(&item)
+        }
+        "receive" | "consume" => {
+            let MoveParams { key, quantity, location, unit, reason } = params(params_given)?;
+            let sku = collection.resolve(&key)?;
+            let location = LocationId(location);
+            match (method, reason) {
+                ("receive", None) => collection.receive(&key, &location, quantity, unit)?,
+                ("receive", Some(_)) => {
+                    return Err(RpcError::new(INVALID_PARAMS, "receive takes no reason"));
+                }
+                (_, reason) => {
+                    let reason = reason.unwrap_or(Reason::Sold);
+                    collection.consume(&key, &location, quantity, unit, reason)?
+                }
+            }
+            *changed = true;
+            item_value(collection.lookup(&sku)?)
+        }
+        "set_attribute" | "unset_attribute" => {
+            let AttributeParams { key, name, value } = params(params_given)?;
+            let sku = collection.resolve(&key)?;
+            match (method, value) {
+                ("set_attribute", Some(value)) => collection.set_attribute(&key, &name, &value)?,
+                ("set_attribute", None) => {
+                    return Err(RpcError::new(INVALID_PARAMS, "set_attribute needs a value"));
+                }
+                (_, None) => {
+                    collection.unset_attribute(&key, &name)?;
+                }
+                (_, Some(_)) => {
+                    return Err(RpcError::new(INVALID_PARAMS, "unset_attribute takes no value"));
+                }
+            }
+            *changed = true;
+            item_value(collection.lookup(&sku)?)
+        }
+        "summary" => {
+            let SummaryParams { by } = params(params_given)?;
+            let by = by.as_deref().map_or(GroupBy::Category, GroupBy::named);
+            serde_json::to_value(collection.summarize(&by))
+                .map_err(|e| RpcError::new(INTERNAL_ERROR, e))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("there is no method {:?}", method))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer_to(line: &str) -> Option<Value> {
+        answer_line(&mut Collection::new(), line, &mut false)
+    }
+
+    #[test]
+    fn a_malformed_request_without_an_id_is_answered_with_id_null() {
+        let answer = answer_to(r#"{"method": "list"}"#).unwrap();
+        assert_eq!(answer["error"]["code"], INVALID_REQUEST);
+        assert_eq!(answer["id"], Value::Null);
+    }
+
+    #[test]
+    fn a_well_formed_notification_is_not_answered() {
+        assert_eq!(answer_to(r#"{"jsonrpc": "2.0", "method": "nope"}"#), None);
+        assert_eq!(answer_to(r#"[{"jsonrpc": "2.0", "method": "list"}]"#), None);
+    }
+
+    #[test]
+    fn a_request_is_answered_with_its_id() {
+        let answer = answer_to(r#"{"jsonrpc": "2.0", "method": "nope", "id": 3}"#).unwrap();
+        assert_eq!(answer["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(answer["id"], 3);
+    }
+}
@@ -0,0 +1,275 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use tokio::sync::{watch, Mutex, Notify, RwLock};
+
+use inventory_core::audit::AUDIT_LOG;
+use inventory_core::unit::Unit;
+use inventory_core::{Collection, InventoryError, Item};
+
+use crate::config::Config;
+use crate::store::{self, AsyncStorage};
+use crate::Storage;
+
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod metrics;
+
+use metrics::Action;
+
+// Everything the handlers share. The lock is tokio's, so a request waiting
+// for it lets the thread serve others meanwhile.
+struct Shared {
+    collection: RwLock<Collection>,
+    storage: Option<Storage>,
+    // Changes since the last save; the autosave task wakes on each one.
+    changes: AtomicUsize,
+    wake: Notify,
+    // Marked changed after every change, for anything watching the stock,
+    // and set to true when the server stops.
+    updates: watch::Sender<bool>,
+    saving: Mutex<()>,
+    // For /metrics.
+    actions: metrics::Counters,
+    // With autosave_seconds at 0 every change is saved before the response.
+    save_each_change: bool,
+}
+
+#[derive(Deserialize)]
+struct NewItem {
+    name: String,
+    quantity: u32,
+    #[serde(default = "uncategorized")]
+    category: String,
+    #[serde(default)]
+    unit: Unit,
+    #[serde(default)]
+    unit_price: u64,
+}
+
+#[derive(Deserialize)]
+struct ItemUpdate {
+    quantity: u32,
+    unit_price: Option<u64>,
+}
+
+fn uncategorized() -> String {
+    String::from("uncategorized")
+}
+
+// An error sent back as `{"error": "..."}` with a matching status code.
+struct ApiError(StatusCode, String);
+
+impl From<InventoryError> for ApiError {
+    fn from(e: InventoryError) -> Self {
+        let status = match e {
+            InventoryError::ItemNotFound { .. } => StatusCode::NOT_FOUND,
+            InventoryError::AmbiguousName { .. } => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+// Which API serve answers with.
+#[derive(Clone, Copy)]
+pub enum Api {
+    // JSON over plain HTTP routes under /items.
+    Rest,
+    // A GraphQL schema at /graphql.
+    #[cfg(feature = "graphql")]
+    Graphql,
+    // The service in proto/inventory.proto.
+    #[cfg(feature = "grpc")]
+    Grpc,
+}
+
+// Serves the collection over HTTP on localhost until Ctrl+C. Changes are
+// saved in the background, like in the menu, and once more on the way out.
+pub fn serve(
+    collection: Collection,
+    storage: Option<Storage>,
+    port: u16,
+    api: Api,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let shared = Arc::new(Shared {
+        collection: RwLock::new(collection),
+        storage,
+        changes: AtomicUsize::new(0),
+        wake: Notify::new(),
+        updates: watch::Sender::new(false),
+        saving: Mutex::new(()),
+        actions: metrics::Counters::default(),
+        save_each_change: config.autosave_seconds == 0,
+    });
+    let (app, path) = match api {
+        Api::Rest => {
+            let app = Router::new()
+                .route("/items", get(list_items).post(add_item))
+                .route("/items/{key}", get(get_item).put(update_item).delete(delete_item))
+                .with_state(Arc::clone(&shared));
+            (app, "")
+        }
+        #[cfg(feature = "graphql")]
+        Api::Graphql => {
+            (graphql::router(Arc::clone(&shared), config.low_stock_threshold), "/graphql")
+        }
+        #[cfg(feature = "grpc")]
+        Api::Grpc => (grpc::router(Arc::clone(&shared)), ""),
+    };
+    let app = app.merge(metrics::router(Arc::clone(&shared)));
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        if !shared.save_each_change {
+            let interval = Duration::from_secs(config.autosave_seconds);
+            let max_changes = config.autosave_changes.max(1);
+            tokio::spawn(Arc::clone(&shared).autosave(interval, max_changes));
+        }
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        println!("Serving the inventory on http://{}{}", addr, path);
+        let stopping = Arc::clone(&shared);
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = tokio::signal::ctrl_c().await;
+                // Streams still open would keep the server from stopping.
+                stopping.updates.send_replace(true);
+            })
+            .await?;
+        shared.flush().await;
+        Ok::<(), Box<dyn Error>>(())
+    })?;
+    Ok(())
+}
+
+impl Shared {
+    // Saves every `interval`, or sooner once `max_changes` changes pile up.
+    async fn autosave(self: Arc<Self>, interval: Duration, max_changes: usize) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = self.wake.notified() => {
+                    if self.changes.load(Ordering::SeqCst) < max_changes {
+                        continue;
+                    }
+                }
+            }
+            self.flush().await;
+        }
+    }
+
+    // Writes the collection if anything changed since the last write. The
+    // copy is taken inside `saving`, so writes land in the order the
+    // changes were made.
+    async fn flush(&self) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        let _saving = self.saving.lock().await;
+        let changes = self.changes.swap(0, Ordering::SeqCst);
+        if changes == 0 {
+            return;
+        }
+        let snapshot = self.collection.read().await.clone();
+        if let Err(e) = storage.save_async(snapshot).await {
+            tracing::error!(file = storage.path(), error = %e, "could not save");
+            // Try again next time round.
+            self.changes.fetch_add(changes, Ordering::SeqCst);
+        }
+    }
+
+    // Logs a change that was just made, given the lines from
+    // take_audit_lines, and has it saved.
+    async fn record(&self, action: Action, audit_lines: String) -> Result<(), ApiError> {
+        self.actions.count(action);
+        self.changes.fetch_add(1, Ordering::SeqCst);
+        self.updates.send_modify(|_| {});
+        if self.save_each_change {
+            self.flush().await;
+        } else {
+            self.wake.notify_one();
+        }
+        store::append_audit(path::Path::new(AUDIT_LOG), audit_lines).await.map_err(|e| {
+            ApiError(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("could not write {}: {}", AUDIT_LOG, e),
+            )
+        })
+    }
+}
+
+async fn list_items(State(shared): State<Arc<Shared>>) -> Json<Vec<Item>> {
+    let collection = shared.collection.read().await;
+    Json(collection.search("").into_iter().cloned().collect())
+}
+
+// `key` in the paths below is a SKU, or a name that only one item has.
+async fn get_item(
+    State(shared): State<Arc<Shared>>,
+    Path(key): Path<String>,
+) -> Result<Json<Item>, ApiError> {
+    let collection = shared.collection.read().await;
+    Ok(Json(collection.lookup(&key)?.clone()))
+}
+
+async fn add_item(
+    State(shared): State<Arc<Shared>>,
+    Json(new): Json<NewItem>,
+) -> Result<(StatusCode, Json<Item>), ApiError> {
+    let mut collection = shared.collection.write().await;
+    let sku =
+        collection.add_item(new.name, new.quantity, new.unit, new.category, new.unit_price)?;
+    let item = collection.lookup(&sku)?.clone();
+    let audit_lines = collection.take_audit_lines();
+    drop(collection);
+    shared.record(Action::Add, audit_lines).await?;
+    Ok((StatusCode::CREATED, Json(item)))
+}
+
+async fn update_item(
+    State(shared): State<Arc<Shared>>,
+    Path(key): Path<String>,
+    Json(update): Json<ItemUpdate>,
+) -> Result<Json<Item>, ApiError> {
+    let mut collection = shared.collection.write().await;
+    let sku = collection.resolve(&key)?;
+    collection.update_item(sku.to_string(), update.quantity, update.unit_price)?;
+    let item = collection.lookup(&sku)?.clone();
+    let audit_lines = collection.take_audit_lines();
+    drop(collection);
+    shared.record(Action::Update, audit_lines).await?;
+    Ok(Json(item))
+}
+
+async fn delete_item(
+    State(shared): State<Arc<Shared>>,
+    Path(key): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut collection = shared.collection.write().await;
+    collection.remove_item(&key)?;
+    let audit_lines = collection.take_audit_lines();
+    drop(collection);
+    shared.record(Action::Remove, audit_lines).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
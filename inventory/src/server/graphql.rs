@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{Context, EmptySubscription, Object, Result, Schema, SimpleObject};
+use axum::extract::State;
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use inventory_core::unit::Unit;
+use inventory_core::{Item, Operation};
+
+use super::{Action, ApiError, Shared};
+
+type InventorySchema = Schema<Query, Mutation, EmptySubscription>;
+
+// The GraphQL endpoint, and GraphiQL for trying queries out in a browser at
+// the same address.
+pub fn router(shared: Arc<Shared>, low_stock_threshold: u32) -> Router {
+    let schema = Schema::build(Query, Mutation, EmptySubscription)
+        .data(shared)
+        .data(LowStock(low_stock_threshold))
+        .finish();
+    Router::new().route("/graphql", get(graphiql).post(execute)).with_state(schema)
+}
+
+async fn execute(
+    State(schema): State<InventorySchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request).await)
+}
+
+async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+// The threshold lowStock uses when the query doesn't give one.
+struct LowStock(u32);
+
+fn shared<'a>(ctx: &Context<'a>) -> &'a Arc<Shared> {
+    ctx.data_unchecked::<Arc<Shared>>()
+}
+
+// An item as the schema shows it. Prices are in cents, like in the JSON API.
+struct ItemObject(Item);
+
+#[derive(SimpleObject)]
+struct Stock {
+    location: String,
+    quantity: u32,
+}
+
+#[derive(SimpleObject)]
+struct Attribute {
+    name: String,
+    value: String,
+}
+
+#[Object(name = "Item")]
+impl ItemObject {
+    async fn sku(&self) -> &str {
+        &self.0.sku
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn quantity(&self) -> u32 {
+        self.0.quantity
+    }
+
+    async fn reserved(&self) -> u32 {
+        self.0.reserved
+    }
+
+    async fn unit(&self) -> String {
+        self.0.unit.to_string()
+    }
+
+    async fn category(&self) -> &str {
+        &self.0.category
+    }
+
+    async fn unit_price(&self) -> u64 {
+        self.0.unit_price
+    }
+
+    // Sorted by location.
+    async fn locations(&self) -> Vec<Stock> {
+        let mut stock: Vec<Stock> = self
+            .0
+            .locations
+            .iter()
+            .map(|(location, &quantity)| Stock { location: location.0.to_string(), quantity })
+            .collect();
+        stock.sort_by(|a, b| a.location.cmp(&b.location));
+        stock
+    }
+
+    // Sorted by name.
+    async fn attributes(&self) -> Vec<Attribute> {
+        let attributes = self.0.attributes().into_iter();
+        attributes
+            .map(|(name, value)| Attribute { name: name.to_string(), value: value.to_string() })
+            .collect()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn expires_on(&self) -> Option<NaiveDate> {
+        self.0.expires_on
+    }
+}
+
+struct Query;
+
+#[Object]
+impl Query {
+    // Every item, by name.
+    async fn items(&self, ctx: &Context<'_>) -> Vec<ItemObject> {
+        let collection = shared(ctx).collection.read().await;
+        collection.search("").into_iter().cloned().map(ItemObject).collect()
+    }
+
+    // `sku` may also be a name that only one item has.
+    async fn item(&self, ctx: &Context<'_>, sku: String) -> Option<ItemObject> {
+        let collection = shared(ctx).collection.read().await;
+        collection.get(&sku).cloned().map(ItemObject)
+    }
+
+    // Items with `threshold` or fewer in stock, fewest first. The threshold
+    // defaults to low_stock_threshold from the configuration.
+    async fn low_stock(&self, ctx: &Context<'_>, threshold: Option<u32>) -> Vec<ItemObject> {
+        let threshold = threshold.unwrap_or(ctx.data_unchecked::<LowStock>().0);
+        let collection = shared(ctx).collection.read().await;
+        let mut items: Vec<&Item> = collection
+            .search("")
+            .into_iter()
+            .filter(|item| item.quantity <= threshold)
+            .collect();
+        items.sort_by_key(|item| item.quantity);
+        items.into_iter().cloned().map(ItemObject).collect()
+    }
+}
+
+struct Mutation;
+
+#[Object]
+impl Mutation {
+    // Adds an item under a new SKU. The unit is a name like pcs or kg.
+    async fn add_item(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        quantity: u32,
+        #[graphql(default = "uncategorized")] category: String,
+        unit: Option<String>,
+        #[graphql(default)] unit_price: u64,
+    ) -> Result<ItemObject> {
+        let unit = match unit {
+            Some(unit) => unit.parse::<Unit>()?,
+            None => Unit::default(),
+        };
+        let shared = shared(ctx);
+        let mut collection = shared.collection.write().await;
+        let sku = collection.add_item(name, quantity, unit, category, unit_price)?;
+        let item = collection.lookup(&sku)?.clone();
+        let audit_lines = collection.take_audit_lines();
+        drop(collection);
+        shared.record(Action::Add, audit_lines).await.map_err(message)?;
+        Ok(ItemObject(item))
+    }
+
+    // Changes the quantity of the item by `delta`, which may be negative.
+    async fn adjust(&self, ctx: &Context<'_>, sku: String, delta: i32) -> Result<ItemObject> {
+        let shared = shared(ctx);
+        let mut collection = shared.collection.write().await;
+        let sku = collection.resolve(&sku)?;
+        collection.apply(&Operation::Adjust { name: sku.to_string(), delta })?;
+        let item = collection.lookup(&sku)?.clone();
+        let audit_lines = collection.take_audit_lines();
+        drop(collection);
+        shared.record(Action::Adjust, audit_lines).await.map_err(message)?;
+        Ok(ItemObject(item))
+    }
+
+    // Removes the item, and returns it as it was.
+    async fn delete_item(&self, ctx: &Context<'_>, sku: String) -> Result<ItemObject> {
+        let shared = shared(ctx);
+        let mut collection = shared.collection.write().await;
+        let item = collection.remove_item(&sku)?;
+        let audit_lines = collection.take_audit_lines();
+        drop(collection);
+        shared.record(Action::Remove, audit_lines).await.map_err(message)?;
+        Ok(ItemObject(item))
+    }
+}
+
+// The change was made but couldn't be logged; the message says which.
+fn message(e: ApiError) -> String {
+    e.1
+}
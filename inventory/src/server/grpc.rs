@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use axum::Router;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::service::Routes;
+use tonic::{Request, Response, Status};
+
+use inventory_core::unit::Unit;
+use inventory_core::{Collection, InventoryError};
+
+use super::{Action, ApiError, Shared};
+
+use proto::inventory_server::{Inventory, InventoryServer};
+use proto::{
+    Item, ItemKey, ItemUpdate, ListItemsRequest, ListItemsResponse, NewItem, StockChange,
+    WatchStockRequest,
+};
+
+// The messages of proto/inventory.proto, and the service build.rs generates
+// for them.
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Item {
+        #[prost(string, tag = "1")]
+        pub sku: String,
+        #[prost(string, tag = "2")]
+        pub name: String,
+        #[prost(uint32, tag = "3")]
+        pub quantity: u32,
+        #[prost(uint32, tag = "4")]
+        pub reserved: u32,
+        #[prost(string, tag = "5")]
+        pub unit: String,
+        #[prost(string, tag = "6")]
+        pub category: String,
+        #[prost(uint64, tag = "7")]
+        pub unit_price: u64,
+        #[prost(string, tag = "8")]
+        pub updated_at: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ListItemsRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ListItemsResponse {
+        #[prost(message, repeated, tag = "1")]
+        pub items: Vec<Item>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ItemKey {
+        #[prost(string, tag = "1")]
+        pub key: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct NewItem {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(uint32, tag = "2")]
+        pub quantity: u32,
+        #[prost(string, tag = "3")]
+        pub category: String,
+        #[prost(string, tag = "4")]
+        pub unit: String,
+        #[prost(uint64, tag = "5")]
+        pub unit_price: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ItemUpdate {
+        #[prost(string, tag = "1")]
+        pub key: String,
+        #[prost(uint32, tag = "2")]
+        pub quantity: u32,
+        #[prost(uint64, optional, tag = "3")]
+        pub unit_price: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WatchStockRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StockChange {
+        #[prost(message, optional, tag = "1")]
+        pub item: Option<Item>,
+        #[prost(bool, tag = "2")]
+        pub removed: bool,
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/inventory.Inventory.rs"));
+}
+
+// How many changes are queued for a watcher that is slow to read them.
+const WATCH_BUFFER: usize = 64;
+
+pub fn router(shared: Arc<Shared>) -> Router {
+    Routes::new(InventoryServer::new(Service { shared })).into_axum_router()
+}
+
+struct Service {
+    shared: Arc<Shared>,
+}
+
+impl From<&inventory_core::Item> for Item {
+    fn from(item: &inventory_core::Item) -> Self {
+        Item {
+            sku: item.sku.to_string(),
+            name: item.name.to_string(),
+            quantity: item.quantity,
+            reserved: item.reserved,
+            unit: item.unit.to_string(),
+            category: item.category.to_string(),
+            unit_price: item.unit_price,
+            updated_at: item.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+fn status(e: InventoryError) -> Status {
+    match e {
+        InventoryError::ItemNotFound { .. } => Status::not_found(e.to_string()),
+        InventoryError::AmbiguousName { .. } => Status::failed_precondition(e.to_string()),
+        _ => Status::invalid_argument(e.to_string()),
+    }
+}
+
+// The change was made but couldn't be logged.
+fn internal(e: ApiError) -> Status {
+    Status::internal(e.1)
+}
+
+#[tonic::async_trait]
+impl Inventory for Service {
+    async fn list_items(
+        &self,
+        _request: Request<ListItemsRequest>,
+    ) -> Result<Response<ListItemsResponse>, Status> {
+        let collection = self.shared.collection.read().await;
+        let items = collection.search("").into_iter().map(Item::from).collect();
+        Ok(Response::new(ListItemsResponse { items }))
+    }
+
+    async fn get_item(&self, request: Request<ItemKey>) -> Result<Response<Item>, Status> {
+        let collection = self.shared.collection.read().await;
+        let item = collection.lookup(&request.into_inner().key).map_err(status)?;
+        Ok(Response::new(Item::from(item)))
+    }
+
+    async fn add_item(&self, request: Request<NewItem>) -> Result<Response<Item>, Status> {
+        let new = request.into_inner();
+        let unit = match new.unit.as_str() {
+            "" => Unit::default(),
+            unit => unit.parse::<Unit>().map_err(status)?,
+        };
+        let category = match new.category.as_str() {
+            "" => String::from("uncategorized"),
+            _ => new.category,
+        };
+        let quantity = new.quantity;
+
+        let mut collection = self.shared.collection.write().await;
+        let sku = collection
+            .add_item(new.name, quantity, unit, category, new.unit_price)
+            .map_err(status)?;
+        let item = Item::from(collection.lookup(&sku).map_err(status)?);
+        let audit_lines = collection.take_audit_lines();
+        drop(collection);
+        self.shared.record(Action::Add, audit_lines).await.map_err(internal)?;
+        Ok(Response::new(item))
+    }
+
+    async fn update_item(&self, request: Request<ItemUpdate>) -> Result<Response<Item>, Status> {
+        let update = request.into_inner();
+        let quantity = update.quantity;
+
+        let mut collection = self.shared.collection.write().await;
+        let sku = collection.resolve(&update.key).map_err(status)?;
+        collection.update_item(sku.to_string(), quantity, update.unit_price).map_err(status)?;
+        let item = Item::from(collection.lookup(&sku).map_err(status)?);
+        let audit_lines = collection.take_audit_lines();
+        drop(collection);
+        self.shared.record(Action::Update, audit_lines).await.map_err(internal)?;
+        Ok(Response::new(item))
+    }
+
+    async fn delete_item(&self, request: Request<ItemKey>) -> Result<Response<Item>, Status> {
+        let mut collection = self.shared.collection.write().await;
+        let item = collection.remove_item(&request.into_inner().key).map_err(status)?;
+        let audit_lines = collection.take_audit_lines();
+        drop(collection);
+        self.shared.record(Action::Remove, audit_lines).await.map_err(internal)?;
+        Ok(Response::new(Item::from(&item)))
+    }
+
+    type WatchStockStream = Pin<Box<dyn Stream<Item = Result<StockChange, Status>> + Send>>;
+
+    async fn watch_stock(
+        &self,
+        _request: Request<WatchStockRequest>,
+    ) -> Result<Response<Self::WatchStockStream>, Status> {
+        let (sender, receiver) = mpsc::channel(WATCH_BUFFER);
+        tokio::spawn(watch(Arc::clone(&self.shared), sender));
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+// Sends every item, then compares the collection with what was sent each
+// time it changes and sends the items whose stock differs. Stops when the
+// watcher goes away or the server stops.
+async fn watch(shared: Arc<Shared>, sender: mpsc::Sender<Result<StockChange, Status>>) {
+    let mut updates = shared.updates.subscribe();
+    let mut sent: HashMap<String, Item> = HashMap::new();
+    loop {
+        let collection = shared.collection.read().await;
+        let changes = changes(&collection, &mut sent);
+        drop(collection);
+        for change in changes {
+            if sender.send(Ok(change)).await.is_err() {
+                return;
+            }
+        }
+        let stopped = updates.changed().await.is_err() || *updates.borrow_and_update();
+        if stopped {
+            return;
+        }
+    }
+}
+
+// What changed since `sent`, which is brought up to date. Only changes to an
+// item's stock count; a new name or price waits for the next one.
+fn changes(collection: &Collection, sent: &mut HashMap<String, Item>) -> Vec<StockChange> {
+    let mut changes = Vec::new();
+    for item in collection.search("") {
+        let stock = (item.quantity, item.reserved);
+        let unchanged = sent.get(&item.sku).is_some_and(|old| {
+            (old.quantity, old.reserved) == stock
+        });
+        if !unchanged {
+            let item = Item::from(item);
+            sent.insert(item.sku.to_string(), item.clone());
+            changes.push(StockChange { item: Some(item), removed: false });
+        }
+    }
+    let mut removed: Vec<String> =
+        sent.keys().filter(|sku| collection.get(sku).is_none()).cloned().collect();
+    removed.sort();
+    for sku in removed {
+        let item = sent.remove(&sku);
+        changes.push(StockChange { item, removed: true });
+    }
+    changes
+}
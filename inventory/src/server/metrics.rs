@@ -0,0 +1,95 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+
+use super::Shared;
+
+// /metrics, in Prometheus' text format, whichever API the server answers
+// with: gauges for the stock as it is and counters for the changes made
+// through the server since it started.
+
+// A change the server can make, as it's counted.
+#[derive(Clone, Copy)]
+pub enum Action {
+    Add,
+    Update,
+    Adjust,
+    Remove,
+}
+
+impl Action {
+    const ALL: [Action; 4] = [Action::Add, Action::Update, Action::Adjust, Action::Remove];
+
+    fn name(self) -> &'static str {
+        match self {
+            Action::Add => "add",
+            Action::Update => "update",
+            Action::Adjust => "adjust",
+            Action::Remove => "remove",
+        }
+    }
+}
+
+// How many times each action was performed, indexed by Action.
+#[derive(Default)]
+pub struct Counters([AtomicU64; Action::ALL.len()]);
+
+impl Counters {
+    pub fn count(&self, action: Action) {
+        self.0[action as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub fn router(shared: Arc<Shared>) -> Router {
+    Router::new().route("/metrics", get(metrics)).with_state(shared)
+}
+
+async fn metrics(State(shared): State<Arc<Shared>>) -> impl IntoResponse {
+    let collection = shared.collection.read().await;
+    let items = collection.len();
+    let quantity: u64 = collection.iter().map(|item| u64::from(item.quantity)).sum();
+    // By the rule stock alerts follow, so the two agree.
+    let low = collection
+        .iter()
+        .filter(|item| {
+            collection.low_stock_threshold_of(item).is_some_and(|low| item.quantity <= low)
+        })
+        .count();
+    drop(collection);
+
+    let mut text = String::new();
+    gauge(&mut text, "inventory_items", "Items in the collection.", items as u64);
+    gauge(
+        &mut text,
+        "inventory_quantity",
+        "Stock across all items, added up whatever their unit.",
+        quantity,
+    );
+    gauge(
+        &mut text,
+        "inventory_low_stock_items",
+        "Items at or below their reorder level, or else low_stock_threshold.",
+        low as u64,
+    );
+    let _ = writeln!(text, "# HELP inventory_operations_total Changes made through the server.");
+    let _ = writeln!(text, "# TYPE inventory_operations_total counter");
+    for action in Action::ALL {
+        let count = shared.actions.0[action as usize].load(Ordering::Relaxed);
+        let name = action.name();
+        let _ = writeln!(text, "inventory_operations_total{{operation=\"{}\"}} {}", name, count);
+    }
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text)
+}
+
+// Writing to a String can't fail.
+fn gauge(text: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} gauge", name);
+    let _ = writeln!(text, "{} {}", name, value);
+}
@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::future::Future;
+use std::path::Path;
+
+use inventory_core::Collection;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::task;
+
+use crate::Storage;
+
+pub type StoreError = Box<dyn Error + Send + Sync>;
+
+// Storage for async code. The file work runs on tokio's blocking threads, so
+// the threads answering requests never wait on the disk.
+pub trait AsyncStorage {
+    // Takes the collection by value: a copy made under the lock, so the lock
+    // isn't held while the file is written.
+    fn save_async(
+        &self,
+        collection: Collection,
+    ) -> impl Future<Output = Result<(), StoreError>> + Send;
+}
+
+impl AsyncStorage for Storage {
+    fn save_async(
+        &self,
+        collection: Collection,
+    ) -> impl Future<Output = Result<(), StoreError>> + Send {
+        let storage = self.clone();
+        async move {
+            let saved =
+                task::spawn_blocking(move || storage.save(&collection).map_err(|e| e.to_string()));
+            Ok(saved.await??)
+        }
+    }
+}
+
+// Appends lines from Collection::take_audit_lines to the audit log.
+pub async fn append_audit(path: &Path, lines: String) -> std::io::Result<()> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path).await?;
+    file.write_all(lines.as_bytes()).await?;
+    // Tokio's write may still be under way on another thread until flushed.
+    file.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use inventory_core::unit::Unit;
+
+    use super::*;
+    use crate::Format;
+
+    // A storage file of its own for each test, removed before it starts.
+    fn storage(format: Format, name: &str) -> (Storage, PathBuf) {
+        let path = std::env::temp_dir().join(format!("inventory-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        let storage = Storage {
+            format,
+            path: path.display().to_string(),
+            backups: 0,
+            compact_after: 100,
+            #[cfg(feature = "encryption")]
+            secret: None,
+        };
+        (storage, path)
+    }
+
+    fn apples(quantity: u32) -> Collection {
+        let mut collection = Collection::new();
+        let (name, category) = (String::from("apple"), String::from("fruit"));
+        collection.add_item(name, quantity, Unit::default(), category, 25).unwrap();
+        collection
+    }
+
+    #[tokio::test]
+    async fn what_is_saved_loads_back() {
+        for (format, name) in [(Format::Json, "saved.json"), (Format::Events, "saved.log")] {
+            let (storage, path) = storage(format, name);
+            storage.save_async(apples(3)).await.unwrap();
+            let loaded = storage.load().unwrap().unwrap();
+            let apple = loaded.lookup("apple").unwrap();
+            assert_eq!((apple.quantity, apple.unit_price), (3, 25), "{}", name);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn a_later_save_replaces_the_file() {
+        let (storage, path) = storage(Format::Json, "resaved.json");
+        storage.save_async(apples(3)).await.unwrap();
+        storage.save_async(apples(9)).await.unwrap();
+        let loaded = storage.load().unwrap().unwrap();
+        assert_eq!((loaded.len(), loaded.lookup("apple").unwrap().quantity), (1, 9));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_save_that_fails_says_so() {
+        let (storage, _) = storage(Format::Json, "missing/saved.json");
+        assert!(storage.save_async(apples(3)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn audit_lines_are_appended() {
+        let path = std::env::temp_dir().join(format!("inventory-{}-audit.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        append_audit(&path, String::from("one\n")).await.unwrap();
+        append_audit(&path, String::new()).await.unwrap();
+        append_audit(&path, String::from("two\n")).await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use inventory_core::audit::AUDIT_LOG;
+use inventory_core::unit::Unit;
+use inventory_core::{format_price, Collection};
+
+use crate::{parse_quantity, Storage};
+
+// What the keyboard is currently typing into.
+enum Mode {
+    Browse,
+    Search,
+    Add,
+    Update,
+}
+
+struct App<'a> {
+    collection: &'a mut Collection,
+    storage: Option<&'a Storage>,
+    mode: Mode,
+    query: String,
+    input: String,
+    message: String,
+    table: TableState,
+}
+
+// Runs the full-screen interface until the user quits. The terminal is put
+// back the way it was even when drawing fails.
+pub fn run(collection: &mut Collection, storage: Option<&Storage>) -> Result<(), Box<dyn Error>> {
+    let message = crate::expired_warning(collection).unwrap_or_default();
+    let mut app = App {
+        collection,
+        storage,
+        mode: Mode::Browse,
+        query: String::new(),
+        input: String::new(),
+        message,
+        table: TableState::default().with_selected(Some(0)),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    Ok(result?)
+}
+
+impl App<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+                if let Err(e) = self.collection.write_audit(Path::new(AUDIT_LOG)) {
+                    self.message = format!("could not write {}: {}", AUDIT_LOG, e);
+                }
+            }
+        }
+    }
+
+    // SKUs of the items matching the search, in the order they're shown.
+    fn visible(&self) -> Vec<String> {
+        self.collection.search(&self.query).into_iter().map(|item| item.sku.to_string()).collect()
+    }
+
+    fn selected(&self) -> Option<String> {
+        self.table.selected().and_then(|i| self.visible().into_iter().nth(i))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search_area, table_area, status_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+                .areas(frame.area());
+
+        let search_title = match self.mode {
+            Mode::Search => "Search (Enter to keep, Esc to clear)",
+            _ => "Search (/)",
+        };
+        frame.render_widget(
+            Paragraph::new(self.query.as_str()).block(Block::bordered().title(search_title)),
+            search_area,
+        );
+
+        let rows: Vec<Row> = self
+            .collection
+            .search(&self.query)
+            .into_iter()
+            .map(|item| {
+                Row::new(vec![
+                    item.sku.to_string(),
+                    item.name.to_string(),
+                    item.quantity.to_string(),
+                    item.category.to_string(),
+                    format_price(item.unit_price),
+                    self.collection.format_time(item.updated_at),
+                ])
+            })
+            .collect();
+        let count = rows.len();
+        let widths = [
+            Constraint::Length(10),
+            Constraint::Min(20),
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Length(10),
+            Constraint::Length(16),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(vec!["SKU", "Name", "Quantity", "Category", "Unit price", "Updated"])
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::bordered().title(format!("Items ({})", count)))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let (title, text) = match self.mode {
+            Mode::Add => ("Add <name> <quantity> (Enter to add, Esc to cancel)", &self.input),
+            Mode::Update => ("New quantity (Enter to update, Esc to cancel)", &self.input),
+            _ => (
+                "a add  u update  d delete  / search  s save  q quit",
+                &self.message,
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(text.as_str()).block(Block::bordered().title(title)),
+            status_area,
+        );
+    }
+
+    // Returns false when the user asked to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match self.mode {
+            Mode::Browse => return self.browse_key(key.code),
+            Mode::Search => match key.code {
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Char(c) => self.query.push(c),
+                _ => {}
+            },
+            Mode::Add | Mode::Update => match key.code {
+                KeyCode::Enter => {
+                    self.submit();
+                    self.input.clear();
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Esc => {
+                    self.input.clear();
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Char(c) => self.input.push(c),
+                _ => {}
+            },
+        }
+        self.clamp_selection();
+        true
+    }
+
+    fn browse_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::PageDown => self.table.scroll_down_by(10),
+            KeyCode::PageUp => self.table.scroll_up_by(10),
+            KeyCode::Home => self.table.select_first(),
+            KeyCode::End => self.table.select_last(),
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('a') => self.mode = Mode::Add,
+            KeyCode::Char('u') if self.selected().is_some() => self.mode = Mode::Update,
+            KeyCode::Char('d') => {
+                if let Some(sku) = self.selected() {
+                    self.message = match self.collection.remove_item(&sku) {
+                        Ok(item) => format!("Removed {} ({})", item.name, sku),
+                        Err(e) => e.to_string(),
+                    };
+                }
+            }
+            KeyCode::Char('s') => {
+                self.message = match self.storage {
+                    Some(storage) => match storage.save(self.collection) {
+                        Ok(()) => format!("Saved to {}", storage.path()),
+                        Err(e) => format!("could not save {}: {}", storage.path(), e),
+                    },
+                    None => String::from("no storage is available in this build"),
+                }
+            }
+            _ => {}
+        }
+        self.clamp_selection();
+        true
+    }
+
+    // Applies what was typed in the add or update box.
+    fn submit(&mut self) {
+        let input = self.input.trim();
+        let result = match self.mode {
+            Mode::Add => match input.rsplit_once(' ') {
+                Some((name, qty)) => match parse_quantity(qty) {
+                    Ok(quantity) => {
+                        let name = name.trim().to_string();
+                        let category = String::from("uncategorized");
+                        self.collection
+                            .add_item(name.to_string(), quantity, Unit::Pieces, category, 0)
+                            .map(|sku| format!("Added {} as {}", name, sku))
+                    }
+                    Err(problem) => Ok(problem),
+                },
+                None => Ok(String::from("expected <name> <quantity>")),
+            },
+            Mode::Update => match (self.selected(), parse_quantity(input)) {
+                (Some(sku), Ok(quantity)) => self
+                    .collection
+                    .update_item(sku.to_string(), quantity, None)
+                    .map(|()| format!("Updated {} to {}", sku, quantity)),
+                (None, _) => Ok(String::from("no item is selected")),
+                (_, Err(problem)) => Ok(problem),
+            },
+            _ => return,
+        };
+        self.message = match result {
+            Ok(message) => message,
+            Err(e) => e.to_string(),
+        };
+    }
+
+    // Keeps the highlighted row inside the list after it shrinks.
+    fn clamp_selection(&mut self) {
+        let len = self.visible().len();
+        match self.table.selected() {
+            _ if len == 0 => self.table.select(None),
+            Some(i) if i >= len => self.table.select(Some(len - 1)),
+            None => self.table.select(Some(0)),
+            _ => {}
+        }
+    }
+}
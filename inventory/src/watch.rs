@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use notify::{RecursiveMode, Watcher};
+
+use crate::config::Config;
+use crate::Storage;
+
+// Shows the collection as a table and draws it again each time the storage
+// file changes, e.g. from a server or another inventory run alongside. It
+// runs until interrupted.
+pub fn run(storage: &Storage, config: &Config, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    // Saves may replace the file rather than write to it, which would end a
+    // watch on the file itself, so its folder is watched instead.
+    let file = Path::new(storage.path());
+    let folder = file.parent().filter(|folder| !folder.as_os_str().is_empty());
+    watcher.watch(folder.unwrap_or(Path::new(".")), RecursiveMode::NonRecursive)?;
+
+    loop {
+        draw(storage, config);
+        loop {
+            let event = receiver.recv()??;
+            let ours = event.paths.iter().any(|path| path.file_name() == file.file_name());
+            if ours && !event.kind.is_access() {
+                break;
+            }
+        }
+        // Whatever else changes meanwhile is in the next drawing anyway.
+        thread::sleep(interval);
+        while receiver.try_recv().is_ok() {}
+    }
+}
+
+// A file caught halfway through a save may not load; the next change to it
+// draws it again.
+fn draw(storage: &Storage, config: &Config) {
+    if io::stdout().is_terminal() {
+        // Clears the screen and goes back to the top.
+        print!("\x1b[2J\x1b[H");
+    }
+    println!("{} at {}, watching for changes", storage.path(), Local::now().format("%H:%M:%S"));
+    match storage.load() {
+        Ok(Some(mut collection)) => {
+            collection.set_date_format(config.date_format.clone());
+            print!("{}", collection.to_table(config.sort, config.highlight()));
+        }
+        Ok(None) => println!("(nothing saved yet)"),
+        Err(e) => println!("could not load {}: {}", storage.path(), e),
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use inventory_core::alerts::StockEvent;
+use inventory_core::Collection;
+use serde::Serialize;
+use ureq::Agent;
+
+use crate::config::Config;
+
+// Posts each stock event as JSON to every URL in the webhooks setting, like
+//
+//     {"at": "2024-05-01T09:30:00Z", "event": "low_stock", "sku": "SKU-0003",
+//      "name": "apple", "quantity": 4, "threshold": 5}
+//
+// Each URL has a thread of its own, so changes don't wait for the posts and a
+// URL that is down doesn't hold up the others. A post that fails is tried
+// again after 1, 2, 4 and 8 seconds, unless the answer was a client error,
+// and then given up on with an error in the log.
+
+const ATTEMPTS: u32 = 5;
+const FIRST_DELAY: Duration = Duration::from_secs(1);
+// For each attempt, from connecting to the end of the answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a StockEvent,
+}
+
+// The posting threads. Dropping this waits for them to post what they were
+// given, so a run doesn't end with events unsent.
+pub struct Webhooks {
+    senders: Arc<Mutex<Vec<Sender<String>>>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+// Subscribes to the collection's stock events, when there are webhooks set.
+pub fn start(collection: &mut Collection, config: &Config) -> Option<Webhooks> {
+    if config.webhooks.is_empty() {
+        return None;
+    }
+    let mut senders = Vec::new();
+    let mut threads = Vec::new();
+    for url in &config.webhooks {
+        let (sender, receiver) = mpsc::channel::<String>();
+        let url = url.clone();
+        senders.push(sender);
+        threads.push(thread::spawn(move || {
+            let agent: Agent = Agent::config_builder().timeout_global(Some(TIMEOUT)).build().into();
+            for body in receiver {
+                post(&agent, &url, &body);
+            }
+        }));
+    }
+
+    let senders = Arc::new(Mutex::new(senders));
+    let listening = Arc::clone(&senders);
+    collection.subscribe(move |event| {
+        let payload = Payload { at: Utc::now(), event };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => return tracing::error!(error = %e, "could not write a stock event as JSON"),
+        };
+        // Once dropping has begun there is no one left to post it.
+        for sender in listening.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = sender.send(body.clone());
+        }
+    });
+    Some(Webhooks { senders, threads })
+}
+
+fn post(agent: &Agent, url: &str, body: &str) {
+    let mut delay = FIRST_DELAY;
+    for attempt in 1..=ATTEMPTS {
+        let result = agent.post(url).header("Content-Type", "application/json").send(body);
+        match result {
+            Ok(_) => return,
+            Err(ureq::Error::StatusCode(code)) if (400..500).contains(&code) => {
+                return tracing::warn!(url, status = code, "a webhook refused a stock event");
+            }
+            Err(e) if attempt == ATTEMPTS => {
+                return tracing::error!(url, error = %e, "gave up posting a stock event");
+            }
+            Err(_) => {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+        }
+    }
+}
+
+impl Drop for Webhooks {
+    fn drop(&mut self) {
+        // The threads stop once their channels are empty and closed.
+        self.senders.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+use std::hash::Hash;
 use std::mem;
 
-fn analyze_slice(slice: &[i32]) {
-    println!("First element of slice is {}", slice[0]);
+fn analyze_slice<T: std::fmt::Display + Copy>(slice: &[T]) {
+    match slice.first() {
+        Some(first) => println!("First element of slice is {}", first),
+        None => println!("empty slice"),
+    }
     println!("Length of the slice is {}", slice.len());
 }
 
@@ -11,6 +16,67 @@ fn print(n: &[f64]){
     }
 }
 
+// Calls `f` on consecutive chunks of `chunk_size` elements. The last chunk
+// is shorter when the length isn't a multiple of `chunk_size`.
+fn process_chunks<T, F: FnMut(&[T])>(slice: &[T], chunk_size: usize, mut f: F) {
+    if chunk_size == 0 {
+        return;
+    }
+    for chunk in slice.chunks(chunk_size) {
+        f(chunk);
+    }
+}
+
+// Like `.get`, but the error says which index missed and how long the slice was.
+fn safe_get<T>(slice: &[T], i: usize) -> Result<&T, String> {
+    slice
+        .get(i)
+        .ok_or_else(|| format!("index {} out of bounds for slice of length {}", i, slice.len()))
+}
+
+// Tallies how many times each distinct element appears in the slice.
+fn count_occurrences<T: Eq + Hash + Clone>(slice: &[T]) -> HashMap<T, usize> {
+    let mut counts = HashMap::new();
+    for elt in slice {
+        *counts.entry(elt.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// Returns the indices of the smallest and largest values, taking the first
+// one on ties. NaN never compares as smaller or larger, so it is skipped; a
+// slice that is empty or all NaN gives None.
+fn argmin_argmax(slice: &[f64]) -> Option<(usize, usize)> {
+    let mut result: Option<(usize, usize)> = None;
+    for (i, &x) in slice.iter().enumerate() {
+        if x.is_nan() {
+            continue;
+        }
+        result = match result {
+            None => Some((i, i)),
+            Some((min, max)) => Some((
+                if x < slice[min] { i } else { min },
+                if x > slice[max] { i } else { max },
+            )),
+        };
+    }
+    result
+}
+
+// Element i of the result is the sum of slice[0..=i]. Overflow is reported
+// as an error instead of wrapping.
+fn prefix_sums(slice: &[i64]) -> Result<Vec<i64>, String> {
+    let mut sums = Vec::with_capacity(slice.len());
+    let mut total: i64 = 0;
+    for (i, &x) in slice.iter().enumerate() {
+        total = total
+            .checked_add(x)
+            .ok_or_else(|| format!("prefix sum overflows at index {}", i))?;
+        sums.push(total);
+    }
+    Ok(sums)
+}
+
 fn main() {
     let xs: [i32; 5] = [1, 2, 3, 4, 5];
 
@@ -51,6 +117,17 @@ fn main() {
         }
     }
 
+    // safe_get wraps `.get` and turns the `None` into a message with context.
+    assert_eq!(safe_get(&xs, 2), Ok(&3));
+    match safe_get(&xs, 5) {
+        Ok(xval) => println!("{}", xval),
+        Err(e) => println!("{}", e),
+    }
+    assert_eq!(
+        safe_get(&xs, 5),
+        Err(String::from("index 5 out of bounds for slice of length 5"))
+    );
+
     // Out of bound indexing on array causes compile time error.
     //println!("{}", xs[5]);
     // Out of bound indexing on slice causes runtime error.
@@ -71,6 +148,40 @@ fn main() {
     print(sv); // works on vectors
     print(av); // works on arrays
 
+    // argmin_argmax gives back positions rather than the values themselves.
+    assert_eq!(argmin_argmax(sv), Some((0, 2)));
+    assert_eq!(argmin_argmax(av), Some((2, 0)));
+    assert_eq!(argmin_argmax(&[2.0, 1.0, 3.0, 1.0]), Some((1, 2)));
+    assert_eq!(argmin_argmax(&[f64::NAN, 4.0]), Some((1, 1)));
+    assert_eq!(argmin_argmax(&[]), None);
+
+    // analyze_slice is generic, so the same function works on the f64 slices
+    // and on an empty slice without panicking on slice[0].
+    analyze_slice(sv);
+    analyze_slice(&empty_array);
+
+    // Buffers are often processed in fixed-size blocks. `chunks` hands out
+    // sub-slices of the original, so nothing is copied.
+    let data = [1, 2, 3, 4, 5, 6, 7];
+    let mut chunk_lens = Vec::new();
+    process_chunks(&data, 3, |chunk| chunk_lens.push(chunk.len()));
+    assert_eq!(chunk_lens, [3, 3, 1]);
+    println!("Chunk lengths {:?}", chunk_lens);
+
+    let counts = count_occurrences(&[3, 1, 3, 2, 3, 1]);
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[&3], 3);
+    assert_eq!(counts[&1], 2);
+    assert_eq!(counts[&2], 1);
+    assert!(count_occurrences::<i32>(&[]).is_empty());
+
+    assert_eq!(prefix_sums(&[1, 2, 3, 4]), Ok(vec![1, 3, 6, 10]));
+    assert_eq!(prefix_sums(&[]), Ok(vec![]));
+    assert_eq!(
+        prefix_sums(&[i64::MAX, 1]),
+        Err(String::from("prefix sum overflows at index 1"))
+    );
+
     // In fact, many methods you might think of as belonging to vectors or arrays are methods defined on slices: for example, the sort and reverse methods, which sort or reverse a sequence of elements in place, are actually methods on the slice type [T].
 
     //Since slices almost always appear behind references, we often just refer to types like &[T] or &str as “slices,” using the shorter name for the more common concept.